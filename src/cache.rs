@@ -1,60 +1,403 @@
 use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
     env,
     ffi::OsStr,
     fs::{self, File},
-    io::{BufReader, Cursor, Read},
-    path::{Path, PathBuf},
+    io::{self, BufReader, Cursor, Read, Seek, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+    thread,
     time::{Duration, SystemTime},
 };
 
-use anyhow::{ensure, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use app_dirs::{get_app_root, AppDataType};
-use log::debug;
-use reqwest::{blocking::Client, Proxy};
+use log::{debug, warn};
+use reqwest::{
+    blocking::{Client, Response},
+    Proxy, StatusCode, Url,
+};
 use walkdir::{DirEntry, WalkDir};
 use zip::ZipArchive;
 
-use crate::types::{PathSource, PlatformType};
+#[cfg(feature = "page-index")]
+use once_cell::unsync::OnceCell;
+
+#[cfg(feature = "page-index")]
+use crate::index::PageIndex;
+use crate::{
+    line_iterator::LineIterator,
+    render_cache::RENDER_CACHE_DIR,
+    types::{LineType, PathSource, PlatformType},
+};
 
 static CACHE_DIR_ENV_VAR: &str = "TEALDEER_CACHE_DIR";
+static OFFLINE_ENV_VAR: &str = "TEALDEER_OFFLINE";
+static IMMUTABLE_ENV_VAR: &str = "TEALDEER_CACHE_IMMUTABLE";
+
+/// The default limit for the size of downloaded archives (256 MiB).
+pub const DEFAULT_MAX_ARCHIVE_SIZE: u64 = 256 * 1024 * 1024;
+
+/// How long to wait for a connection when probing whether we are online.
+const CONNECTIVITY_PROBE_TIMEOUT: Duration = Duration::from_secs(1);
 
 pub static TLDR_PAGES_DIR: &str = "tldr-pages";
 static TLDR_OLD_PAGES_DIR: &str = "tldr-master";
+static TLDR_PAGES_TMP_DIR: &str = "tldr-pages.tmp";
+/// File that is briefly created to check whether the cache directory is writable.
+static WRITE_PROBE_FILE: &str = ".tealdeer-write-probe";
+static PAGES_RAW_URL: &str = "https://raw.githubusercontent.com/tldr-pages/tldr/main";
+static PAGES_GITHUB_URL: &str = "https://github.com/tldr-pages/tldr/blob/main";
+
+/// File in the pages directory that contains the version of the cache contents.
+static VERSION_FILE: &str = "VERSION";
+/// File in the pages directory that contains the pinned upstream ref the
+/// cache was downloaded for, if any.
+static ARCHIVE_REF_FILE: &str = "REF";
+/// File in a delta archive that describes the changes to apply.
+static DELTA_MANIFEST_FILE: &str = "MANIFEST";
+/// A well-known page that is used to verify the cache after an update.
+static VERIFICATION_PAGE: &str = "tar";
 
 #[derive(Debug)]
 pub struct Cache {
     url: String,
+    mirror_urls: Vec<String>,
+    keep_archive: Option<PathBuf>,
+    max_archive_size: u64,
+    dir_mode: Option<u32>,
+    pages_subdir: Option<PathBuf>,
+    update_languages: Option<Vec<String>>,
+    archive_ref: Option<String>,
+    sources: Vec<(String, PathBuf)>,
+    delta_url: Option<String>,
     platform: PlatformType,
+    platform_fallback: Option<Vec<String>>,
+    merge_common: bool,
+    fill_from_english: bool,
+    case_insensitive: bool,
+    normalize_separators: bool,
+    skip_broken_symlinks: bool,
+    /// The page index of the cache, loaded on first use
+    #[cfg(feature = "page-index")]
+    index: OnceCell<Option<PageIndex>>,
+}
+
+/// Details about a successful cache update.
+#[derive(Debug, Default)]
+pub struct UpdateSummary {
+    /// The URL of the mirror the archive was downloaded from, if it was not
+    /// the primary URL.
+    pub mirror_url: Option<String>,
+    /// The path the downloaded archive was saved to, if requested.
+    pub kept_archive: Option<PathBuf>,
+}
+
+/// The manifest of a delta archive.
+///
+/// A delta archive is a ZIP archive that contains all added or changed files
+/// (at the same paths as in the full archive), along with a `MANIFEST` file:
+///
+/// ```text
+/// base <version the delta applies to>
+/// version <version after applying the delta>
+/// remove <path of a removed file>
+/// ```
+#[derive(Debug, PartialEq, Eq)]
+struct DeltaManifest {
+    base: String,
+    version: String,
+    removed: Vec<PathBuf>,
+}
+
+impl DeltaManifest {
+    fn parse(contents: &str) -> Result<Self> {
+        let mut base = None;
+        let mut version = None;
+        let mut removed = vec![];
+        for line in contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+        {
+            let (key, value) = line
+                .split_once(' ')
+                .with_context(|| format!("Invalid line in delta manifest: {line:?}"))?;
+            let value = value.trim();
+            match key {
+                "base" => base = Some(value.to_string()),
+                "version" => version = Some(value.to_string()),
+                "remove" => {
+                    let path = PathBuf::from(value);
+                    ensure!(
+                        path.components().all(|c| matches!(c, Component::Normal(_))),
+                        "Invalid path in delta manifest: {value:?}"
+                    );
+                    removed.push(path);
+                }
+                _ => bail!("Unknown key in delta manifest: {key:?}"),
+            }
+        }
+        Ok(Self {
+            base: base.context("Delta manifest does not specify a base version")?,
+            version: version.context("Delta manifest does not specify a version")?,
+            removed,
+        })
+    }
+}
+
+/// Return the `file://` URL of an absolute path. Characters that are not
+/// allowed in URLs are percent-encoded.
+fn file_url(path: &Path) -> String {
+    use std::fmt::Write as _;
+
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut url = String::from(if path.starts_with('/') {
+        "file://"
+    } else {
+        "file:///"
+    });
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' | b':' => {
+                url.push(char::from(byte));
+            }
+            byte => {
+                let _ = write!(url, "%{byte:02X}");
+            }
+        }
+    }
+    url
+}
+
+/// Where a page was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageSource {
+    /// A page in the pages cache
+    Cache,
+    /// A custom page in the custom pages directory
+    Custom,
+    /// A page that was downloaded on demand (see `Cache::fetch_page`)
+    Fetched,
+    /// A file that was passed in explicitly
+    File,
+    /// A page that is built into tealdeer (see `--demo`), with its contents
+    Embedded(&'static str),
+}
+
+/// A page that matches a lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageCandidate {
+    pub path: PathBuf,
+    /// The platform directory of the page (e.g. `linux` or `common`)
+    pub platform: Option<String>,
+    /// The language of the page (e.g. `en`)
+    pub language: Option<String>,
+    /// The name of the page source (see `Cache::with_sources`), if the page
+    /// is from one
+    pub source_name: Option<String>,
+}
+
+impl PageCandidate {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            platform: None,
+            language: None,
+            source_name: None,
+        }
+    }
+
+    pub fn with_platform(mut self, platform: impl Into<String>) -> Self {
+        self.platform = Some(platform.into());
+        self
+    }
+
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    pub fn with_source_name(mut self, source_name: impl Into<String>) -> Self {
+        self.source_name = Some(source_name.into());
+        self
+    }
 }
 
+/// The result of a page lookup.
+///
+/// Besides the page that will be rendered (the primary candidate), this
+/// contains all other candidates that matched the lookup, in order of
+/// preference.
 #[derive(Debug)]
 pub struct PageLookupResult {
-    pub page_path: PathBuf,
-    pub patch_path: Option<PathBuf>,
+    candidates: Vec<PageCandidate>,
+    patch_path: Option<PathBuf>,
+    merge_path: Option<PathBuf>,
+    english_path: Option<PathBuf>,
+    target_platform: Option<PlatformType>,
+    source: PageSource,
 }
 
 impl PageLookupResult {
+    /// Create a lookup result for the page file at `page_path`.
     pub fn with_page(page_path: PathBuf) -> Self {
+        Self::with_candidate(PageCandidate::new(page_path))
+    }
+
+    pub fn with_candidate(candidate: PageCandidate) -> Self {
         Self {
-            page_path,
+            candidates: vec![candidate],
             patch_path: None,
+            merge_path: None,
+            english_path: None,
+            target_platform: None,
+            source: PageSource::File,
         }
     }
 
+    /// Add candidates with a lower preference than the existing ones.
+    pub fn with_candidates(mut self, candidates: impl IntoIterator<Item = PageCandidate>) -> Self {
+        self.candidates.extend(candidates);
+        self
+    }
+
     pub fn with_optional_patch(mut self, patch_path: Option<PathBuf>) -> Self {
         self.patch_path = patch_path;
         self
     }
 
+    pub fn with_optional_merge(mut self, merge_path: Option<PathBuf>) -> Self {
+        self.merge_path = merge_path;
+        self
+    }
+
+    pub fn with_optional_english(mut self, english_path: Option<PathBuf>) -> Self {
+        self.english_path = english_path;
+        self
+    }
+
+    /// Set the platform the page is shown for. Examples that are tagged for
+    /// other platforms are not rendered.
+    pub fn with_target_platform(mut self, platform: Option<PlatformType>) -> Self {
+        self.target_platform = platform;
+        self
+    }
+
+    pub fn with_source(mut self, source: PageSource) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// The path of the page that will be rendered.
+    pub fn primary_path(&self) -> &Path {
+        &self.candidates[0].path
+    }
+
+    pub fn patch_path(&self) -> Option<&Path> {
+        self.patch_path.as_deref()
+    }
+
+    /// The path of a page whose examples are merged with the primary page
+    /// when rendering (the `common` page, if `directories.merge_common` is
+    /// enabled).
+    pub fn merge_path(&self) -> Option<&Path> {
+        self.merge_path.as_deref()
+    }
+
+    /// The path of the English page whose examples are appended to the
+    /// primary (translated) page when rendering, unless the translation
+    /// already has an example with the same commands (if
+    /// `display.fill_from_english` is enabled).
+    pub fn english_path(&self) -> Option<&Path> {
+        self.english_path.as_deref()
+    }
+
+    /// All pages that matched the lookup, the primary one first.
+    pub fn candidates(&self) -> &[PageCandidate] {
+        &self.candidates
+    }
+
+    /// The platforms of all candidates, without duplicates.
+    pub fn platforms(&self) -> Vec<&str> {
+        let mut platforms: Vec<&str> = vec![];
+        for platform in self.candidates.iter().filter_map(|c| c.platform.as_deref()) {
+            if !platforms.contains(&platform) {
+                platforms.push(platform);
+            }
+        }
+        platforms
+    }
+
+    /// The languages of all candidates, without duplicates.
+    pub fn languages(&self) -> Vec<&str> {
+        let mut languages: Vec<&str> = vec![];
+        for language in self.candidates.iter().filter_map(|c| c.language.as_deref()) {
+            if !languages.contains(&language) {
+                languages.push(language);
+            }
+        }
+        languages
+    }
+
+    pub fn target_platform(&self) -> Option<PlatformType> {
+        self.target_platform
+    }
+
+    pub fn source(&self) -> PageSource {
+        self.source
+    }
+
+    /// The name of the page source the page was found in, if any.
+    pub fn source_name(&self) -> Option<&str> {
+        self.candidates[0].source_name.as_deref()
+    }
+
+    /// The URL of the primary page's source: the page on GitHub for pages
+    /// from the cache, and a `file://` URL for custom pages and local files.
+    pub fn source_url(&self) -> Option<String> {
+        let primary = &self.candidates[0];
+        match self.source {
+            PageSource::Cache | PageSource::Fetched => {
+                let language = primary.language.as_deref()?;
+                let platform_dir = primary.platform.as_deref()?;
+                let page_filename = primary.path.file_name()?.to_str()?;
+                let lang_dir = Cache::language_dirs(&[language.to_string()]).remove(0);
+                Some(Cache::page_url_with_base(
+                    PAGES_GITHUB_URL,
+                    &lang_dir,
+                    platform_dir,
+                    page_filename,
+                ))
+            }
+            PageSource::Custom | PageSource::File => {
+                let path = primary.path.canonicalize().ok()?;
+                Some(file_url(&path))
+            }
+            PageSource::Embedded(_) => None,
+        }
+    }
+
     /// Create a buffered reader that sequentially reads from the page and the
     /// patch, as if they were concatenated.
     ///
     /// This will return an error if either the page file or the patch file
     /// cannot be opened.
     pub fn reader(&self) -> Result<BufReader<Box<dyn Read>>> {
+        if let PageSource::Embedded(contents) = self.source {
+            return Ok(BufReader::new(Box::new(contents.as_bytes())));
+        }
+
         // Open page file
-        let page_file = File::open(&self.page_path)
-            .with_context(|| format!("Could not open page file at {:?}", self.page_path))?;
+        let page_path = self.primary_path();
+        let page_file = File::open(page_path).with_context(|| {
+            if is_broken_symlink(page_path) {
+                format!("Page is a broken symlink: {}", page_path.display())
+            } else {
+                format!("Could not open page file at {}", page_path.display())
+            }
+        })?;
 
         // Open patch file
         let patch_file_opt = match &self.patch_path {
@@ -95,10 +438,203 @@ impl Cache {
     {
         Self {
             url: url.into(),
+            mirror_urls: Vec::new(),
+            keep_archive: None,
+            max_archive_size: DEFAULT_MAX_ARCHIVE_SIZE,
+            dir_mode: None,
+            pages_subdir: None,
+            update_languages: None,
+            archive_ref: None,
+            sources: vec![],
+            delta_url: None,
             platform,
+            platform_fallback: None,
+            merge_common: false,
+            fill_from_english: false,
+            case_insensitive: false,
+            normalize_separators: false,
+            skip_broken_symlinks: false,
+            #[cfg(feature = "page-index")]
+            index: OnceCell::new(),
+        }
+    }
+
+    /// Set the platform directories to look up pages in, in order of
+    /// preference. By default, the platform specific directory is preferred
+    /// over `common`.
+    pub fn with_platform_fallback(mut self, platform_fallback: Option<Vec<String>>) -> Self {
+        self.platform_fallback = platform_fallback;
+        self
+    }
+
+    /// If enabled, a platform specific page found by `find_page` is merged
+    /// with the `common` page of the same name (see
+    /// `PageLookupResult::merge_path`).
+    pub fn with_merge_common(mut self, merge_common: bool) -> Self {
+        self.merge_common = merge_common;
+        self
+    }
+
+    /// If enabled, a translated page found by `find_page` is filled up with
+    /// the examples of the English page of the same name (see
+    /// `PageLookupResult::english_path`).
+    pub fn with_fill_from_english(mut self, fill_from_english: bool) -> Self {
+        self.fill_from_english = fill_from_english;
+        self
+    }
+
+    /// If enabled, `find_page` falls back to a case insensitive lookup if
+    /// there is no page with exactly the given name.
+    pub fn with_case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    /// If there is no page with the exact name, look up the name with all
+    /// separators (spaces, underscores and hyphens) replaced by hyphens.
+    pub fn with_normalize_separators(mut self, normalize_separators: bool) -> Self {
+        self.normalize_separators = normalize_separators;
+        self
+    }
+
+    /// If enabled, a custom page that is a broken symlink is skipped when
+    /// looking up pages. Otherwise, it is found as usual and reading it fails
+    /// with an error about the broken symlink.
+    pub fn with_skip_broken_symlinks(mut self, skip_broken_symlinks: bool) -> Self {
+        self.skip_broken_symlinks = skip_broken_symlinks;
+        self
+    }
+
+    /// Set the URLs of mirrors to download the archive from (in order of
+    /// preference) if it cannot be downloaded from the primary URL.
+    pub fn with_mirror_urls(mut self, mirror_urls: Vec<String>) -> Self {
+        self.mirror_urls = mirror_urls;
+        self
+    }
+
+    /// Save a copy of the downloaded full archive at this path when updating.
+    /// The extension of the detected archive format is appended to the path,
+    /// unless it already has it. Delta updates are skipped if this is set.
+    pub fn with_keep_archive(mut self, keep_archive: Option<PathBuf>) -> Self {
+        self.keep_archive = keep_archive;
+        self
+    }
+
+    /// Set the maximum size of downloaded archives in bytes. Larger downloads
+    /// are aborted.
+    pub fn with_max_archive_size(mut self, max_archive_size: u64) -> Self {
+        self.max_archive_size = max_archive_size;
+        self
+    }
+
+    /// Set the directory within the archive that contains the pages
+    /// directories (`pages`, `pages.de`, ...). Only that directory is
+    /// extracted into the cache. If not set, it is detected automatically
+    /// (see `detect_pages_subdir`).
+    pub fn with_pages_subdir(mut self, pages_subdir: Option<PathBuf>) -> Self {
+        self.pages_subdir = pages_subdir;
+        self
+    }
+
+    /// Set the permissions of the cache directory and of the extracted pages
+    /// directory during an update. This only has an effect on Unix.
+    pub fn with_cache_dir_mode(mut self, mode: Option<u32>) -> Self {
+        self.dir_mode = mode;
+        self
+    }
+
+    /// Only extract the pages of these languages (and the English pages)
+    /// when updating. If not set, the pages of all languages are extracted.
+    pub fn with_update_languages(mut self, languages: Option<Vec<String>>) -> Self {
+        self.update_languages = languages;
+        self
+    }
+
+    /// Return the names of the page directories to extract when updating,
+    /// or `None` if all of them should be extracted.
+    fn update_language_dirs(&self) -> Option<Vec<String>> {
+        self.update_languages.as_ref().map(|languages| {
+            let mut dirs = Self::language_dirs(languages);
+            dirs.push(String::from("pages"));
+            dirs
+        })
+    }
+
+    /// Set the named page sources, as pairs of name and directory. Their
+    /// pages (`<name>.page`) are looked up in order, after the custom pages
+    /// and before the cache.
+    pub fn with_sources(mut self, sources: Vec<(String, PathBuf)>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Return the page with the given file name (`<name>.page`) from the
+    /// first page source that contains it, or only from the source named
+    /// `only_source` if it is given.
+    pub fn find_source_page(
+        &self,
+        filename: &str,
+        only_source: Option<&str>,
+    ) -> Option<PageCandidate> {
+        self.sources
+            .iter()
+            .filter(|(source_name, _)| only_source.map_or(true, |only| only == source_name))
+            .find_map(|(source_name, dir)| {
+                let path = dir.join(filename);
+                path.is_file()
+                    .then(|| PageCandidate::new(path).with_source_name(source_name.as_str()))
+            })
+    }
+
+    /// Pin the cache to a tag, branch or commit of the upstream repository.
+    /// The `{ref}` placeholder in the archive URLs is replaced with it, and
+    /// delta updates are skipped.
+    pub fn with_archive_ref(mut self, archive_ref: Option<String>) -> Self {
+        self.archive_ref = archive_ref;
+        self
+    }
+
+    /// Return the archive URL with the `{ref}` placeholder replaced by the
+    /// pinned ref, if any.
+    fn archive_url(&self, url: &str) -> String {
+        match self.archive_ref {
+            Some(ref archive_ref) => url.replace("{ref}", archive_ref),
+            None => url.to_string(),
+        }
+    }
+
+    /// Return the pinned ref the pages in the cache were downloaded for, or
+    /// `None` if they track the latest pages.
+    pub fn cached_archive_ref() -> Result<Option<String>> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let path = cache_dir.join(TLDR_PAGES_DIR).join(ARCHIVE_REF_FILE);
+        match fs::read_to_string(&path) {
+            Ok(archive_ref) => Ok(Some(archive_ref.trim().to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Could not read {}", path.display())),
+        }
+    }
+
+    /// Return the version of the pages in the cache (as used for delta
+    /// updates), if known.
+    pub fn cached_version() -> Result<Option<String>> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let path = cache_dir.join(TLDR_PAGES_DIR).join(VERSION_FILE);
+        match fs::read_to_string(&path) {
+            Ok(version) => Ok(Some(version.trim().to_string())),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e).context("Could not read the version of the cache"),
         }
     }
 
+    /// Set the URL of a delta archive to try before downloading the full
+    /// archive. A `{version}` placeholder is replaced with the version of the
+    /// current cache.
+    pub fn with_delta_url(mut self, delta_url: Option<String>) -> Self {
+        self.delta_url = delta_url;
+        self
+    }
+
     /// Return the path to the cache directory.
     pub fn get_cache_dir() -> Result<(PathBuf, PathSource)> {
         // Allow overriding the cache directory by setting the env variable.
@@ -134,8 +670,8 @@ impl Cache {
         Ok((dirs, PathSource::OsConvention))
     }
 
-    /// Download the archive
-    fn download(&self) -> Result<Vec<u8>> {
+    /// Build an HTTP client that respects the proxy env variables.
+    fn build_client() -> Result<Client> {
         let mut builder = Client::builder();
         if let Ok(ref host) = env::var("HTTP_PROXY") {
             if let Ok(proxy) = Proxy::http(host) {
@@ -147,28 +683,199 @@ impl Cache {
                 builder = builder.proxy(proxy);
             }
         }
-        let client = builder
-            .build()
-            .context("Could not instantiate HTTP client")?;
-        let mut resp = client
-            .get(&self.url)
+        builder.build().context("Could not instantiate HTTP client")
+    }
+
+    /// Return whether we are (probably) offline, either because the
+    /// `TEALDEER_OFFLINE` env variable is set, or because the archive host
+    /// cannot be reached within a short timeout.
+    ///
+    /// This is meant to skip automatic updates quickly instead of waiting for
+    /// a request to time out.
+    pub fn is_offline(&self) -> bool {
+        if env::var_os(OFFLINE_ENV_VAR).map_or(false, |value| !value.is_empty() && value != "0") {
+            debug!("{OFFLINE_ENV_VAR} is set, assuming we are offline");
+            return true;
+        }
+
+        // A proxy may be able to reach the host even if we cannot
+        if env::var_os("HTTP_PROXY").is_some() || env::var_os("HTTPS_PROXY").is_some() {
+            return false;
+        }
+
+        let addrs = Url::parse(&self.url).ok().and_then(|url| {
+            let host = url.host_str()?.to_string();
+            let port = url.port_or_known_default()?;
+            (host, port).to_socket_addrs().ok()
+        });
+        let reachable = addrs.map_or(false, |mut addrs| {
+            addrs.any(|addr| TcpStream::connect_timeout(&addr, CONNECTIVITY_PROBE_TIMEOUT).is_ok())
+        });
+        if !reachable {
+            debug!(
+                "Could not connect to the host of {}, assuming we are offline",
+                self.url
+            );
+        }
+        !reachable
+    }
+
+    /// Return whether the `TEALDEER_CACHE_IMMUTABLE` env variable is set (to
+    /// any value except `0`). The cache must not be modified in that case.
+    pub fn is_immutable() -> bool {
+        env::var_os(IMMUTABLE_ENV_VAR).map_or(false, |value| !value.is_empty() && value != "0")
+    }
+
+    /// Fail if the cache is immutable, mentioning what would have modified it.
+    fn ensure_mutable(action: &str) -> Result<()> {
+        ensure!(
+            !Self::is_immutable(),
+            "Refusing to {action}, the cache is immutable because ${IMMUTABLE_ENV_VAR} is set"
+        );
+        Ok(())
+    }
+
+    /// Download the archive at `url`. The download is aborted if the archive
+    /// is larger than `max_size` bytes.
+    fn download(url: &str, max_size: u64) -> Result<Vec<u8>> {
+        let too_large = || {
+            format!(
+                "The archive at {url} is larger than {max_size} bytes. \
+                 If this is expected, increase `updates.max_archive_size` in the config."
+            )
+        };
+
+        let client = Self::build_client()?;
+        let resp = client
+            .get(url)
             .send()?
             .error_for_status()
-            .with_context(|| format!("Could not download tldr pages from {}", &self.url))?;
+            .with_context(|| format!("Could not download tldr pages from {url}"))?;
+
+        // Check the announced size first, but don't rely on it
+        if resp
+            .content_length()
+            .map_or(false, |length| length > max_size)
+        {
+            bail!(too_large());
+        }
         let mut buf: Vec<u8> = vec![];
-        let bytes_downloaded = resp.copy_to(&mut buf)?;
+        let bytes_downloaded = resp
+            .take(max_size.saturating_add(1))
+            .read_to_end(&mut buf)
+            .with_context(|| format!("Could not download tldr pages from {url}"))?;
+        ensure!(bytes_downloaded as u64 <= max_size, too_large());
         debug!("{} bytes downloaded", bytes_downloaded);
         Ok(buf)
     }
 
     /// Update the pages cache.
-    pub fn update(&self) -> Result<()> {
-        // First, download the compressed data
-        let bytes: Vec<u8> = self.download()?;
+    ///
+    /// If a delta URL is configured, the delta archive is applied to the
+    /// current cache. If that is not possible, the full archive is downloaded,
+    /// trying the mirrors one after another if the primary URL fails.
+    ///
+    /// Details about the update are returned, e.g. which mirror was used.
+    pub fn update(&self) -> Result<UpdateSummary> {
+        Self::ensure_mutable("update the cache")?;
+
+        // Fail early with a clear message instead of a cryptic error during extraction
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        ensure_writable(&cache_dir)?;
+        if let Some(mode) = self.dir_mode {
+            set_dir_mode(&cache_dir, mode)?;
+        }
+
+        if let (Some(ref delta_url), None, None) =
+            (&self.delta_url, &self.keep_archive, &self.archive_ref)
+        {
+            match self.update_from_delta(delta_url) {
+                Ok(true) => return Ok(UpdateSummary::default()),
+                Ok(false) => debug!("Delta archive does not apply, downloading full archive"),
+                Err(e) => debug!("Could not apply delta archive, downloading full archive: {e:?}"),
+            }
+        }
+        self.update_full()
+    }
+
+    /// Verify that a well-known page can be found in the cache and contains
+    /// examples. This is meant to catch malformed archives after an update.
+    pub fn verify(&self) -> Result<()> {
+        let lookup_result = self
+            .find_page(VERIFICATION_PAGE, &["en".to_string()], None)
+            .with_context(|| format!("Could not find the `{VERIFICATION_PAGE}` page"))?;
+        verify_page(lookup_result.primary_path())
+    }
+
+    /// Try to update the cache using the delta archive at `delta_url`.
+    ///
+    /// Returns `Ok(false)` if the delta archive does not apply to the current
+    /// cache (e.g. because the cache is missing or has an unknown version).
+    fn update_from_delta(&self, delta_url: &str) -> Result<bool> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let pages_dir = cache_dir.join(TLDR_PAGES_DIR);
+        let current_version = match Self::cached_version()? {
+            Some(version) => version,
+            None => return Ok(false),
+        };
+
+        let url = delta_url.replace("{version}", &current_version);
+        let bytes = Self::download(&url, self.max_archive_size)?;
+        let applied = apply_delta(
+            &pages_dir,
+            bytes,
+            &current_version,
+            self.update_language_dirs().as_deref(),
+        )?;
+        #[cfg(feature = "page-index")]
+        if applied {
+            PageIndex::rebuild(&pages_dir)?;
+        }
+        Ok(applied)
+    }
+
+    /// Download the full archive at `url` and decompress it. If requested, a
+    /// copy of the downloaded archive is saved first, and its path is stored
+    /// in `kept_archive`.
+    fn download_archive(
+        &self,
+        url: &str,
+        kept_archive: &mut Option<PathBuf>,
+    ) -> Result<ZipArchive<Cursor<Vec<u8>>>> {
+        let bytes: Vec<u8> = Self::download(url, self.max_archive_size)?;
+        if let Some(ref path) = self.keep_archive {
+            let path = with_archive_extension(path, &bytes);
+            fs::write(&path, &bytes).with_context(|| {
+                format!(
+                    "Could not save the downloaded archive to {}",
+                    path.display()
+                )
+            })?;
+            *kept_archive = Some(path);
+        }
+        ZipArchive::new(Cursor::new(bytes)).context("Could not decompress downloaded ZIP archive")
+    }
 
-        // Decompress the response body into an `Archive`
-        let mut archive = ZipArchive::new(Cursor::new(bytes))
-            .context("Could not decompress downloaded ZIP archive")?;
+    /// Replace the pages cache with the contents of the full archive.
+    fn update_full(&self) -> Result<UpdateSummary> {
+        // First, download the compressed data, trying the mirrors in order
+        let mut summary = UpdateSummary::default();
+        let mut result =
+            self.download_archive(&self.archive_url(&self.url), &mut summary.kept_archive);
+        for url in &self.mirror_urls {
+            match result {
+                Ok(_) => break,
+                Err(ref e) => warn!("Could not download the archive, trying next mirror: {e:#}"),
+            }
+            let url = self.archive_url(url);
+            result = self.download_archive(&url, &mut summary.kept_archive);
+            summary.mirror_url = Some(url);
+        }
+        let mut archive = if self.mirror_urls.is_empty() {
+            result?
+        } else {
+            result.context("Could not download the archive from any mirror")?
+        };
 
         // Determine paths
         let (cache_dir, _) = Self::get_cache_dir()?;
@@ -178,42 +885,91 @@ impl Cache {
         debug!("Ensure cache directory {:?} exists", &cache_dir);
         fs::create_dir_all(&cache_dir).context("Could not create cache directory")?;
 
-        // Clear cache directory
-        // Note: This is not the best solution. Ideally we would download the
-        // archive to a temporary directory and then swap the two directories.
-        // But renaming a directory doesn't work across filesystems and Rust
-        // does not yet offer a recursive directory copying function. So for
-        // now, we'll use this approach.
-        Self::clear().context("Could not clear the cache directory")?;
+        // Extract archive into a temporary directory first, so that the
+        // existing cache stays intact if the extraction fails (e.g. because
+        // the disk is full). The temporary directory is in the cache
+        // directory, so that it can be renamed to the pages directory.
+        let tmp_dir = cache_dir.join(TLDR_PAGES_TMP_DIR);
+        if tmp_dir.exists() {
+            fs::remove_dir_all(&tmp_dir)
+                .context("Could not remove leftover temporary pages directory")?;
+        }
+        let pages_subdir = match self.pages_subdir {
+            Some(ref subdir) => {
+                ensure!(
+                    archive_contains_dir(&mut archive, subdir),
+                    "The archive does not contain the pages directory `{}` (see `updates.pages_subdir`)",
+                    subdir.display()
+                );
+                subdir.clone()
+            }
+            None => detect_pages_subdir(&mut archive),
+        };
+        debug!("Extracting the pages in {:?} of the archive", pages_subdir);
+        let language_dirs = self.update_language_dirs();
+        extract_archive(
+            &mut archive,
+            &tmp_dir,
+            &pages_subdir,
+            language_dirs.as_deref(),
+            |path| File::create(path),
+        )
+        .context("Could not unpack compressed data")?;
+        if let Some(ref archive_ref) = self.archive_ref {
+            fs::write(tmp_dir.join(ARCHIVE_REF_FILE), archive_ref)
+                .context("Could not write the pinned ref of the cache")?;
+        }
+        #[cfg(feature = "page-index")]
+        PageIndex::rebuild(&tmp_dir)?;
+        if let Some(mode) = self.dir_mode {
+            set_dir_mode(&tmp_dir, mode)?;
+        }
 
-        // Extract archive
-        archive
-            .extract(&pages_dir)
-            .context("Could not unpack compressed data")?;
+        // Replace the pages directory
+        Self::clear().context("Could not clear the cache directory")?;
+        fs::rename(&tmp_dir, &pages_dir)
+            .context("Could not move the extracted pages into the cache directory")?;
 
-        Ok(())
+        Ok(summary)
     }
 
     /// Return the duration since the cache directory was last modified.
-    pub fn last_update() -> Option<Duration> {
-        if let Ok((cache_dir, _)) = Self::get_cache_dir() {
-            if let Ok(metadata) = fs::metadata(cache_dir.join(TLDR_PAGES_DIR)) {
-                if let Ok(mtime) = metadata.modified() {
-                    let now = SystemTime::now();
-                    return now.duration_since(mtime).ok();
-                };
-            };
+    ///
+    /// If the cache has never been updated, `Ok(None)` is returned. An error
+    /// is returned if the cache directory exists, but its metadata cannot be
+    /// read (e.g. due to missing permissions).
+    pub fn last_update() -> Result<Option<Duration>> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let pages_dir = cache_dir.join(TLDR_PAGES_DIR);
+        let metadata = match fs::metadata(&pages_dir) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Could not read metadata of the cache directory at {}",
+                        pages_dir.display()
+                    )
+                })
+            }
         };
-        None
+        let mtime = metadata
+            .modified()
+            .context("Could not read the modification time of the cache directory")?;
+
+        // A modification time in the future is treated as "just updated"
+        Ok(Some(
+            SystemTime::now().duration_since(mtime).unwrap_or_default(),
+        ))
     }
 
     /// Return the freshness of the cache (fresh, stale or missing).
-    pub fn freshness() -> CacheFreshness {
-        match Cache::last_update() {
+    pub fn freshness() -> Result<CacheFreshness> {
+        Ok(match Cache::last_update()? {
             Some(ago) if ago > crate::config::MAX_CACHE_AGE => CacheFreshness::Stale(ago),
             Some(_) => CacheFreshness::Fresh,
             None => CacheFreshness::Missing,
-        }
+        })
     }
 
     /// Return the platform directory.
@@ -227,33 +983,286 @@ impl Cache {
         }
     }
 
-    /// Check for pages for a given platform in one of the given languages.
-    fn find_page_for_platform(
-        page_name: &str,
-        cache_dir: &Path,
-        platform: &str,
-        language_dirs: &[String],
-    ) -> Option<PathBuf> {
-        language_dirs
+    /// Return the platform directories to look up pages in, in order of
+    /// preference.
+    fn platform_dirs(&self) -> Vec<&str> {
+        match self.platform_fallback {
+            Some(ref platforms) => platforms.iter().map(String::as_str).collect(),
+            None => vec![self.get_platform_dir(), "common"],
+        }
+    }
+
+    /// Return the names of the page directories for the given languages
+    /// (e.g. `pages` for English and `pages.de` for German).
+    fn language_dirs(languages: &[String]) -> Vec<String> {
+        languages
             .iter()
-            .map(|lang_dir| cache_dir.join(lang_dir).join(platform).join(page_name))
-            .find(|path| path.exists() && path.is_file())
+            .map(|lang| {
+                if lang == "en" {
+                    String::from("pages")
+                } else {
+                    format!("pages.{lang}")
+                }
+            })
+            .collect()
     }
 
-    /// Look up custom patch (<name>.patch). If it exists, store it in a variable.
-    fn find_patch(patch_name: &str, custom_pages_dir: Option<&Path>) -> Option<PathBuf> {
-        custom_pages_dir
-            .map(|custom_dir| custom_dir.join(patch_name))
-            .filter(|path| path.exists() && path.is_file())
+    /// Return the possible pages for a given platform in the given languages,
+    /// in order of the languages, whether they exist or not.
+    fn page_paths_for_platform<'a>(
+        page_name: &'a str,
+        cache_dir: &'a Path,
+        platform: &'a str,
+        languages: &'a [String],
+    ) -> impl Iterator<Item = PageCandidate> + 'a {
+        languages
+            .iter()
+            .zip(Self::language_dirs(languages))
+            .map(move |(language, lang_dir)| {
+                PageCandidate::new(cache_dir.join(lang_dir).join(platform).join(page_name))
+                    .with_platform(platform)
+                    .with_language(language.as_str())
+            })
     }
 
-    /// Search for a page and return the path to it.
-    pub fn find_page(
+    /// Return all pages for a given platform in the given languages, in order
+    /// of the languages.
+    fn find_pages_for_platform(
+        page_name: &str,
+        cache_dir: &Path,
+        platform: &str,
+        languages: &[String],
+    ) -> Vec<PageCandidate> {
+        Self::page_paths_for_platform(page_name, cache_dir, platform, languages)
+            .filter(|candidate| candidate.path.exists() && candidate.path.is_file())
+            .collect()
+    }
+
+    /// Return all paths that `find_page` considers for a page, in order of
+    /// precedence, whether they exist or not.
+    ///
+    /// The custom page comes first, followed by the pages in the cache. The
+    /// first existing path is the page that `find_page` returns.
+    pub fn page_candidates(
         &self,
         name: &str,
         languages: &[String],
         custom_pages_dir: Option<&Path>,
-    ) -> Option<PageLookupResult> {
+    ) -> Result<Vec<PageCandidate>> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let cache_dir = cache_dir.join(TLDR_PAGES_DIR);
+        let page_filename = format!("{name}.md");
+
+        let custom_page = custom_pages_dir
+            .map(|custom_dir| PageCandidate::new(custom_dir.join(format!("{name}.page"))));
+        let source_pages = self.sources.iter().map(|(source_name, dir)| {
+            PageCandidate::new(dir.join(format!("{name}.page")))
+                .with_source_name(source_name.as_str())
+        });
+        let pages = self.platform_dirs().into_iter().flat_map(|platform| {
+            Self::page_paths_for_platform(&page_filename, &cache_dir, platform, languages)
+                .collect::<Vec<_>>()
+        });
+        Ok(custom_page
+            .into_iter()
+            .chain(source_pages)
+            .chain(pages)
+            .collect())
+    }
+
+    /// Return the path of the custom patch for a page, if it exists.
+    pub fn patch_path(name: &str, custom_pages_dir: Option<&Path>) -> Option<PathBuf> {
+        Self::find_patch(&format!("{name}.patch"), custom_pages_dir)
+    }
+
+    /// Look up custom patch (<name>.patch). If it exists, store it in a variable.
+    fn find_patch(patch_name: &str, custom_pages_dir: Option<&Path>) -> Option<PathBuf> {
+        custom_pages_dir
+            .map(|custom_dir| custom_dir.join(patch_name))
+            .filter(|path| path.exists() && path.is_file())
+    }
+
+    /// Search for a page and return the path to it.
+    ///
+    /// If case insensitive lookups are enabled and there is no page with
+    /// exactly the given name, the lowercased name is tried, and finally the
+    /// page directories are scanned for a name that only differs in case.
+    ///
+    /// If separators are normalized and no page is found, the same is tried
+    /// for the name with hyphens as separators (e.g. `git-log` for `git_log`).
+    pub fn find_page(
+        &self,
+        name: &str,
+        languages: &[String],
+        custom_pages_dir: Option<&Path>,
+    ) -> Option<PageLookupResult> {
+        self.find_page_in_platforms(name, languages, custom_pages_dir, &self.platform_dirs())
+    }
+
+    /// Search for a page like `find_page`, but only in the cache directories
+    /// of the given platforms, in order of preference.
+    fn find_page_in_platforms(
+        &self,
+        name: &str,
+        languages: &[String],
+        custom_pages_dir: Option<&Path>,
+        platforms: &[&str],
+    ) -> Option<PageLookupResult> {
+        let result = self.find_page_any_case(name, languages, custom_pages_dir, platforms);
+        if result.is_some() || !self.normalize_separators {
+            return result;
+        }
+
+        let normalized_name = normalize_separators(name);
+        if normalized_name == name {
+            return None;
+        }
+        debug!("Looking up `{normalized_name}` for `{name}`");
+        self.find_page_any_case(&normalized_name, languages, custom_pages_dir, platforms)
+    }
+
+    /// Search for a page like `find_page_in_platforms`, but without
+    /// normalizing the separators.
+    fn find_page_any_case(
+        &self,
+        name: &str,
+        languages: &[String],
+        custom_pages_dir: Option<&Path>,
+        platforms: &[&str],
+    ) -> Option<PageLookupResult> {
+        let result = self.find_page_exact(name, languages, custom_pages_dir, platforms);
+        if result.is_some() || !self.case_insensitive {
+            return result;
+        }
+
+        // Lowercasing is cheap and covers the page names of the tldr project
+        let lowercase_name = name.to_lowercase();
+        if lowercase_name != name {
+            if let Some(result) =
+                self.find_page_exact(&lowercase_name, languages, custom_pages_dir, platforms)
+            {
+                return Some(result);
+            }
+        }
+
+        // Only scan the directories if there is no exact match
+        let actual_name = self.find_name_case_insensitive(
+            &lowercase_name,
+            languages,
+            custom_pages_dir,
+            platforms,
+        )?;
+        debug!("Found page `{actual_name}` for `{name}` by case insensitive lookup");
+        self.find_page_exact(&actual_name, languages, custom_pages_dir, platforms)
+    }
+
+    /// Return the name of a page whose lowercased name is `lowercase_name`,
+    /// preferring custom pages and then following the order of `platforms`
+    /// and `languages`.
+    fn find_name_case_insensitive(
+        &self,
+        lowercase_name: &str,
+        languages: &[String],
+        custom_pages_dir: Option<&Path>,
+        platforms: &[&str],
+    ) -> Option<String> {
+        let (cache_dir, _) = Self::get_cache_dir().ok()?;
+        let pages_dir = cache_dir.join(TLDR_PAGES_DIR);
+
+        let custom_dirs = custom_pages_dir
+            .map(|dir| (dir.to_path_buf(), "page", None))
+            .into_iter()
+            .chain(
+                self.sources
+                    .iter()
+                    .map(|(_, dir)| (dir.clone(), "page", None)),
+            );
+        let cache_dirs = platforms.iter().flat_map(|&platform| {
+            languages
+                .iter()
+                .zip(Self::language_dirs(languages))
+                .map(|(language, lang_dir)| {
+                    // Take the names from the page index instead of the
+                    // directory, if there is one
+                    let indexed_names = self
+                        .indexed_pages(language, &[platform])
+                        .map(|pages| pages.into_iter().map(|(name, _)| name).collect::<Vec<_>>());
+                    (pages_dir.join(lang_dir).join(platform), "md", indexed_names)
+                })
+                .collect::<Vec<_>>()
+        });
+        custom_dirs
+            .chain(cache_dirs)
+            .find_map(|(dir, extension, indexed_names)| {
+                let mut names: Vec<String> = indexed_names
+                    .unwrap_or_else(|| Self::page_names(&dir, extension))
+                    .into_iter()
+                    .filter(|name| name.to_lowercase() == lowercase_name)
+                    .collect();
+                // Make the choice deterministic if several names match
+                names.sort();
+                names.into_iter().next()
+            })
+    }
+
+    /// Return the names of the pages with the given extension in a directory
+    /// (not recursive). A missing directory yields no names.
+    fn page_names(dir: &Path, extension: &str) -> Vec<String> {
+        fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension() != Some(OsStr::new(extension)) {
+                    return None;
+                }
+                path.file_stem()?.to_str().map(str::to_string)
+            })
+            .collect()
+    }
+
+    /// Return the names and paths of the pages in the given language and
+    /// platform directories according to the page index of the cache (see
+    /// `PageIndex`), or `None` if there is no index.
+    #[cfg(feature = "page-index")]
+    fn indexed_pages(&self, language: &str, platforms: &[&str]) -> Option<Vec<(String, PathBuf)>> {
+        let index = self
+            .index
+            .get_or_init(|| {
+                let (cache_dir, _) = Self::get_cache_dir().ok()?;
+                PageIndex::load(&cache_dir.join(TLDR_PAGES_DIR))
+            })
+            .as_ref()?;
+        let pages_dir = Self::get_cache_dir().ok()?.0.join(TLDR_PAGES_DIR);
+        Some(
+            index
+                .pages(language, platforms)
+                .map(|entry| (entry.name.clone(), pages_dir.join(&entry.path)))
+                .collect(),
+        )
+    }
+
+    /// Without the `page-index` feature, the page directories are always
+    /// scanned.
+    #[cfg(not(feature = "page-index"))]
+    #[allow(clippy::unused_self)]
+    fn indexed_pages(
+        &self,
+        _language: &str,
+        _platforms: &[&str],
+    ) -> Option<Vec<(String, PathBuf)>> {
+        None
+    }
+
+    /// Search for a page with exactly the given name and return the path to it.
+    fn find_page_exact(
+        &self,
+        name: &str,
+        languages: &[String],
+        custom_pages_dir: Option<&Path>,
+        platforms: &[&str],
+    ) -> Option<PageLookupResult> {
         let page_filename = format!("{}.md", name);
         let patch_filename = format!("{}.patch", name);
         let custom_filename = format!("{}.page", name);
@@ -267,45 +1276,370 @@ impl Cache {
             }
         };
 
-        let lang_dirs: Vec<String> = languages
+        // Look up custom page (<name>.page). If it exists, return it directly
+        if let Some(config_dir) = custom_pages_dir {
+            let custom_page = config_dir.join(&custom_filename);
+            if (custom_page.exists() && custom_page.is_file())
+                || (is_broken_symlink(&custom_page) && !self.skip_broken_symlinks)
+            {
+                return Some(
+                    PageLookupResult::with_page(custom_page)
+                        .with_target_platform(Some(self.platform))
+                        .with_source(PageSource::Custom),
+                );
+            }
+        }
+
+        // Then look up the page in the page sources
+        if let Some(candidate) = self.find_source_page(&custom_filename, None) {
+            return Some(
+                PageLookupResult::with_candidate(candidate)
+                    .with_target_platform(Some(self.platform))
+                    .with_source(PageSource::Custom),
+            );
+        }
+
+        let patch_path = Self::find_patch(&patch_filename, custom_pages_dir);
+
+        // Platform specific pages are preferred, fall back to "common" (or
+        // the configured fallback order). Append custom patch to the page.
+        let mut candidates: Vec<PageCandidate> = platforms
             .iter()
-            .map(|lang| {
-                if lang == "en" {
-                    String::from("pages")
-                } else {
-                    format!("pages.{}", lang)
-                }
+            .flat_map(|platform| {
+                Self::find_pages_for_platform(&page_filename, &cache_dir, platform, languages)
             })
             .collect();
+        if candidates.is_empty() {
+            return None;
+        }
 
-        // Look up custom page (<name>.page). If it exists, return it directly
-        if let Some(config_dir) = custom_pages_dir {
-            let custom_page = config_dir.join(custom_filename);
-            if custom_page.exists() && custom_page.is_file() {
-                return Some(PageLookupResult::with_page(custom_page));
+        // If the best page is platform specific, merge the best common page
+        // into it, if requested
+        let is_common =
+            |candidate: &&PageCandidate| candidate.platform.as_deref() == Some("common");
+        let merge_path = if self.merge_common && !is_common(&&candidates[0]) {
+            candidates
+                .iter()
+                .find(is_common)
+                .map(|candidate| candidate.path.clone())
+        } else {
+            None
+        };
+
+        // If the best page is a translation, fill it up with the English
+        // page of the same platform, if requested
+        let english_path = match (&candidates[0].language, &candidates[0].platform) {
+            (Some(language), Some(platform)) if self.fill_from_english && language != "en" => {
+                Some(cache_dir.join("pages").join(platform).join(&page_filename))
+                    .filter(|path| path.is_file())
+            }
+            _ => None,
+        };
+
+        let primary = candidates.remove(0);
+        Some(
+            PageLookupResult::with_candidate(primary)
+                .with_candidates(candidates)
+                .with_optional_patch(patch_path)
+                .with_optional_merge(merge_path)
+                .with_optional_english(english_path)
+                .with_target_platform(Some(self.platform))
+                .with_source(PageSource::Cache),
+        )
+    }
+
+    /// Search for a page that lists `name` in its aliases (`> Aliases: ...`)
+    /// and return the path to it.
+    ///
+    /// The pages are scanned in the same platform and language order as in
+    /// `find_page`, and within a directory in alphabetical order.
+    pub fn find_page_by_alias(
+        &self,
+        name: &str,
+        languages: &[String],
+        custom_pages_dir: Option<&Path>,
+    ) -> Option<PageLookupResult> {
+        let cache_dir = match Self::get_cache_dir() {
+            Ok((cache_dir, _)) => cache_dir.join(TLDR_PAGES_DIR),
+            Err(e) => {
+                log::error!("Could not get cache directory: {e}");
+                return None;
+            }
+        };
+
+        for platform in self.platform_dirs() {
+            for (language, lang_dir) in languages.iter().zip(Self::language_dirs(languages)) {
+                let mut paths =
+                    Self::files_with_extension(&cache_dir.join(&lang_dir).join(platform), "md");
+                paths.sort();
+                let path = match paths.into_iter().find(|path| page_has_alias(path, name)) {
+                    Some(path) => path,
+                    None => continue,
+                };
+                debug!("Found alias {name:?} in page {}", path.display());
+
+                let patch_path = path
+                    .file_stem()
+                    .and_then(OsStr::to_str)
+                    .and_then(|page| Self::find_patch(&format!("{page}.patch"), custom_pages_dir));
+                let candidate = PageCandidate::new(path)
+                    .with_platform(platform)
+                    .with_language(language.as_str());
+                return Some(
+                    PageLookupResult::with_candidate(candidate)
+                        .with_optional_patch(patch_path)
+                        .with_target_platform(Some(self.platform))
+                        .with_source(PageSource::Cache),
+                );
             }
         }
 
-        let patch_path = Self::find_patch(&patch_filename, custom_pages_dir);
+        None
+    }
 
-        // Try to find a platform specific path next, append custom patch to it.
-        let platform_dir = self.get_platform_dir();
-        if let Some(page) =
-            Self::find_page_for_platform(&page_filename, &cache_dir, platform_dir, &lang_dirs)
-        {
-            return Some(PageLookupResult::with_page(page).with_optional_patch(patch_path));
+    /// Search for a page on all platforms and return one result per platform
+    /// the page exists for.
+    ///
+    /// The `common` page comes first, followed by the platform specific pages
+    /// in alphabetical order. The page is looked up like in `find_page`, so a
+    /// custom page or a page from a source is returned on its own.
+    pub fn find_page_all_platforms(
+        &self,
+        name: &str,
+        languages: &[String],
+        custom_pages_dir: Option<&Path>,
+    ) -> Result<Vec<PageLookupResult>> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let cache_dir = cache_dir.join(TLDR_PAGES_DIR);
+
+        // Without a target platform, the examples of all platforms are shown
+        let find_page = |platforms: &[&str]| {
+            self.find_page_in_platforms(name, languages, custom_pages_dir, platforms)
+                .map(|result| result.with_target_platform(None))
+        };
+
+        // Without any platforms, only custom pages and sources are searched
+        if let Some(result) = find_page(&[]) {
+            return Ok(vec![result]);
         }
 
-        // Did not find platform specific results, fall back to "common"
-        Self::find_page_for_platform(&page_filename, &cache_dir, "common", &lang_dirs)
-            .map(|page| PageLookupResult::with_page(page).with_optional_patch(patch_path))
+        // Collect the platforms of all languages
+        let mut platforms = BTreeSet::new();
+        for lang_dir in Self::language_dirs(languages) {
+            let entries = match fs::read_dir(cache_dir.join(lang_dir)) {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e).context("Could not read the pages directory"),
+            };
+            for entry in entries {
+                let entry = entry.context("Could not read the pages directory")?;
+                if entry.path().is_dir() {
+                    if let Some(platform) = entry.file_name().to_str() {
+                        platforms.insert(platform.to_string());
+                    }
+                }
+            }
+        }
+        let common = platforms.take("common");
+        Ok(common
+            .into_iter()
+            .chain(platforms)
+            .filter_map(|platform| find_page(&[&platform]))
+            .collect())
     }
 
-    /// Return the available pages.
-    pub fn list_pages(&self, custom_pages_dir: Option<&Path>) -> Result<Vec<String>> {
+    /// Download a single page from the upstream repository and store it in
+    /// the cache.
+    ///
+    /// The pages are looked up in the same platform order as in `find_page`.
+    /// If no page can be found upstream, `Ok(None)` is returned.
+    pub fn fetch_page(
+        &self,
+        name: &str,
+        languages: &[String],
+        custom_pages_dir: Option<&Path>,
+    ) -> Result<Option<PageLookupResult>> {
+        Self::ensure_mutable("store a fetched page in the cache")?;
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let client = Self::build_client()?;
+        let page_filename = format!("{name}.md");
+
+        for platform_dir in self.platform_dirs() {
+            for (language, lang_dir) in languages.iter().zip(Self::language_dirs(languages)) {
+                let url = Self::page_url(&lang_dir, platform_dir, &page_filename);
+                debug!("Fetching page from {}", url);
+                let resp = client
+                    .get(&url)
+                    .send()
+                    .with_context(|| format!("Could not download page from {url}"))?;
+                if resp.status() == StatusCode::NOT_FOUND {
+                    continue;
+                }
+                let contents = resp
+                    .error_for_status()
+                    .and_then(Response::bytes)
+                    .with_context(|| format!("Could not download page from {url}"))?;
+
+                // Store the page where it would have been extracted to
+                let page_dir = cache_dir
+                    .join(TLDR_PAGES_DIR)
+                    .join(&lang_dir)
+                    .join(platform_dir);
+                fs::create_dir_all(&page_dir)
+                    .context("Could not create directory for the fetched page")?;
+                let page_path = page_dir.join(&page_filename);
+                fs::write(&page_path, &contents).with_context(|| {
+                    format!("Could not write fetched page to {}", page_path.display())
+                })?;
+
+                let patch_path = Self::find_patch(&format!("{name}.patch"), custom_pages_dir);
+                let candidate = PageCandidate::new(page_path)
+                    .with_platform(platform_dir)
+                    .with_language(language.as_str());
+                return Ok(Some(
+                    PageLookupResult::with_candidate(candidate)
+                        .with_optional_patch(patch_path)
+                        .with_target_platform(Some(self.platform))
+                        .with_source(PageSource::Fetched),
+                ));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Return the upstream URL of a raw page file.
+    fn page_url(lang_dir: &str, platform_dir: &str, page_filename: &str) -> String {
+        Self::page_url_with_base(PAGES_RAW_URL, lang_dir, platform_dir, page_filename)
+    }
+
+    /// Return the URL of a page file below `base` (e.g. the raw files or the
+    /// GitHub repository).
+    fn page_url_with_base(
+        base: &str,
+        lang_dir: &str,
+        platform_dir: &str,
+        page_filename: &str,
+    ) -> String {
+        format!("{base}/{lang_dir}/{platform_dir}/{page_filename}")
+    }
+
+    /// Return the names of all pages for the current platform whose contents
+    /// contain `query` (case insensitive), sorted by name.
+    ///
+    /// The page files are read and scanned by `concurrency` threads in
+    /// parallel.
+    pub fn search_pages(
+        &self,
+        query: &str,
+        languages: &[String],
+        custom_pages_dir: Option<&Path>,
+        concurrency: usize,
+    ) -> Result<Vec<String>> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let pages_dir = cache_dir.join(TLDR_PAGES_DIR);
+
+        // Collect the files to search
+        let mut paths: Vec<PathBuf> = Vec::new();
+        let platform_dirs = [self.get_platform_dir(), "common"];
+        for (language, lang_dir) in languages.iter().zip(Self::language_dirs(languages)) {
+            if let Some(pages) = self.indexed_pages(language, &platform_dirs) {
+                paths.extend(pages.into_iter().map(|(_, path)| path));
+                continue;
+            }
+            for platform_dir in platform_dirs {
+                let dir = pages_dir.join(&lang_dir).join(platform_dir);
+                paths.extend(Self::files_with_extension(&dir, "md"));
+            }
+        }
+        if let Some(custom_pages_dir) = custom_pages_dir {
+            paths.extend(Self::files_with_extension(custom_pages_dir, "page"));
+        }
+
+        // Scan the files in parallel
+        let query: Arc<str> = query.to_lowercase().into();
+        // Round up, so that there are at most `concurrency` chunks
+        let concurrency = concurrency.max(1);
+        let chunk_size = ((paths.len() + concurrency - 1) / concurrency).max(1);
+        let handles: Vec<_> = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let chunk = chunk.to_vec();
+                let query = Arc::clone(&query);
+                thread::spawn(move || {
+                    chunk
+                        .into_iter()
+                        .filter(|path| {
+                            fs::read_to_string(path)
+                                .map_or(false, |contents| contents.to_lowercase().contains(&*query))
+                        })
+                        .filter_map(|path| {
+                            path.file_stem().and_then(OsStr::to_str).map(str::to_string)
+                        })
+                        .collect::<Vec<String>>()
+                })
+            })
+            .collect();
+
+        // Collect the results in a deterministic order
+        let mut pages = Vec::new();
+        for handle in handles {
+            match handle.join() {
+                Ok(matches) => pages.extend(matches),
+                Err(_) => bail!("A search thread panicked"),
+            }
+        }
+        pages.sort();
+        pages.dedup();
+        Ok(pages)
+    }
+
+    /// Return the files with the given extension in a directory (not
+    /// recursive). A missing directory yields no files.
+    fn files_with_extension(dir: &Path, extension: &str) -> Vec<PathBuf> {
+        WalkDir::new(dir)
+            .min_depth(1)
+            .max_depth(1)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                entry.file_type().is_file()
+                    && entry.path().extension() == Some(OsStr::new(extension))
+            })
+            .map(DirEntry::into_path)
+            .collect()
+    }
+
+    /// Return the available pages in the given language, sorted by name.
+    pub fn list_pages(
+        &self,
+        language: &str,
+        custom_pages_dir: Option<&Path>,
+    ) -> Result<Vec<String>> {
+        let mut pages = Vec::new();
+        self.for_each_page(language, custom_pages_dir, |page| {
+            pages.push(page.to_string());
+        })?;
+        pages.sort();
+        Ok(pages)
+    }
+
+    /// Call `callback` with the name of every available page in the given
+    /// language, in the order in which the pages are found on disk. Every
+    /// name is passed only once, even if there are multiple pages with it.
+    pub fn for_each_page<F>(
+        &self,
+        language: &str,
+        custom_pages_dir: Option<&Path>,
+        mut callback: F,
+    ) -> Result<()>
+    where
+        F: FnMut(&str),
+    {
         // Determine platforms directory and platform
         let (cache_dir, _) = Self::get_cache_dir()?;
-        let platforms_dir = cache_dir.join(TLDR_PAGES_DIR).join("pages");
+        let lang_dir = &Self::language_dirs(&[language.to_string()])[0];
+        let platforms_dir = cache_dir.join(TLDR_PAGES_DIR).join(lang_dir);
         let platform_dir = self.get_platform_dir();
 
         // Closure that allows the WalkDir instance to traverse platform
@@ -332,21 +1666,33 @@ impl Cache {
                 .map(str::to_string)
         };
 
-        // Recursively walk through common and (if applicable) platform specific directory
-        let mut pages = WalkDir::new(platforms_dir)
-            .min_depth(1) // Skip root directory
-            .into_iter()
-            .filter_entry(should_walk) // Filter out pages for other architectures
-            .filter_map(Result::ok) // Convert results to options, filter out errors
-            .filter_map(|e| {
-                let extension = e.path().extension().unwrap_or_default();
-                if e.file_type().is_file() && extension == "md" {
-                    to_stem(e)
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<String>>();
+        let mut seen = HashSet::new();
+        let mut visit = |page: String| {
+            if !seen.contains(&page) {
+                callback(&page);
+                seen.insert(page);
+            }
+        };
+
+        if let Some(pages) = self.indexed_pages(language, &[platform_dir, "common"]) {
+            pages.into_iter().map(|(name, _)| name).for_each(&mut visit);
+        } else {
+            // Recursively walk through common and (if applicable) platform specific directory
+            let pages = WalkDir::new(platforms_dir)
+                .min_depth(1) // Skip root directory
+                .into_iter()
+                .filter_entry(should_walk) // Filter out pages for other architectures
+                .filter_map(Result::ok) // Convert results to options, filter out errors
+                .filter_map(|e| {
+                    let extension = e.path().extension().unwrap_or_default();
+                    if e.file_type().is_file() && extension == "md" {
+                        to_stem(e)
+                    } else {
+                        None
+                    }
+                });
+            pages.for_each(&mut visit);
+        }
 
         if let Some(custom_pages_dir) = custom_pages_dir {
             let is_page = |entry: &DirEntry| -> bool {
@@ -362,16 +1708,104 @@ impl Cache {
                 .filter_map(Result::ok)
                 .filter_map(to_stem);
 
-            pages.extend(custom_pages);
+            custom_pages.for_each(&mut visit);
         }
 
-        pages.sort();
-        pages.dedup();
-        Ok(pages)
+        Ok(())
+    }
+
+    /// Return the languages available in the cache, e.g. `["de", "en"]`.
+    pub fn available_languages() -> Result<Vec<String>> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let pages_dir = cache_dir.join(TLDR_PAGES_DIR);
+
+        let mut languages = Vec::new();
+        if pages_dir.is_dir() {
+            for entry in fs::read_dir(&pages_dir).context("Could not read the pages directory")? {
+                let entry = entry.context("Could not read the pages directory")?;
+                if !entry
+                    .file_type()
+                    .map_or(false, |file_type| file_type.is_dir())
+                {
+                    continue;
+                }
+                match entry.file_name().to_str() {
+                    Some("pages") => languages.push("en".to_string()),
+                    Some(name) => {
+                        if let Some(language) = name.strip_prefix("pages.") {
+                            languages.push(language.to_string());
+                        }
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        languages.sort();
+        Ok(languages)
+    }
+
+    /// Return the number of pages per platform, grouped by language directory
+    /// (e.g. `pages` or `pages.de`).
+    pub fn page_tree() -> Result<BTreeMap<String, BTreeMap<String, usize>>> {
+        let (cache_dir, _) = Self::get_cache_dir()?;
+        let pages_dir = cache_dir.join(TLDR_PAGES_DIR);
+
+        let mut tree: BTreeMap<String, BTreeMap<String, usize>> = BTreeMap::new();
+        for entry in WalkDir::new(&pages_dir)
+            .min_depth(1)
+            .max_depth(3)
+            .into_iter()
+            .filter_map(Result::ok)
+        {
+            let components: Vec<&str> = match entry.path().strip_prefix(&pages_dir) {
+                Ok(path) => path.iter().filter_map(OsStr::to_str).collect(),
+                Err(_) => continue,
+            };
+            match components[..] {
+                [language] if entry.file_type().is_dir() => {
+                    tree.entry(language.to_string()).or_default();
+                }
+                [language, platform] if entry.file_type().is_dir() => {
+                    tree.entry(language.to_string())
+                        .or_default()
+                        .entry(platform.to_string())
+                        .or_default();
+                }
+                [language, platform, _]
+                    if entry.file_type().is_file()
+                        && entry.path().extension() == Some(OsStr::new("md")) =>
+                {
+                    *tree
+                        .entry(language.to_string())
+                        .or_default()
+                        .entry(platform.to_string())
+                        .or_default() += 1;
+                }
+                _ => {}
+            }
+        }
+
+        Ok(tree)
     }
 
     /// Delete the cache directory.
     pub fn clear() -> Result<()> {
+        Self::ensure_mutable("clear the cache")?;
+        for pages_dir in Self::clear_targets()? {
+            fs::remove_dir_all(&pages_dir).with_context(|| {
+                format!(
+                    "Could not remove the cache directory at {}",
+                    pages_dir.display()
+                )
+            })?;
+        }
+
+        Ok(())
+    }
+
+    /// Return the existing directories that `clear` would delete.
+    pub fn clear_targets() -> Result<Vec<PathBuf>> {
         let (path, _) = Self::get_cache_dir()?;
 
         // Check preconditions
@@ -386,23 +1820,376 @@ impl Cache {
             path.display()
         );
 
-        // Delete old tldr-pages cache location as well if present
-        // TODO: To be removed in the future
-        for pages_dir_name in [TLDR_PAGES_DIR, TLDR_OLD_PAGES_DIR] {
-            let pages_dir = path.join(pages_dir_name);
+        let dir_names = [
+            TLDR_PAGES_DIR,
+            // Delete old tldr-pages cache location as well if present
+            // TODO: To be removed in the future
+            TLDR_OLD_PAGES_DIR,
+            // The rendered pages are stale once the pages are gone
+            RENDER_CACHE_DIR,
+        ];
+        Ok(dir_names
+            .into_iter()
+            .map(|dir_name| path.join(dir_name))
+            .filter(|dir| dir.exists())
+            .collect())
+    }
 
-            if pages_dir.exists() {
-                fs::remove_dir_all(&pages_dir).with_context(|| {
-                    format!(
-                        "Could not remove the cache directory at {}",
-                        pages_dir.display()
-                    )
-                })?;
+    /// Return the total size in bytes of all files in `dir` (recursively).
+    /// Files that cannot be read are skipped.
+    pub fn dir_size(dir: &Path) -> u64 {
+        WalkDir::new(dir)
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| entry.metadata().ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+}
+
+/// Apply the delta archive in `bytes` to the pages in `pages_dir`.
+///
+/// Returns `Ok(false)` without changing anything if the delta archive is not
+/// based on `current_version`. If `language_dirs` is set, only the files in
+/// these pages directories are written.
+fn apply_delta(
+    pages_dir: &Path,
+    bytes: Vec<u8>,
+    current_version: &str,
+    language_dirs: Option<&[String]>,
+) -> Result<bool> {
+    let mut archive =
+        ZipArchive::new(Cursor::new(bytes)).context("Could not decompress delta archive")?;
+
+    // Read manifest
+    let mut contents = String::new();
+    archive
+        .by_name(DELTA_MANIFEST_FILE)
+        .context("Delta archive does not contain a manifest")?
+        .read_to_string(&mut contents)
+        .context("Could not read delta manifest")?;
+    let manifest = DeltaManifest::parse(&contents)?;
+    if manifest.base != current_version {
+        debug!(
+            "Delta archive is based on version {:?}, but the cache has version {:?}",
+            manifest.base, current_version
+        );
+        return Ok(false);
+    }
+
+    // Remove deleted files
+    for path in &manifest.removed {
+        match fs::remove_file(pages_dir.join(path)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                return Err(e).with_context(|| format!("Could not remove {}", path.display()));
             }
+            _ => {}
         }
+    }
 
-        Ok(())
+    // Write added and changed files
+    write_archive_files(
+        &mut archive,
+        pages_dir,
+        Path::new(""),
+        &[DELTA_MANIFEST_FILE],
+        language_dirs,
+        |path| File::create(path),
+    )?;
+
+    fs::write(pages_dir.join(VERSION_FILE), &manifest.version)
+        .context("Could not write the version of the cache")?;
+    Ok(true)
+}
+
+/// Ensure that the page at `path` can be read and contains examples.
+fn verify_page(path: &Path) -> Result<()> {
+    let file =
+        File::open(path).with_context(|| format!("Could not open page {}", path.display()))?;
+    let page = LineIterator::new(BufReader::new(file)).parse();
+    let has_examples = page
+        .examples
+        .iter()
+        .any(|example| !example.commands.is_empty());
+    ensure!(
+        has_examples,
+        "The page {} does not contain any examples",
+        path.display()
+    );
+    Ok(())
+}
+
+/// Return whether the page at `path` lists `name` (case insensitive) in its
+/// aliases. Only the header of the page is read.
+fn page_has_alias(path: &Path, name: &str) -> bool {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) => {
+            warn!("Could not open page {}: {e}", path.display());
+            return false;
+        }
+    };
+    LineIterator::new(BufReader::new(file))
+        .take_while(|line| !matches!(line, LineType::ExampleText(_) | LineType::ExampleCode(_)))
+        .any(|line| match line {
+            LineType::Aliases(aliases) => {
+                aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))
+            }
+            _ => false,
+        })
+}
+
+/// Return whether the error was caused by a full disk.
+fn is_disk_full(error: &io::Error) -> bool {
+    // `io::ErrorKind::StorageFull` is not stable yet, so check the OS error codes
+    #[cfg(unix)]
+    const CODES: &[i32] = &[28]; // ENOSPC
+    #[cfg(windows)]
+    const CODES: &[i32] = &[39, 112]; // ERROR_HANDLE_DISK_FULL, ERROR_DISK_FULL
+    #[cfg(not(any(unix, windows)))]
+    const CODES: &[i32] = &[];
+
+    error
+        .raw_os_error()
+        .map_or(false, |code| CODES.contains(&code))
+}
+
+/// Return whether the archive entry `name` (relative to the directory with
+/// the pages directories) is part of one of the pages directories in
+/// `language_dirs`. Entries outside of pages directories are always part.
+fn is_in_language_dirs(name: &Path, language_dirs: &[String]) -> bool {
+    match name.components().next() {
+        Some(Component::Normal(dir)) => {
+            let dir = dir.to_string_lossy();
+            !(dir == "pages" || dir.starts_with("pages."))
+                || language_dirs
+                    .iter()
+                    .any(|language_dir| *language_dir == dir)
+        }
+        _ => true,
+    }
+}
+
+/// Write the files in `archive` to `target_dir`, skipping the files named in
+/// `skip`. If `language_dirs` is set, only the pages directories in it are
+/// written. The output files are opened with `create_file`.
+fn write_archive_files<R, W, F>(
+    archive: &mut ZipArchive<R>,
+    target_dir: &Path,
+    subdir: &Path,
+    skip: &[&str],
+    language_dirs: Option<&[String]>,
+    mut create_file: F,
+) -> Result<()>
+where
+    R: Read + Seek,
+    W: Write,
+    F: FnMut(&Path) -> io::Result<W>,
+{
+    let write_error = |e: io::Error, path: &Path| {
+        if is_disk_full(&e) {
+            anyhow::Error::new(e).context("Not enough disk space to extract the pages")
+        } else {
+            anyhow::Error::new(e).context(format!("Could not write {}", path.display()))
+        }
+    };
+
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if skip.contains(&file.name()) {
+            continue;
+        }
+        let path = match file.enclosed_name() {
+            Some(name) => match name.strip_prefix(subdir) {
+                Ok(name) if name.as_os_str().is_empty() => continue,
+                Ok(name)
+                    if !language_dirs.map_or(true, |language_dirs| {
+                        is_in_language_dirs(name, language_dirs)
+                    }) =>
+                {
+                    continue
+                }
+                Ok(name) => target_dir.join(name),
+                Err(_) => continue,
+            },
+            None => bail!("Invalid path in archive: {:?}", file.name()),
+        };
+        if file.is_dir() {
+            fs::create_dir_all(&path).map_err(|e| write_error(e, &path))?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| write_error(e, parent))?;
+        }
+        let mut out = create_file(&path).map_err(|e| write_error(e, &path))?;
+        io::copy(&mut file, &mut out).map_err(|e| write_error(e, &path))?;
+        out.flush().map_err(|e| write_error(e, &path))?;
     }
+    Ok(())
+}
+
+/// Return `path` with the file extension of the archive format of `bytes`
+/// appended, unless the path already has that extension. If the format is not
+/// recognized, the path is returned as it is.
+fn with_archive_extension(path: &Path, bytes: &[u8]) -> PathBuf {
+    let extension = if bytes.starts_with(b"PK\x03\x04") {
+        "zip"
+    } else if bytes.starts_with(&[0x1f, 0x8b]) {
+        "gz"
+    } else if bytes.starts_with(b"BZh") {
+        "bz2"
+    } else if bytes.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        "xz"
+    } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        "zst"
+    } else {
+        return path.to_path_buf();
+    };
+    if path.extension() == Some(OsStr::new(extension)) {
+        return path.to_path_buf();
+    }
+    let mut path = path.as_os_str().to_os_string();
+    path.push(".");
+    path.push(extension);
+    PathBuf::from(path)
+}
+
+/// Replace the separators in a page name (spaces, underscores and runs of
+/// hyphens) by single hyphens, e.g. `git-log` for `git_log` or `git  log`.
+fn normalize_separators(name: &str) -> String {
+    name.split(|c: char| c.is_whitespace() || c == '_' || c == '-')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Return whether `path` is a symlink whose target does not exist.
+fn is_broken_symlink(path: &Path) -> bool {
+    !path.exists()
+        && path
+            .symlink_metadata()
+            .map_or(false, |metadata| metadata.file_type().is_symlink())
+}
+
+/// Return whether an IO error was caused by a read-only file system.
+fn is_read_only_fs(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    const CODES: &[i32] = &[30]; // EROFS
+    #[cfg(windows)]
+    const CODES: &[i32] = &[19]; // ERROR_WRITE_PROTECT
+    #[cfg(not(any(unix, windows)))]
+    const CODES: &[i32] = &[];
+
+    error
+        .raw_os_error()
+        .map_or(false, |code| CODES.contains(&code))
+}
+
+/// Set the permissions of the directory `dir` to `mode` (see
+/// `updates.cache_dir_mode`). This does nothing on platforms other than Unix.
+fn set_dir_mode(dir: &Path, mode: u32) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        debug!("Setting the permissions of {:?} to {mode:o}", dir);
+        fs::set_permissions(dir, fs::Permissions::from_mode(mode))
+            .with_context(|| format!("Could not set the permissions of {}", dir.display()))?;
+    }
+    #[cfg(not(unix))]
+    debug!(
+        "Ignoring the permissions {mode:o} for {:?}, only supported on Unix",
+        dir
+    );
+    Ok(())
+}
+
+/// Make sure that files can be written to the cache directory `dir`, which is
+/// created if it does not exist yet.
+fn ensure_writable(dir: &Path) -> Result<()> {
+    let probe = dir.join(WRITE_PROBE_FILE);
+    let result = fs::create_dir_all(dir).and_then(|()| File::create(&probe).map(drop));
+    match result {
+        Ok(()) => {
+            fs::remove_file(&probe).ok();
+            Ok(())
+        }
+        Err(e) if is_read_only_fs(&e) || e.kind() == io::ErrorKind::PermissionDenied => {
+            let reason = if is_read_only_fs(&e) {
+                "is on a read-only file system"
+            } else {
+                "is not writable"
+            };
+            Err(anyhow::Error::new(e).context(format!(
+                "The cache directory `{}` {reason}. \
+                 Set ${CACHE_DIR_ENV_VAR} to use a different cache directory.",
+                dir.display()
+            )))
+        }
+        Err(e) => Err(anyhow::Error::new(e).context(format!(
+            "Could not write to the cache directory `{}`",
+            dir.display()
+        ))),
+    }
+}
+
+/// Return whether the archive contains any files below `dir`.
+fn archive_contains_dir<R: Read + Seek>(archive: &mut ZipArchive<R>, dir: &Path) -> bool {
+    archive.file_names().any(|name| {
+        Path::new(name)
+            .strip_prefix(dir)
+            .map_or(false, |rest| !rest.as_os_str().is_empty())
+    })
+}
+
+/// Return the directory within the archive that contains the pages
+/// directories (`pages`, `pages.de`, ...).
+///
+/// This is the root directory of the archive if it contains a `pages`
+/// directory. Otherwise, the least nested directory containing a `pages`
+/// directory is used (e.g. `tldr-main` in an archive of the tldr
+/// repository). If there is none, the root directory is used as well.
+fn detect_pages_subdir<R: Read + Seek>(archive: &mut ZipArchive<R>) -> PathBuf {
+    let mut subdirs: Vec<PathBuf> = archive
+        .file_names()
+        .filter_map(|name| {
+            let components: Vec<&str> = name.split('/').collect();
+            let pages_index = components.iter().position(|c| *c == "pages")?;
+            // The `pages` directory must not be the file name itself
+            (pages_index + 1 < components.len())
+                .then(|| components[..pages_index].iter().collect::<PathBuf>())
+        })
+        .collect();
+    subdirs.sort_by_key(|subdir| subdir.components().count());
+    subdirs.into_iter().next().unwrap_or_default()
+}
+
+/// Extract the contents of the directory `subdir` of `archive` into
+/// `target_dir`, optionally only the pages directories in `language_dirs`.
+/// If the extraction fails, `target_dir` is removed again.
+fn extract_archive<R, W, F>(
+    archive: &mut ZipArchive<R>,
+    target_dir: &Path,
+    subdir: &Path,
+    language_dirs: Option<&[String]>,
+    create_file: F,
+) -> Result<()>
+where
+    R: Read + Seek,
+    W: Write,
+    F: FnMut(&Path) -> io::Result<W>,
+{
+    let result = write_archive_files(archive, target_dir, subdir, &[], language_dirs, create_file);
+    if result.is_err() && target_dir.exists() {
+        if let Err(e) = fs::remove_dir_all(target_dir) {
+            warn!(
+                "Could not remove the partially extracted pages at {}: {e}",
+                target_dir.display()
+            );
+        }
+    }
+    result
 }
 
 /// Unit Tests for cache module
@@ -439,6 +2226,57 @@ mod tests {
         assert_eq!(&buf, b"Hello\n\nWorld");
     }
 
+    #[test]
+    fn test_lookup_result_accessors() {
+        let result = PageLookupResult::with_candidate(
+            PageCandidate::new(PathBuf::from("pages.de/linux/tar.md"))
+                .with_platform("linux")
+                .with_language("de"),
+        )
+        .with_candidates([
+            PageCandidate::new(PathBuf::from("pages/linux/tar.md"))
+                .with_platform("linux")
+                .with_language("en"),
+            PageCandidate::new(PathBuf::from("pages/common/tar.md"))
+                .with_platform("common")
+                .with_language("en"),
+        ])
+        .with_source(PageSource::Cache);
+
+        assert_eq!(result.primary_path(), Path::new("pages.de/linux/tar.md"));
+        assert_eq!(result.patch_path(), None);
+        assert_eq!(result.candidates().len(), 3);
+        assert_eq!(result.platforms(), ["linux", "common"]);
+        assert_eq!(result.languages(), ["de", "en"]);
+        assert_eq!(result.source(), PageSource::Cache);
+
+        let result = PageLookupResult::with_page(PathBuf::from("foo.md"));
+        assert_eq!(result.source(), PageSource::File);
+        assert!(result.platforms().is_empty());
+    }
+
+    #[test]
+    fn test_lookup_result_source_url() {
+        let result = PageLookupResult::with_candidate(
+            PageCandidate::new(PathBuf::from("/cache/tldr-pages/pages.de/linux/tar.md"))
+                .with_platform("linux")
+                .with_language("de"),
+        )
+        .with_source(PageSource::Cache);
+        assert_eq!(
+            result.source_url().as_deref(),
+            Some("https://github.com/tldr-pages/tldr/blob/main/pages.de/linux/tar.md")
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let page_path = dir.path().join("my page.page");
+        File::create(&page_path).unwrap();
+        let result = PageLookupResult::with_page(page_path).with_source(PageSource::Custom);
+        let url = result.source_url().unwrap();
+        assert!(url.starts_with("file:///"), "{url}");
+        assert!(url.ends_with("/my%20page.page"), "{url}");
+    }
+
     #[test]
     fn test_reader_without_patch() {
         // Write test file
@@ -459,4 +2297,291 @@ mod tests {
 
         assert_eq!(&buf, b"Hello\n");
     }
+
+    #[test]
+    fn test_ensure_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().join("cache");
+        assert!(ensure_writable(&cache_dir).is_ok());
+        assert!(cache_dir.is_dir());
+        assert!(!cache_dir.join(WRITE_PROBE_FILE).exists());
+    }
+
+    #[test]
+    fn test_normalize_separators() {
+        assert_eq!(normalize_separators("git-log"), "git-log");
+        assert_eq!(normalize_separators("git_log"), "git-log");
+        assert_eq!(normalize_separators("git log"), "git-log");
+        assert_eq!(normalize_separators(" git__log -- x"), "git-log-x");
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_ensure_writable_read_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o555)).unwrap();
+
+        // Permissions are not enforced for privileged users
+        if File::create(dir.path().join("probe")).is_ok() {
+            return;
+        }
+
+        let message = format!("{:#}", ensure_writable(dir.path()).unwrap_err());
+        assert!(message.contains("is not writable"), "{message}");
+        assert!(message.contains("TEALDEER_CACHE_DIR"), "{message}");
+
+        fs::set_permissions(dir.path(), fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn test_with_archive_extension() {
+        let zip = b"PK\x03\x04rest";
+        assert_eq!(
+            with_archive_extension(Path::new("/tmp/tldr"), zip),
+            Path::new("/tmp/tldr.zip")
+        );
+        assert_eq!(
+            with_archive_extension(Path::new("/tmp/tldr.zip"), zip),
+            Path::new("/tmp/tldr.zip")
+        );
+        assert_eq!(
+            with_archive_extension(Path::new("tldr.tar"), &[0x1f, 0x8b, 0x08]),
+            Path::new("tldr.tar.gz")
+        );
+        assert_eq!(
+            with_archive_extension(Path::new("tldr"), b"<html>"),
+            Path::new("tldr")
+        );
+    }
+
+    #[test]
+    fn test_verify_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let page_path = dir.path().join("tar.md");
+
+        fs::write(
+            &page_path,
+            "# tar\n\n> Archiving utility.\n\n- Create:\n\n`tar cf {{a}}`\n",
+        )
+        .unwrap();
+        assert!(verify_page(&page_path).is_ok());
+
+        fs::write(&page_path, "# tar\n\n> Archiving utility.\n").unwrap();
+        assert!(verify_page(&page_path).is_err());
+
+        fs::write(&page_path, [0xff, 0xfe, 0x00]).unwrap();
+        assert!(verify_page(&page_path).is_err());
+
+        assert!(verify_page(&dir.path().join("missing.md")).is_err());
+    }
+
+    #[test]
+    fn test_page_url() {
+        let lang_dirs = Cache::language_dirs(&["de".to_string(), "en".to_string()]);
+        assert_eq!(lang_dirs, ["pages.de", "pages"]);
+        assert_eq!(
+            Cache::page_url(&lang_dirs[0], "linux", "tar.md"),
+            "https://raw.githubusercontent.com/tldr-pages/tldr/main/pages.de/linux/tar.md"
+        );
+    }
+
+    #[test]
+    fn test_parse_delta_manifest() {
+        let manifest =
+            DeltaManifest::parse("base 1\nversion 2\nremove pages/common/foo.md\n").unwrap();
+        assert_eq!(
+            manifest,
+            DeltaManifest {
+                base: "1".into(),
+                version: "2".into(),
+                removed: vec![PathBuf::from("pages/common/foo.md")],
+            }
+        );
+
+        assert!(DeltaManifest::parse("version 2\n").is_err());
+        assert!(DeltaManifest::parse("base 1\nversion 2\nremove ../foo.md\n").is_err());
+        assert!(DeltaManifest::parse("base 1\nversion 2\nrename foo.md\n").is_err());
+    }
+
+    fn delta_archive(manifest: &str, files: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = zip::write::FileOptions::default();
+        writer.start_file("MANIFEST", options).unwrap();
+        writer.write_all(manifest.as_bytes()).unwrap();
+        for (name, contents) in files {
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    /// A writer that fails because the disk is full.
+    struct DiskFullWriter;
+
+    impl Write for DiskFullWriter {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::from_raw_os_error(28))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_extract_archive_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let target_dir = dir.path().join("pages");
+        let bytes = delta_archive("", &[("pages/common/foo.md", "foo")]);
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+
+        let error = extract_archive(&mut archive, &target_dir, Path::new(""), None, |_| {
+            Ok(DiskFullWriter)
+        })
+        .unwrap_err();
+        if cfg!(unix) {
+            assert_eq!(
+                error.to_string(),
+                "Not enough disk space to extract the pages"
+            );
+        }
+
+        // The partially extracted pages are removed
+        assert!(!target_dir.exists());
+
+        // Without errors, all files are extracted
+        extract_archive(&mut archive, &target_dir, Path::new(""), None, |path| {
+            File::create(path)
+        })
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(target_dir.join("pages/common/foo.md")).unwrap(),
+            "foo"
+        );
+    }
+
+    #[test]
+    fn test_pages_subdir() {
+        let detect = |files: &[(&str, &str)]| {
+            let bytes = delta_archive("", files);
+            detect_pages_subdir(&mut ZipArchive::new(Cursor::new(bytes)).unwrap())
+        };
+        assert_eq!(detect(&[("pages/common/foo.md", "foo")]), PathBuf::new());
+        assert_eq!(
+            detect(&[
+                ("tldr-main/README.md", ""),
+                ("tldr-main/pages/common/foo.md", "foo"),
+                ("tldr-main/pages.de/common/foo.md", "foo"),
+            ]),
+            PathBuf::from("tldr-main")
+        );
+        assert_eq!(
+            detect(&[
+                ("docs/tldr/pages/common/foo.md", "foo"),
+                ("docs/tldr/tests/pages/common/bar.md", "bar"),
+            ]),
+            PathBuf::from("docs/tldr")
+        );
+        assert_eq!(detect(&[("README.md", "")]), PathBuf::new());
+
+        // Only the pages below the subdirectory are extracted
+        let dir = tempfile::tempdir().unwrap();
+        let target_dir = dir.path().join("pages");
+        let bytes = delta_archive(
+            "",
+            &[
+                ("docs/tldr/pages/common/foo.md", "foo"),
+                ("docs/README.md", "readme"),
+            ],
+        );
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        assert!(archive_contains_dir(&mut archive, Path::new("docs/tldr")));
+        assert!(!archive_contains_dir(
+            &mut archive,
+            Path::new("docs/tldr/pages/common/foo.md")
+        ));
+        assert!(!archive_contains_dir(&mut archive, Path::new("other")));
+        extract_archive(
+            &mut archive,
+            &target_dir,
+            Path::new("docs/tldr"),
+            None,
+            |path| File::create(path),
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read_to_string(target_dir.join("pages/common/foo.md")).unwrap(),
+            "foo"
+        );
+        assert!(!target_dir.join("README.md").exists());
+        assert!(!target_dir.join("docs").exists());
+    }
+
+    #[test]
+    fn test_update_languages() {
+        let dir = tempfile::tempdir().unwrap();
+        let target_dir = dir.path().join("pages");
+        let bytes = delta_archive(
+            "",
+            &[
+                ("LICENSE.md", "license"),
+                ("pages/common/foo.md", "foo"),
+                ("pages.de/common/foo.md", "foo de"),
+                ("pages.fr/common/foo.md", "foo fr"),
+            ],
+        );
+        let mut archive = ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let cache =
+            Cache::new("", PlatformType::Linux).with_update_languages(Some(vec!["de".to_string()]));
+        extract_archive(
+            &mut archive,
+            &target_dir,
+            Path::new(""),
+            cache.update_language_dirs().as_deref(),
+            |path| File::create(path),
+        )
+        .unwrap();
+        assert!(target_dir.join("LICENSE.md").exists());
+        assert!(target_dir.join("pages/common/foo.md").exists());
+        assert!(target_dir.join("pages.de/common/foo.md").exists());
+        assert!(!target_dir.join("pages.fr").exists());
+    }
+
+    #[test]
+    fn test_apply_delta() {
+        let dir = tempfile::tempdir().unwrap();
+        let common = dir.path().join("pages").join("common");
+        fs::create_dir_all(&common).unwrap();
+        fs::write(common.join("foo.md"), "foo").unwrap();
+        fs::write(common.join("bar.md"), "bar").unwrap();
+
+        let delta = delta_archive(
+            "base 1\nversion 2\nremove pages/common/foo.md\n",
+            &[
+                ("pages/common/bar.md", "new bar"),
+                ("pages/linux/baz.md", "baz"),
+            ],
+        );
+
+        // A delta based on another version is not applied
+        assert!(!apply_delta(dir.path(), delta.clone(), "0", None).unwrap());
+        assert!(common.join("foo.md").exists());
+
+        assert!(apply_delta(dir.path(), delta, "1", None).unwrap());
+        assert!(!common.join("foo.md").exists());
+        assert_eq!(
+            fs::read_to_string(common.join("bar.md")).unwrap(),
+            "new bar"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("pages/linux/baz.md")).unwrap(),
+            "baz"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join(VERSION_FILE)).unwrap(),
+            "2"
+        );
+    }
 }
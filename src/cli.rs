@@ -1,10 +1,13 @@
 //! Definition of the CLI arguments and options.
 
-use std::path::PathBuf;
+use std::{num::NonZeroUsize, path::PathBuf};
 
 use clap::{AppSettings, ArgGroup, Parser};
 
-use crate::types::{ColorOptions, PlatformType};
+use crate::{
+    config::StyleConfig,
+    types::{ColorDepth, ColorOptions, OutputFormat, PlatformType, Theme},
+};
 
 // Note: flag names are specified explicitly in clap attributes
 // to improve readability and allow contributors to grep names like "clear-cache"
@@ -16,7 +19,7 @@ use crate::types::{ColorOptions, PlatformType};
 #[clap(setting = AppSettings::DeriveDisplayOrder)]
 #[clap(arg_required_else_help(true))]
 #[clap(disable_colored_help(true))]
-#[clap(group = ArgGroup::new("command_or_file").args(&["command", "render"]))]
+#[clap(group = ArgGroup::new("command_or_file").args(&["command", "render", "demo"]))]
 pub(crate) struct Args {
     /// The command to show (e.g. `tar` or `git log`)
     #[clap(min_values = 1)]
@@ -26,7 +29,55 @@ pub(crate) struct Args {
     #[clap(short = 'l', long = "list")]
     pub list: bool,
 
-    /// Render a specific markdown file
+    /// Sort the listed commands (the whole list is collected before printing)
+    #[clap(long = "sorted", requires = "list")]
+    pub sorted: bool,
+
+    /// Print the number of commands in the cache
+    #[clap(long = "count", conflicts_with = "list")]
+    pub count: bool,
+
+    /// Separate the listed commands with NUL characters instead of newlines
+    #[clap(short = '0', long = "print0", requires = "list")]
+    pub print0: bool,
+
+    /// Print the description of every listed command next to its name
+    #[clap(
+        long = "with-description",
+        requires = "list",
+        conflicts_with = "print0"
+    )]
+    pub with_description: bool,
+
+    /// Search all pages for the given text
+    #[clap(long = "search", value_name = "QUERY")]
+    pub search: Option<String>,
+
+    /// Number of threads to use for `--search` [default: number of CPUs]
+    #[clap(long = "concurrency", value_name = "N", requires = "search")]
+    pub concurrency: Option<NonZeroUsize>,
+
+    /// Show the cache contents as a tree of languages and platforms
+    #[clap(long = "tree")]
+    pub tree: bool,
+
+    /// Print the upstream ref or version the cache was downloaded for
+    #[clap(long = "cache-version")]
+    pub cache_version: bool,
+
+    /// Export all pages into a single markdown file
+    #[clap(long = "export", value_name = "FILE", conflicts_with_all = &["command", "render"])]
+    pub export: Option<PathBuf>,
+
+    /// Export every page into an HTML file in the given directory
+    #[clap(
+        long = "export-html",
+        value_name = "DIR",
+        conflicts_with_all = &["command", "render", "export"]
+    )]
+    pub export_html: Option<PathBuf>,
+
+    /// Render a specific markdown file, or all files matching a glob pattern
     #[clap(
         short = 'f',
         long = "render",
@@ -35,6 +86,54 @@ pub(crate) struct Args {
     )]
     pub render: Option<PathBuf>,
 
+    /// Re-render the file passed to `--render` whenever it changes
+    #[cfg(feature = "watch")]
+    #[clap(long = "watch", requires = "render")]
+    pub watch: bool,
+
+    /// Render a built-in example page, to preview the configured styles
+    #[clap(long = "demo")]
+    pub demo: bool,
+
+    /// Show the examples in reverse order
+    #[clap(long = "reverse")]
+    pub reverse: bool,
+
+    /// Cut the rendered page off after the given number of lines
+    #[clap(long = "max-lines", value_name = "N", conflicts_with = "raw")]
+    pub max_lines: Option<usize>,
+
+    /// Prefix every rendered line with its line number
+    #[clap(long = "line-numbers")]
+    pub line_numbers: bool,
+
+    /// Print the detected terminal width and the width used for rendering to
+    /// stderr
+    #[clap(long = "debug-width")]
+    pub debug_width: bool,
+
+    /// Print the snippets that a page is split into for highlighting, instead
+    /// of rendering it (for debugging)
+    #[clap(
+        long = "debug-tokens",
+        requires = "command_or_file",
+        conflicts_with = "raw",
+        hide = true
+    )]
+    pub debug_tokens: bool,
+
+    /// Select the example with the given number (starting at 1)
+    #[clap(
+        long = "example",
+        value_name = "NUMBER",
+        requires_all = &["command_or_file", "raw-command"]
+    )]
+    pub example: Option<usize>,
+
+    /// Print only the plain command of the example selected with `--example`
+    #[clap(long = "raw-command", requires = "example")]
+    pub raw_command: bool,
+
     /// Override the operating system
     #[clap(
         short = 'p',
@@ -56,10 +155,38 @@ pub(crate) struct Args {
     #[clap(short = 'L', long = "language")]
     pub language: Option<String>,
 
+    /// Show the pages of all platforms, one after another
+    #[clap(long = "all-platforms", requires = "command")]
+    pub all_platforms: bool,
+
+    /// Print the paths that are considered for the page, in order of precedence
+    #[clap(long = "explain", requires = "command")]
+    pub explain: bool,
+
+    /// Download a page from upstream if it is not found in the cache
+    #[clap(long = "fetch-missing", requires = "command")]
+    pub fetch_missing: bool,
+
+    /// Only look up the page in the page source with the given name
+    #[clap(long = "source", value_name = "NAME", requires = "command")]
+    pub source: Option<String>,
+
+    /// Only show the examples containing the given keyword
+    #[clap(long = "grep", value_name = "KEYWORD", requires = "command_or_file")]
+    pub grep: Option<String>,
+
+    /// Edit the custom page of the command in `$VISUAL` or `$EDITOR`
+    #[clap(long = "edit", requires = "command")]
+    pub edit: bool,
+
     /// Update the local cache
     #[clap(short = 'u', long = "update")]
     pub update: bool,
 
+    /// Save a copy of the downloaded archive at the given path
+    #[clap(long = "keep-archive", value_name = "PATH", requires = "update")]
+    pub keep_archive: Option<PathBuf>,
+
     /// If auto update is configured, disable it for this run
     #[clap(long = "no-auto-update", requires = "command_or_file")]
     pub no_auto_update: bool,
@@ -68,6 +195,10 @@ pub(crate) struct Args {
     #[clap(short = 'c', long = "clear-cache")]
     pub clear_cache: bool,
 
+    /// With `--clear-cache`, only print what would be deleted
+    #[clap(long = "dry-run", requires = "clear-cache")]
+    pub dry_run: bool,
+
     /// Use a pager to page output
     #[clap(long = "pager", requires = "command_or_file")]
     pub pager: bool,
@@ -93,14 +224,50 @@ pub(crate) struct Args {
     #[clap(long = "show-paths")]
     pub show_paths: bool,
 
+    /// Print the languages that pages are looked up in, in order of precedence
+    #[clap(long = "show-languages")]
+    pub show_languages: bool,
+
+    /// Print machine readable output: the paths of `--show-paths` one per
+    /// line (`text`, only with `--show-paths`), or as a JSON object along
+    /// with JSON warnings (`json`)
+    #[clap(
+        long = "format",
+        value_name = "FORMAT",
+        possible_values = ["text", "json"]
+    )]
+    pub format: Option<OutputFormat>,
+
     /// Show config file path
     #[clap(long = "config-path")]
     pub config_path: bool,
 
+    /// Show the resolved style for the given line type and exit
+    #[clap(
+        long = "show-style",
+        value_name = "TARGET",
+        possible_values = StyleConfig::TARGETS
+    )]
+    pub show_style: Option<String>,
+
+    /// Print a shell function that wraps `tldr` and updates the cache once per
+    /// day
+    #[clap(
+        long = "print-shell-hook",
+        value_name = "SHELL",
+        possible_values = ["bash", "zsh", "fish"]
+    )]
+    pub print_shell_hook: Option<String>,
+
     /// Create a basic config
     #[clap(long = "seed-config")]
     pub seed_config: bool,
 
+    /// Warn about an invalid config file and use the default config instead
+    /// of exiting
+    #[clap(long = "ignore-bad-config")]
+    pub ignore_bad_config: bool,
+
     /// Control whether to use color
     #[clap(
         long = "color",
@@ -109,6 +276,22 @@ pub(crate) struct Args {
     )]
     pub color: Option<ColorOptions>,
 
+    /// Override the detected number of colors the terminal supports
+    #[clap(
+        long = "color-depth",
+        value_name = "DEPTH",
+        possible_values = ["truecolor", "256", "16"]
+    )]
+    pub color_depth: Option<ColorDepth>,
+
+    /// Use the default styles for a dark or light terminal background
+    #[clap(
+        long = "theme",
+        value_name = "THEME",
+        possible_values = ["dark", "light", "auto"]
+    )]
+    pub theme: Option<Theme>,
+
     /// Print the version
     // Note: We override the version flag because clap uses `-V` by default,
     // while TLDR specification requires `-v` to be used.
@@ -1,19 +1,26 @@
 use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashSet},
     env, fs,
     io::{Read, Write},
-    path::PathBuf,
+    path::{Component, Path, PathBuf},
     time::Duration,
 };
 
 use ansi_term::{Color, Style};
-use anyhow::{ensure, Context, Result};
+use anyhow::{bail, ensure, Context, Result};
 use app_dirs::{get_app_root, AppDataType};
 use log::debug;
 use serde_derive::{Deserialize, Serialize};
+use unicode_width::UnicodeWidthStr;
 
-use crate::types::PathSource;
+use crate::{
+    split_command::split_command,
+    types::{ColorDepth, ColorOptions, PathSource, Theme},
+};
 
 pub const CONFIG_FILE_NAME: &str = "config.toml";
+pub const LOCAL_CONFIG_FILE_NAME: &str = "config.local.toml";
 pub const MAX_CACHE_AGE: Duration = Duration::from_secs(2_592_000); // 30 days
 const DEFAULT_UPDATE_INTERVAL_HOURS: u64 = MAX_CACHE_AGE.as_secs() / 3600; // 30 days
 
@@ -29,6 +36,50 @@ fn default_italic() -> bool {
     false
 }
 
+fn default_empty_page_note() -> bool {
+    true
+}
+
+fn default_callout_marker() -> String {
+    "│ ".to_string()
+}
+
+fn default_inline_delimiter() -> String {
+    " ".to_string()
+}
+
+const fn default_max_list_name_width() -> usize {
+    30
+}
+
+const fn default_max_align_width() -> usize {
+    40
+}
+
+fn default_command_prefix() -> String {
+    "      ".to_string()
+}
+
+const fn default_min_wrap_width() -> usize {
+    40
+}
+
+fn default_case_insensitive() -> bool {
+    true
+}
+
+fn default_normalize_separators() -> bool {
+    true
+}
+
+fn default_source_enabled() -> bool {
+    true
+}
+
+fn default_style_placeholders() -> bool {
+    true
+}
+
 #[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "lowercase")]
 pub enum RawColor {
@@ -61,10 +112,73 @@ impl From<RawColor> for Color {
     }
 }
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+/// A color in a style, either a literal color or a palette alias.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(untagged)]
+pub enum RawColorRef {
+    Color(RawColor),
+    /// A hex color (e.g. `#ff8800`) or the name of a palette entry
+    Name(String),
+}
+
+impl From<RawColor> for RawColorRef {
+    fn from(raw_color: RawColor) -> Self {
+        Self::Color(raw_color)
+    }
+}
+
+/// Named colors that can be referenced in styles.
+type Palette = BTreeMap<String, RawColorRef>;
+
+/// Parse a hex color of the form `#rrggbb`.
+fn parse_hex_color(hex: &str) -> Option<Color> {
+    let digits = hex.strip_prefix('#')?;
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&digits[i..i + 2], 16).ok();
+    Some(Color::RGB(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Parse file permissions written as octal digits (e.g. `750` or `0o750`).
+fn parse_mode(mode: &str) -> Option<u32> {
+    let digits = mode.strip_prefix("0o").unwrap_or(mode);
+    if digits.is_empty() || !digits.chars().all(|c| matches!(c, '0'..='7')) {
+        return None;
+    }
+    u32::from_str_radix(digits, 8)
+        .ok()
+        .filter(|&mode| mode <= 0o7777)
+}
+
+impl RawColorRef {
+    /// Resolve the color, looking up aliases in `palette`.
+    fn resolve(&self, palette: &Palette) -> Result<Color> {
+        match self {
+            Self::Color(raw_color) => Ok(Color::from(*raw_color)),
+            Self::Name(name) if name.starts_with('#') => {
+                parse_hex_color(name).with_context(|| format!("Invalid hex color {name:?}"))
+            }
+            Self::Name(name) => match palette.get(name) {
+                Some(Self::Color(raw_color)) => Ok(Color::from(*raw_color)),
+                Some(Self::Name(hex)) if hex.starts_with('#') => {
+                    parse_hex_color(hex).with_context(|| {
+                        format!("Invalid hex color {hex:?} in palette entry {name:?}")
+                    })
+                }
+                Some(Self::Name(other)) => bail!(
+                    "Palette entry {name:?} refers to {other:?}, but palette entries must be colors"
+                ),
+                None => bail!("Unknown color or palette alias {name:?}"),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 struct RawStyle {
-    pub foreground: Option<RawColor>,
-    pub background: Option<RawColor>,
+    pub foreground: Option<RawColorRef>,
+    pub background: Option<RawColorRef>,
     #[serde(default = "default_underline")]
     pub underline: bool,
     #[serde(default = "default_bold")]
@@ -86,31 +200,32 @@ impl Default for RawStyle {
     }
 }
 
-impl From<RawStyle> for Style {
-    fn from(raw_style: RawStyle) -> Self {
-        let mut style = Self::default();
+impl RawStyle {
+    /// Convert to a `Style`, resolving palette aliases in `palette`.
+    fn resolve(&self, palette: &Palette) -> Result<Style> {
+        let mut style = Style::default();
 
-        if let Some(foreground) = raw_style.foreground {
-            style = style.fg(Color::from(foreground));
+        if let Some(ref foreground) = self.foreground {
+            style = style.fg(foreground.resolve(palette).context("Invalid foreground")?);
         }
 
-        if let Some(background) = raw_style.background {
-            style = style.on(Color::from(background));
+        if let Some(ref background) = self.background {
+            style = style.on(background.resolve(palette).context("Invalid background")?);
         }
 
-        if raw_style.underline {
+        if self.underline {
             style = style.underline();
         }
 
-        if raw_style.bold {
+        if self.bold {
             style = style.bold();
         }
 
-        if raw_style.italic {
+        if self.italic {
             style = style.italic();
         }
 
-        style
+        Ok(style)
     }
 }
 
@@ -126,14 +241,167 @@ struct RawStyleConfig {
     pub example_code: RawStyle,
     #[serde(default)]
     pub example_variable: RawStyle,
+    #[serde(default)]
+    pub callout: RawStyle,
+    #[serde(default)]
+    pub aliases: RawStyle,
+    #[serde(default)]
+    pub page_header: RawStyle,
+    #[serde(default)]
+    pub background: RawStyle,
+    #[serde(default)]
+    pub line_number: RawStyle,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+/// How to handle rendered lines that are wider than the terminal.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Overflow {
+    /// Wrap the line at the terminal width
+    Wrap,
+    /// Cut the line at the terminal width and append an ellipsis
+    Truncate,
+    /// Leave the line as it is, the terminal will wrap it
+    #[default]
+    None,
+}
+
+/// How to change the case of placeholders (e.g. `{{file}}`) in examples.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaceholderCase {
+    /// Keep the case of the page
+    #[default]
+    None,
+    /// Convert placeholders to uppercase
+    Upper,
+    /// Convert placeholders to lowercase
+    Lower,
+}
+
+impl PlaceholderCase {
+    /// Apply the case conversion to the placeholder text `s`.
+    pub fn apply(self, s: &str) -> Cow<'_, str> {
+        match self {
+            Self::None => Cow::Borrowed(s),
+            Self::Upper => Cow::Owned(s.to_uppercase()),
+            Self::Lower => Cow::Owned(s.to_lowercase()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 struct RawDisplayConfig {
     #[serde(default)]
     pub compact: bool,
     #[serde(default)]
     pub use_pager: bool,
+    #[serde(default)]
+    pub overflow: Overflow,
+    #[serde(default)]
+    pub full_width_background: bool,
+    #[serde(default)]
+    pub show_title: bool,
+    #[serde(default)]
+    pub title_original: bool,
+    #[serde(default)]
+    pub title_link: bool,
+    #[serde(default = "default_callout_marker")]
+    pub callout_marker: String,
+    #[serde(default)]
+    pub section_rule: String,
+    #[serde(default = "default_empty_page_note")]
+    pub empty_page_note: bool,
+    #[serde(default)]
+    pub warn_unknown_syntax: bool,
+    #[serde(default)]
+    pub fill_from_english: bool,
+    #[serde(default)]
+    pub dedup_examples: bool,
+    #[serde(default)]
+    pub inline_examples: bool,
+    #[serde(default = "default_inline_delimiter")]
+    pub inline_delimiter: String,
+    #[serde(default)]
+    pub align_examples: bool,
+    #[serde(default = "default_max_align_width")]
+    pub max_align_width: usize,
+    #[serde(default = "default_command_prefix")]
+    pub command_prefix: String,
+    #[serde(default = "default_min_wrap_width")]
+    pub min_wrap_width: usize,
+    #[serde(default)]
+    pub color: Option<ColorOptions>,
+    #[serde(default)]
+    pub example_bullet: String,
+    #[serde(default)]
+    pub placeholder_case: PlaceholderCase,
+    #[serde(default)]
+    pub not_found_message: Option<String>,
+    #[serde(default)]
+    pub pager_command: Option<String>,
+    #[serde(default)]
+    pub post_render_command: Option<String>,
+    #[serde(default)]
+    pub surround_blank_lines: Option<usize>,
+    #[serde(default)]
+    pub theme: Theme,
+    #[serde(default)]
+    pub cache_rendered: bool,
+    #[serde(default)]
+    pub default_command: Option<String>,
+    #[serde(default = "default_style_placeholders")]
+    pub style_placeholders: bool,
+    #[serde(default)]
+    pub max_description_lines: Option<usize>,
+    #[serde(default)]
+    pub show_usage: bool,
+    #[serde(default = "default_max_list_name_width")]
+    pub max_list_name_width: usize,
+}
+
+impl Default for RawDisplayConfig {
+    fn default() -> Self {
+        Self {
+            compact: false,
+            use_pager: false,
+            overflow: Overflow::default(),
+            full_width_background: false,
+            show_title: false,
+            title_original: false,
+            title_link: false,
+            callout_marker: default_callout_marker(),
+            section_rule: String::new(),
+            empty_page_note: default_empty_page_note(),
+            warn_unknown_syntax: false,
+            fill_from_english: false,
+            dedup_examples: false,
+            inline_examples: false,
+            inline_delimiter: default_inline_delimiter(),
+            align_examples: false,
+            max_align_width: default_max_align_width(),
+            command_prefix: default_command_prefix(),
+            min_wrap_width: default_min_wrap_width(),
+            color: None,
+            example_bullet: String::new(),
+            placeholder_case: PlaceholderCase::default(),
+            not_found_message: None,
+            pager_command: None,
+            post_render_command: None,
+            surround_blank_lines: None,
+            theme: Theme::default(),
+            cache_rendered: false,
+            default_command: None,
+            style_placeholders: default_style_placeholders(),
+            max_description_lines: None,
+            show_usage: false,
+            max_list_name_width: default_max_list_name_width(),
+        }
+    }
+}
+
+const fn default_max_archive_size() -> u64 {
+    crate::cache::DEFAULT_MAX_ARCHIVE_SIZE
 }
 
 /// Serde doesn't support default values yet (tracking issue:
@@ -144,12 +412,43 @@ const fn default_auto_update_interval_hours() -> u64 {
     DEFAULT_UPDATE_INTERVAL_HOURS
 }
 
+/// The stream to print the warning about a stale cache to.
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum WarningStream {
+    #[default]
+    Stderr,
+    Stdout,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 struct RawUpdatesConfig {
     #[serde(default)]
     pub auto_update: bool,
     #[serde(default = "default_auto_update_interval_hours")]
     pub auto_update_interval_hours: u64,
+    #[serde(default)]
+    pub archive_url: Option<String>,
+    #[serde(default)]
+    pub archive_urls: Option<Vec<String>>,
+    #[serde(default)]
+    pub delta_url: Option<String>,
+    #[serde(default)]
+    pub auto_clear_after_hours: Option<u64>,
+    #[serde(default)]
+    pub quiet_success: bool,
+    #[serde(default)]
+    pub warning_stream: WarningStream,
+    #[serde(default = "default_max_archive_size")]
+    pub max_archive_size: u64,
+    #[serde(default)]
+    pub pages_subdir: Option<PathBuf>,
+    #[serde(default)]
+    pub languages: Option<Vec<String>>,
+    #[serde(default)]
+    pub archive_ref: Option<String>,
+    #[serde(default)]
+    pub cache_dir_mode: Option<String>,
 }
 
 impl Default for RawUpdatesConfig {
@@ -157,6 +456,17 @@ impl Default for RawUpdatesConfig {
         Self {
             auto_update: false,
             auto_update_interval_hours: DEFAULT_UPDATE_INTERVAL_HOURS,
+            archive_url: None,
+            archive_urls: None,
+            delta_url: None,
+            auto_clear_after_hours: None,
+            quiet_success: false,
+            warning_stream: WarningStream::default(),
+            max_archive_size: default_max_archive_size(),
+            pages_subdir: None,
+            languages: None,
+            archive_ref: None,
+            cache_dir_mode: None,
         }
     }
 }
@@ -165,6 +475,20 @@ impl Default for RawUpdatesConfig {
 struct RawDirectoriesConfig {
     #[serde(default)]
     pub custom_pages_dir: Option<PathBuf>,
+    #[serde(default)]
+    pub platform_fallback: Option<Vec<String>>,
+    #[serde(default)]
+    pub wsl_prefer_windows: bool,
+    #[serde(default)]
+    pub resolve_aliases: bool,
+    #[serde(default)]
+    pub merge_common: bool,
+    #[serde(default = "default_case_insensitive")]
+    pub case_insensitive: bool,
+    #[serde(default = "default_normalize_separators")]
+    pub normalize_separators: bool,
+    #[serde(default)]
+    pub skip_broken_symlinks: bool,
 }
 
 impl Default for RawDirectoriesConfig {
@@ -176,17 +500,36 @@ impl Default for RawDirectoriesConfig {
                     path.join("pages").join("")
                 })
                 .ok(),
+            platform_fallback: None,
+            wsl_prefer_windows: false,
+            resolve_aliases: false,
+            merge_common: false,
+            case_insensitive: default_case_insensitive(),
+            normalize_separators: default_normalize_separators(),
+            skip_broken_symlinks: false,
         }
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+struct RawSourceConfig {
+    pub name: String,
+    pub path: PathBuf,
+    #[serde(default = "default_source_enabled")]
+    pub enabled: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(default)]
 struct RawConfig {
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    palette: Palette,
     style: RawStyleConfig,
     display: RawDisplayConfig,
     updates: RawUpdatesConfig,
     directories: RawDirectoriesConfig,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    sources: Vec<RawSourceConfig>,
 }
 
 impl RawConfig {
@@ -198,18 +541,25 @@ impl RawConfig {
 impl Default for RawConfig {
     fn default() -> Self {
         let mut raw_config = RawConfig {
+            palette: Palette::new(),
             style: RawStyleConfig::default(),
             display: RawDisplayConfig::default(),
             updates: RawUpdatesConfig::default(),
             directories: RawDirectoriesConfig::default(),
+            sources: vec![],
         };
 
         // Set default config
-        raw_config.style.example_text.foreground = Some(RawColor::Green);
-        raw_config.style.command_name.foreground = Some(RawColor::Cyan);
-        raw_config.style.example_code.foreground = Some(RawColor::Cyan);
-        raw_config.style.example_variable.foreground = Some(RawColor::Cyan);
+        raw_config.style.example_text.foreground = Some(RawColor::Green.into());
+        raw_config.style.command_name.foreground = Some(RawColor::Cyan.into());
+        raw_config.style.example_code.foreground = Some(RawColor::Cyan.into());
+        raw_config.style.example_variable.foreground = Some(RawColor::Cyan.into());
         raw_config.style.example_variable.underline = true;
+        raw_config.style.callout.italic = true;
+        raw_config.style.aliases.bold = true;
+        raw_config.style.page_header.bold = true;
+        raw_config.style.page_header.underline = true;
+        raw_config.style.line_number.foreground = Some(RawColorRef::Name("#808080".into()));
 
         raw_config
     }
@@ -222,23 +572,172 @@ pub struct StyleConfig {
     pub example_text: Style,
     pub example_code: Style,
     pub example_variable: Style,
+    pub callout: Style,
+    pub aliases: Style,
+    pub page_header: Style,
+    pub background: Style,
+    pub line_number: Style,
+}
+
+impl StyleConfig {
+    /// The names of the style targets, as used in the `[style]` config section.
+    pub const TARGETS: [&'static str; 10] = [
+        "description",
+        "command_name",
+        "example_text",
+        "example_code",
+        "example_variable",
+        "callout",
+        "aliases",
+        "page_header",
+        "background",
+        "line_number",
+    ];
+
+    /// Return the style for the target with the given name.
+    pub fn get(&self, target: &str) -> Option<Style> {
+        match target {
+            "description" => Some(self.description),
+            "command_name" => Some(self.command_name),
+            "example_text" => Some(self.example_text),
+            "example_code" => Some(self.example_code),
+            "example_variable" => Some(self.example_variable),
+            "callout" => Some(self.callout),
+            "aliases" => Some(self.aliases),
+            "page_header" => Some(self.page_header),
+            "background" => Some(self.background),
+            "line_number" => Some(self.line_number),
+            _ => None,
+        }
+    }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// Describe the attributes of a style in a human readable way, one per line.
+pub fn describe_style(style: &Style) -> String {
+    let color = |color: Option<Color>| color.map_or_else(|| "none".into(), |c| format!("{c:?}"));
+    format!(
+        "foreground: {}\nbackground: {}\nbold: {}\nitalic: {}\nunderline: {}",
+        color(style.foreground),
+        color(style.background),
+        style.is_bold,
+        style.is_italic,
+        style.is_underline,
+    )
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct DisplayConfig {
     pub compact: bool,
     pub use_pager: bool,
+    pub overflow: Overflow,
+    /// Fill every rendered line up to the terminal width with the
+    /// `[style.background]` style.
+    pub full_width_background: bool,
+    pub show_title: bool,
+    pub title_original: bool,
+    /// Whether to render the title as a link to the page's source.
+    pub title_link: bool,
+    pub callout_marker: String,
+    /// Repeated to draw a rule between the description and the examples (if
+    /// not empty).
+    pub section_rule: String,
+    pub empty_page_note: bool,
+    /// Print a note if a page contains lines that cannot be parsed (e.g.
+    /// because of a newer page format).
+    pub warn_unknown_syntax: bool,
+    /// Append the examples of the English page that are missing from a
+    /// translated page.
+    pub fill_from_english: bool,
+    /// Only render the first of several examples with the same commands.
+    pub dedup_examples: bool,
+    pub inline_examples: bool,
+    pub inline_delimiter: String,
+    /// Render examples inline, with the commands aligned in a column.
+    pub align_examples: bool,
+    /// The maximum width that example texts are padded to when aligning.
+    pub max_align_width: usize,
+    pub command_prefix: String,
+    pub min_wrap_width: usize,
+    /// The color option to use if `--color` is not passed
+    pub color: ColorOptions,
+    pub example_bullet: String,
+    pub placeholder_case: PlaceholderCase,
+    /// Replaces the message that is printed if a page is not found. A
+    /// `{{command}}` placeholder is replaced with the name of the page.
+    pub not_found_message: Option<String>,
+    /// The pager command, which takes precedence over `$PAGER`.
+    pub pager_command: Option<String>,
+    /// A command that the rendered page is piped through before it is
+    /// printed.
+    pub post_render_command: Option<String>,
+    /// The number of blank lines before and after the rendered page, or
+    /// `None` to keep the blank lines of the page as they are.
+    pub surround_blank_lines: Option<usize>,
+    /// The theme to use if `--theme` is not passed
+    pub theme: Theme,
+    /// Whether rendered pages are stored in the render cache and shown from
+    /// there when they are rendered again.
+    pub cache_rendered: bool,
+    /// The page that is shown if `tldr` is run without any arguments.
+    pub default_command: Option<String>,
+    pub style_placeholders: bool,
+    pub max_description_lines: Option<usize>,
+    /// Render a usage line derived from the first example command.
+    pub show_usage: bool,
+    /// The maximum width that command names are padded to in
+    /// `--list --with-description`.
+    pub max_list_name_width: usize,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct UpdatesConfig {
     pub auto_update: bool,
     pub auto_update_interval: Duration,
+    /// The URLs to download the archive from, in order of preference. If
+    /// empty, the default archive URL is used.
+    pub archive_urls: Vec<String>,
+    /// The tag, branch or commit of the upstream repository to download the
+    /// pages of, replacing `{ref}` in the archive URLs.
+    pub archive_ref: Option<String>,
+    pub delta_url: Option<String>,
+    pub auto_clear_after: Option<Duration>,
+    pub quiet_success: bool,
+    pub warning_stream: WarningStream,
+    /// The maximum size of a downloaded archive in bytes.
+    pub max_archive_size: u64,
+    /// The directory within the archive that contains the pages
+    /// directories. If not set, it is detected automatically.
+    pub pages_subdir: Option<PathBuf>,
+    /// The languages to extract from the archive (in addition to English).
+    /// If not set, all languages are extracted.
+    pub languages: Option<Vec<String>>,
+    /// The permissions of the cache directory and the pages directory, which
+    /// are set when updating (only on Unix).
+    pub cache_dir_mode: Option<u32>,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct DirectoriesConfig {
     pub custom_pages_dir: Option<PathBuf>,
+    pub platform_fallback: Option<Vec<String>>,
+    pub wsl_prefer_windows: bool,
+    pub resolve_aliases: bool,
+    pub merge_common: bool,
+    pub case_insensitive: bool,
+    /// Whether to look up `git_log` or `git log` as `git-log` if there is no
+    /// page with the exact name.
+    pub normalize_separators: bool,
+    /// Whether custom pages that are broken symlinks are skipped, instead of
+    /// failing with an error.
+    pub skip_broken_symlinks: bool,
+}
+
+/// A named directory of custom pages (see `[[sources]]`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SourceConfig {
+    pub name: String,
+    pub path: PathBuf,
+    pub enabled: bool,
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -247,73 +746,328 @@ pub struct Config {
     pub display: DisplayConfig,
     pub updates: UpdatesConfig,
     pub directories: DirectoriesConfig,
+    /// The page sources, in the order in which they are searched.
+    pub sources: Vec<SourceConfig>,
 }
 
-impl From<RawConfig> for Config {
-    fn from(raw_config: RawConfig) -> Self {
-        Self {
+impl TryFrom<RawConfig> for Config {
+    type Error = anyhow::Error;
+
+    fn try_from(raw_config: RawConfig) -> Result<Self> {
+        let palette = &raw_config.palette;
+        let style = |name: &str, raw_style: &RawStyle| {
+            raw_style
+                .resolve(palette)
+                .with_context(|| format!("Invalid style for {name:?}"))
+        };
+        let archive_urls = match (
+            raw_config.updates.archive_url,
+            raw_config.updates.archive_urls,
+        ) {
+            (Some(_), Some(_)) => {
+                bail!("Only one of `updates.archive_url` and `updates.archive_urls` may be set")
+            }
+            (Some(url), None) => vec![url],
+            (None, Some(urls)) => {
+                ensure!(!urls.is_empty(), "`updates.archive_urls` must not be empty");
+                urls
+            }
+            (None, None) => vec![],
+        };
+        let archive_ref = match raw_config.updates.archive_ref {
+            Some(reference) => {
+                ensure!(
+                    !reference.is_empty() && !reference.contains(char::is_whitespace),
+                    "`updates.archive_ref` must be the name of a tag, branch or commit"
+                );
+                ensure!(
+                    archive_urls.iter().all(|url| url.contains("{ref}")),
+                    "The archive URLs must contain a `{{ref}}` placeholder if `updates.archive_ref` is set"
+                );
+                Some(reference)
+            }
+            None => None,
+        };
+
+        Ok(Self {
             style: StyleConfig {
-                command_name: raw_config.style.command_name.into(),
-                description: raw_config.style.description.into(),
-                example_text: raw_config.style.example_text.into(),
-                example_code: raw_config.style.example_code.into(),
-                example_variable: raw_config.style.example_variable.into(),
+                command_name: style("command_name", &raw_config.style.command_name)?,
+                description: style("description", &raw_config.style.description)?,
+                example_text: style("example_text", &raw_config.style.example_text)?,
+                example_code: style("example_code", &raw_config.style.example_code)?,
+                example_variable: style("example_variable", &raw_config.style.example_variable)?,
+                callout: style("callout", &raw_config.style.callout)?,
+                aliases: style("aliases", &raw_config.style.aliases)?,
+                page_header: style("page_header", &raw_config.style.page_header)?,
+                background: style("background", &raw_config.style.background)?,
+                line_number: style("line_number", &raw_config.style.line_number)?,
             },
             display: DisplayConfig {
                 compact: raw_config.display.compact,
                 use_pager: raw_config.display.use_pager,
+                overflow: raw_config.display.overflow,
+                full_width_background: raw_config.display.full_width_background,
+                show_title: raw_config.display.show_title,
+                title_original: raw_config.display.title_original,
+                title_link: raw_config.display.title_link,
+                callout_marker: raw_config.display.callout_marker,
+                section_rule: {
+                    let rule = raw_config.display.section_rule;
+                    ensure!(
+                        rule.is_empty() || rule.width() > 0,
+                        "`display.section_rule` must contain visible characters"
+                    );
+                    rule
+                },
+                empty_page_note: raw_config.display.empty_page_note,
+                warn_unknown_syntax: raw_config.display.warn_unknown_syntax,
+                fill_from_english: raw_config.display.fill_from_english,
+                dedup_examples: raw_config.display.dedup_examples,
+                inline_examples: raw_config.display.inline_examples,
+                inline_delimiter: raw_config.display.inline_delimiter,
+                align_examples: raw_config.display.align_examples,
+                max_align_width: raw_config.display.max_align_width,
+                command_prefix: raw_config.display.command_prefix,
+                min_wrap_width: raw_config.display.min_wrap_width,
+                color: raw_config.display.color.unwrap_or_default(),
+                example_bullet: raw_config.display.example_bullet,
+                placeholder_case: raw_config.display.placeholder_case,
+                not_found_message: raw_config.display.not_found_message,
+                pager_command: raw_config.display.pager_command,
+                post_render_command: raw_config.display.post_render_command,
+                surround_blank_lines: raw_config.display.surround_blank_lines,
+                theme: raw_config.display.theme,
+                cache_rendered: raw_config.display.cache_rendered,
+                default_command: match raw_config.display.default_command {
+                    Some(command) => {
+                        let words = split_command(&command)
+                            .context("Invalid `display.default_command`")?;
+                        ensure!(
+                            !words.is_empty(),
+                            "`display.default_command` must not be empty"
+                        );
+                        Some(command)
+                    }
+                    None => None,
+                },
+                style_placeholders: raw_config.display.style_placeholders,
+                max_description_lines: match raw_config.display.max_description_lines {
+                    Some(0) => bail!("`display.max_description_lines` must be at least 1"),
+                    max_lines => max_lines,
+                },
+                show_usage: raw_config.display.show_usage,
+                max_list_name_width: raw_config.display.max_list_name_width,
             },
             updates: UpdatesConfig {
                 auto_update: raw_config.updates.auto_update,
                 auto_update_interval: Duration::from_secs(
                     raw_config.updates.auto_update_interval_hours * 3600,
                 ),
+                archive_urls,
+                archive_ref,
+                delta_url: raw_config.updates.delta_url,
+                auto_clear_after: raw_config
+                    .updates
+                    .auto_clear_after_hours
+                    .map(|hours| Duration::from_secs(hours * 3600)),
+                quiet_success: raw_config.updates.quiet_success,
+                warning_stream: raw_config.updates.warning_stream,
+                max_archive_size: raw_config.updates.max_archive_size,
+                pages_subdir: match raw_config.updates.pages_subdir {
+                    Some(subdir) => {
+                        ensure!(
+                            subdir.is_relative()
+                                && subdir
+                                    .components()
+                                    .all(|c| matches!(c, Component::Normal(_))),
+                            "`updates.pages_subdir` must be a relative path within the archive"
+                        );
+                        Some(subdir)
+                    }
+                    None => None,
+                },
+                languages: match raw_config.updates.languages {
+                    Some(languages) => {
+                        ensure!(
+                            languages.iter().all(|lang| {
+                                !lang.is_empty()
+                                    && lang.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                            }),
+                            "`updates.languages` must only contain language codes (e.g. `de` or `pt_BR`)"
+                        );
+                        Some(languages)
+                    }
+                    None => None,
+                },
+                cache_dir_mode: match raw_config.updates.cache_dir_mode {
+                    Some(ref mode) => Some(parse_mode(mode).with_context(|| {
+                        format!(
+                            "`updates.cache_dir_mode` must be a file mode in octal (e.g. \"750\"), not {mode:?}"
+                        )
+                    })?),
+                    None => None,
+                },
             },
             directories: DirectoriesConfig {
                 custom_pages_dir: raw_config.directories.custom_pages_dir,
+                platform_fallback: raw_config.directories.platform_fallback,
+                wsl_prefer_windows: raw_config.directories.wsl_prefer_windows,
+                resolve_aliases: raw_config.directories.resolve_aliases,
+                merge_common: raw_config.directories.merge_common,
+                case_insensitive: raw_config.directories.case_insensitive,
+                normalize_separators: raw_config.directories.normalize_separators,
+                skip_broken_symlinks: raw_config.directories.skip_broken_symlinks,
             },
-        }
+            sources: {
+                let mut names = HashSet::new();
+                for source in &raw_config.sources {
+                    ensure!(!source.name.is_empty(), "Source names must not be empty");
+                    ensure!(
+                        names.insert(source.name.as_str()),
+                        "The source name `{}` is used more than once",
+                        source.name
+                    );
+                }
+                raw_config
+                    .sources
+                    .into_iter()
+                    .map(|source| SourceConfig {
+                        name: source.name,
+                        path: source.path,
+                        enabled: source.enabled,
+                    })
+                    .collect()
+            },
+        })
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::try_from(RawConfig::new()).expect("The default config is invalid")
     }
 }
 
 impl Config {
-    pub fn load(enable_styles: bool) -> Result<Self> {
+    pub fn load() -> Result<Self> {
         debug!("Loading config");
 
-        // Determine path
+        // Determine paths
         let (config_file_path, _) = get_config_path().context("Could not determine config path")?;
+        let local_config_file_path = config_file_path.with_file_name(LOCAL_CONFIG_FILE_NAME);
+
+        // Load the config file, with the local config file merged on top
+        let mut config_value = read_toml_file(&config_file_path)?;
+        if let Some(local_value) = read_toml_file(&local_config_file_path)? {
+            debug!("Merging local config file {:?}", &local_config_file_path);
+            match config_value {
+                Some(ref mut value) => merge_toml(value, local_value),
+                None => config_value = Some(local_value),
+            }
+        }
 
         // Load raw config
-        let raw_config: RawConfig = if config_file_path.exists() && config_file_path.is_file() {
-            let mut config_file = fs::File::open(&config_file_path).with_context(|| {
-                format!("Failed to open config file path at {:?}", &config_file_path)
-            })?;
-            let mut contents = String::new();
-            config_file.read_to_string(&mut contents).with_context(|| {
-                format!("Failed to read from config file at {:?}", &config_file_path)
-            })?;
-            toml::from_str(&contents).with_context(|| {
+        let raw_config: RawConfig = match config_value {
+            Some(value) => value.try_into().with_context(|| {
                 format!("Failed to parse TOML config file at {:?}", config_file_path)
-            })?
-        } else {
-            RawConfig::new()
+            })?,
+            None => RawConfig::new(),
         };
 
         // Convert to config
-        let mut config = Self::from(raw_config);
-
-        // Potentially override styles
-        if !enable_styles {
-            config.style = StyleConfig {
-                command_name: Style::default(),
-                description: Style::default(),
-                example_text: Style::default(),
-                example_code: Style::default(),
-                example_variable: Style::default(),
-            };
+        Self::try_from(raw_config)
+    }
+
+    /// Replace the default styles by styles for light terminal backgrounds,
+    /// which use blue instead of cyan. Styles that were changed in the config
+    /// file are kept.
+    pub fn use_light_theme(&mut self) {
+        let default = Self::default().style;
+        let style = &mut self.style;
+        for (style, default) in [
+            (&mut style.command_name, default.command_name),
+            (&mut style.example_code, default.example_code),
+            (&mut style.example_variable, default.example_variable),
+        ] {
+            if *style == default {
+                style.foreground = Some(Color::Blue);
+            }
+        }
+    }
+
+    /// Reset all styles, e.g. because the output should not be colored.
+    pub fn disable_styles(&mut self) {
+        self.style = StyleConfig {
+            command_name: Style::default(),
+            description: Style::default(),
+            example_text: Style::default(),
+            example_code: Style::default(),
+            example_variable: Style::default(),
+            callout: Style::default(),
+            aliases: Style::default(),
+            page_header: Style::default(),
+            background: Style::default(),
+            line_number: Style::default(),
+        };
+        // Links are escape sequences as well
+        self.display.title_link = false;
+    }
+
+    /// Convert the colors of all styles to the best representation that a
+    /// terminal with the given color depth supports.
+    pub fn limit_color_depth(&mut self, depth: ColorDepth) {
+        let style = &mut self.style;
+        for style in [
+            &mut style.description,
+            &mut style.command_name,
+            &mut style.example_text,
+            &mut style.example_code,
+            &mut style.example_variable,
+            &mut style.callout,
+            &mut style.aliases,
+            &mut style.page_header,
+            &mut style.background,
+            &mut style.line_number,
+        ] {
+            style.foreground = style.foreground.map(|color| depth.convert(color));
+            style.background = style.background.map(|color| depth.convert(color));
         }
+    }
+}
 
-        Ok(config)
+/// Read and parse a TOML file. If the file does not exist, `None` is returned.
+fn read_toml_file(path: &Path) -> Result<Option<toml::Value>> {
+    if !(path.exists() && path.is_file()) {
+        return Ok(None);
+    }
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("Failed to open config file path at {}", path.display()))?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
+        .with_context(|| format!("Failed to read from config file at {}", path.display()))?;
+    let value = toml::from_str(&contents)
+        .with_context(|| format!("Failed to parse TOML config file at {}", path.display()))?;
+    Ok(Some(value))
+}
+
+/// Merge `overrides` into `base`.
+///
+/// Tables are merged recursively, so that only the keys present in
+/// `overrides` are replaced. All other values (including arrays) in
+/// `overrides` replace the corresponding value in `base`.
+fn merge_toml(base: &mut toml::Value, overrides: toml::Value) {
+    match (base, overrides) {
+        (toml::Value::Table(base), toml::Value::Table(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(&key) {
+                    Some(existing) => merge_toml(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overrides) => *base = overrides,
     }
 }
 
@@ -391,3 +1145,204 @@ fn test_serialize_deserialize() {
     let deserialized: RawConfig = toml::from_str(&serialized).unwrap();
     assert_eq!(raw_config, deserialized);
 }
+
+#[test]
+fn test_describe_style() {
+    let style = Style::new().fg(Color::Cyan).underline();
+    assert_eq!(
+        describe_style(&style),
+        "foreground: Cyan\nbackground: none\nbold: false\nitalic: false\nunderline: true"
+    );
+}
+
+#[test]
+fn test_use_light_theme() {
+    let mut config = Config::default();
+    config.style.command_name = Style::new().fg(Color::Red);
+    config.use_light_theme();
+
+    // Only the styles that were not changed use the light theme
+    assert_eq!(config.style.command_name, Style::new().fg(Color::Red));
+    assert_eq!(config.style.example_code.foreground, Some(Color::Blue));
+    assert_eq!(config.style.example_variable.foreground, Some(Color::Blue));
+    assert!(config.style.example_variable.is_underline);
+}
+
+#[test]
+fn test_palette_aliases() {
+    let raw_config: RawConfig = toml::from_str(
+        r##"
+        [palette]
+        accent = "#ff8800"
+        muted = { ansi = 8 }
+
+        [style.description]
+        foreground = "accent"
+        background = "muted"
+
+        [style.command_name]
+        foreground = "#00ff00"
+
+        [style.example_text]
+        foreground = "red"
+        "##,
+    )
+    .unwrap();
+    let config = Config::try_from(raw_config).unwrap();
+    assert_eq!(
+        config.style.description.foreground,
+        Some(Color::RGB(255, 136, 0))
+    );
+    assert_eq!(config.style.description.background, Some(Color::Fixed(8)));
+    assert_eq!(
+        config.style.command_name.foreground,
+        Some(Color::RGB(0, 255, 0))
+    );
+    assert_eq!(config.style.example_text.foreground, Some(Color::Red));
+}
+
+#[test]
+fn test_palette_unknown_alias() {
+    let raw_config: RawConfig = toml::from_str(
+        r##"
+        [palette]
+        accent = "#ff8800"
+
+        [style.description]
+        foreground = "acent"
+        "##,
+    )
+    .unwrap();
+    let error = Config::try_from(raw_config).unwrap_err();
+    assert_eq!(
+        format!("{error:#}"),
+        "Invalid style for \"description\": Invalid foreground: \
+         Unknown color or palette alias \"acent\""
+    );
+
+    let raw_config: RawConfig =
+        toml::from_str("[style.description]\nforeground = '#ff88'").unwrap();
+    assert!(Config::try_from(raw_config).is_err());
+}
+
+#[test]
+fn test_archive_urls() {
+    let config = |toml: &str| Config::try_from(toml::from_str::<RawConfig>(toml).unwrap());
+
+    assert!(config("").unwrap().updates.archive_urls.is_empty());
+    assert_eq!(
+        config("[updates]\narchive_url = 'a'")
+            .unwrap()
+            .updates
+            .archive_urls,
+        ["a"]
+    );
+    assert_eq!(
+        config("[updates]\narchive_urls = ['a', 'b']")
+            .unwrap()
+            .updates
+            .archive_urls,
+        ["a", "b"]
+    );
+    assert!(config("[updates]\narchive_urls = []").is_err());
+    assert!(config("[updates]\narchive_url = 'a'\narchive_urls = ['b']").is_err());
+}
+
+#[test]
+fn test_section_rule() {
+    let config = |toml: &str| Config::try_from(toml::from_str::<RawConfig>(toml).unwrap());
+
+    assert_eq!(config("").unwrap().display.section_rule, "");
+    assert_eq!(
+        config("[display]\nsection_rule = '─'")
+            .unwrap()
+            .display
+            .section_rule,
+        "─"
+    );
+    assert!(config("[display]\nsection_rule = '\u{200b}'").is_err());
+}
+
+#[test]
+fn test_cache_dir_mode() {
+    let config = |toml: &str| Config::try_from(toml::from_str::<RawConfig>(toml).unwrap());
+
+    assert_eq!(config("").unwrap().updates.cache_dir_mode, None);
+    for mode in ["750", "0750", "0o750"] {
+        let toml = format!("[updates]\ncache_dir_mode = '{mode}'");
+        assert_eq!(config(&toml).unwrap().updates.cache_dir_mode, Some(0o750));
+    }
+    for mode in ["", "0o", "758", "77777", "rwx"] {
+        let toml = format!("[updates]\ncache_dir_mode = '{mode}'");
+        assert!(config(&toml).is_err(), "{mode}");
+    }
+}
+
+#[test]
+fn test_merge_toml_precedence() {
+    let mut base: toml::Value = toml::from_str(
+        r#"
+        [style.command_name]
+        foreground = "red"
+        bold = true
+
+        [display]
+        compact = true
+        "#,
+    )
+    .unwrap();
+    let local: toml::Value = toml::from_str(
+        r#"
+        [style.command_name]
+        foreground = "green"
+
+        [style.example_text]
+        italic = true
+        "#,
+    )
+    .unwrap();
+    merge_toml(&mut base, local);
+
+    let raw_config: RawConfig = base.try_into().unwrap();
+    // Keys from the local file win...
+    assert_eq!(
+        raw_config.style.command_name.foreground,
+        Some(RawColor::Green.into())
+    );
+    assert!(raw_config.style.example_text.italic);
+    // ...but keys that are only in the base file are kept
+    assert!(raw_config.style.command_name.bold);
+    assert!(raw_config.display.compact);
+}
+
+#[test]
+fn test_sources() {
+    let config = |toml: &str| Config::try_from(toml::from_str::<RawConfig>(toml).unwrap());
+
+    assert!(config("").unwrap().sources.is_empty());
+    assert_eq!(
+        config(
+            "[[sources]]\nname = 'work'\npath = '/work'\n\
+             [[sources]]\nname = 'old'\npath = '/old'\nenabled = false"
+        )
+        .unwrap()
+        .sources,
+        [
+            SourceConfig {
+                name: "work".to_string(),
+                path: PathBuf::from("/work"),
+                enabled: true,
+            },
+            SourceConfig {
+                name: "old".to_string(),
+                path: PathBuf::from("/old"),
+                enabled: false,
+            },
+        ]
+    );
+    assert!(config("[[sources]]\nname = ''\npath = '/a'").is_err());
+    assert!(
+        config("[[sources]]\nname = 'a'\npath = '/a'\n[[sources]]\nname = 'a'\npath = '/b'")
+            .is_err()
+    );
+}
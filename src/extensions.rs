@@ -3,6 +3,10 @@ use std::mem;
 /// An extension trait to clear duplicates from a collection.
 pub(crate) trait Dedup<T: PartialEq + Clone> {
     fn clear_duplicates(&mut self);
+
+    /// Like `clear_duplicates`, but two items are duplicates if `same`
+    /// returns `true` for them.
+    fn clear_duplicates_by(&mut self, same: impl FnMut(&T, &T) -> bool);
 }
 
 /// Clear duplicates from a collection, keep the first one seen.
@@ -10,9 +14,13 @@ pub(crate) trait Dedup<T: PartialEq + Clone> {
 /// For small vectors, this will be faster than a `HashSet`.
 impl<T: PartialEq + Clone> Dedup<T> for Vec<T> {
     fn clear_duplicates(&mut self) {
+        self.clear_duplicates_by(|a, b| a == b);
+    }
+
+    fn clear_duplicates_by(&mut self, mut same: impl FnMut(&T, &T) -> bool) {
         let orig = mem::replace(self, Vec::with_capacity(self.len()));
         for item in orig {
-            if !self.contains(&item) {
+            if !self.iter().any(|kept| same(kept, &item)) {
                 self.push(item);
             }
         }
@@ -2,16 +2,27 @@
 
 use log::debug;
 
-use crate::{extensions::FindFrom, types::LineType};
+use crate::{
+    extensions::FindFrom,
+    types::{split_platform_tag, LineType},
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 /// Represents a snippet from a page of a specific highlighting class.
 pub enum PageSnippet<'a> {
+    Title(&'a str),
     CommandName(&'a str),
     Variable(&'a str),
     NormalCode(&'a str),
     Description(&'a str),
+    Callout(&'a str),
+    /// The comma separated aliases of the command
+    Aliases(&'a str),
+    /// A usage summary (e.g. `tar cf TARGET.TAR FILE`)
+    Usage(&'a str),
     Text(&'a str),
+    /// The indentation in front of an example command
+    CodePrefix,
     Linebreak,
 }
 
@@ -20,8 +31,9 @@ impl<'a> PageSnippet<'a> {
         use PageSnippet::*;
 
         match self {
-            CommandName(s) | Variable(s) | NormalCode(s) | Description(s) | Text(s) => s.is_empty(),
-            Linebreak => false,
+            Title(s) | CommandName(s) | Variable(s) | NormalCode(s) | Description(s)
+            | Callout(s) | Aliases(s) | Usage(s) | Text(s) => s.is_empty(),
+            CodePrefix | Linebreak => false,
         }
     }
 }
@@ -45,7 +57,7 @@ where
                 }
             }
             LineType::Title(title) => {
-                debug!("Ignoring title");
+                process_snippet(PageSnippet::Title(&title))?;
 
                 // This is safe as long as the parsed title is only the command,
                 // and the iterator yields values in order of appearance.
@@ -53,9 +65,16 @@ where
                 debug!("Detected command name: {}", &command);
             }
             LineType::Description(text) => process_snippet(PageSnippet::Description(&text))?,
-            LineType::ExampleText(text) => process_snippet(PageSnippet::Text(&text))?,
+            LineType::Callout(text) => process_snippet(PageSnippet::Callout(&text))?,
+            LineType::Aliases(aliases) => {
+                process_snippet(PageSnippet::Aliases(&aliases.join(", ")))?;
+            }
+            LineType::ExampleText(text) => {
+                process_snippet(PageSnippet::Text(split_platform_tag(&text).0))?;
+            }
+            LineType::Usage(text) => process_snippet(PageSnippet::Usage(&text))?,
             LineType::ExampleCode(text) => {
-                process_snippet(PageSnippet::NormalCode("      "))?;
+                process_snippet(PageSnippet::CodePrefix)?;
                 highlight_code(&command, &text, process_snippet)?;
                 process_snippet(PageSnippet::Linebreak)?;
             }
@@ -67,6 +86,59 @@ where
     Ok(())
 }
 
+/// Return the example code `text` with the curly braces around the user
+/// variables removed (e.g. `tar xf source.tar` for `tar xf {{source.tar}}`).
+pub fn strip_placeholders(text: &str) -> String {
+    let mut plain = String::new();
+    let _ = highlight_code("", text, &mut |snip| {
+        if let PageSnippet::Variable(s) | PageSnippet::NormalCode(s) = snip {
+            plain.push_str(s);
+        }
+        Ok::<(), ()>(())
+    });
+    plain
+}
+
+/// Split the example code `text` into pieces of plain code and user
+/// variables (without the curly braces), in order of appearance. Variables
+/// are marked with `true`, and empty pieces are skipped.
+pub fn split_placeholders(text: &str) -> Vec<(&str, bool)> {
+    let mut pieces = Vec::new();
+    let _ = highlight_code("", text, &mut |snip| {
+        match snip {
+            PageSnippet::NormalCode(s) if !s.is_empty() => pieces.push((s, false)),
+            PageSnippet::Variable(s) if !s.is_empty() => pieces.push((s, true)),
+            _ => {}
+        }
+        Ok::<(), ()>(())
+    });
+    pieces
+}
+
+/// Return a usage summary for the example code `text`, with the placeholders
+/// replaced by uppercase argument names (e.g. `tar cf TARGET.TAR FILE` for
+/// `tar cf {{path/to/target.tar}} {{file}}`).
+///
+/// This is a heuristic: a leading `path/to/` is removed from placeholders,
+/// and placeholders for options (e.g. `{{[-v|--verbose]}}`) are kept as they
+/// are.
+pub fn usage_line(text: &str) -> String {
+    let mut usage = String::new();
+    let _ = highlight_code("", text, &mut |snip| {
+        match snip {
+            PageSnippet::NormalCode(s) => usage.push_str(s),
+            PageSnippet::Variable(s) if s.starts_with(['-', '[']) => usage.push_str(s),
+            PageSnippet::Variable(s) => {
+                let name = s.strip_prefix("path/to/").unwrap_or(s);
+                usage.push_str(&name.to_uppercase());
+            }
+            _ => {}
+        }
+        Ok::<(), ()>(())
+    });
+    usage
+}
+
 /// Highlight code examples including user variables in {{ curly braces }}.
 fn highlight_code<'a, E>(
     command: &'a str,
@@ -235,4 +307,41 @@ mod tests {
         assert_eq!(run(" ", segment), snippets);
         assert_eq!(run("  \t ", segment), snippets);
     }
+
+    #[test]
+    fn test_strip_placeholders() {
+        assert_eq!(
+            strip_placeholders("tar cf {{target.tar}} {{file}}"),
+            "tar cf target.tar file"
+        );
+        assert_eq!(strip_placeholders("ls"), "ls");
+    }
+
+    #[test]
+    fn test_split_placeholders() {
+        assert_eq!(
+            split_placeholders("tar cf {{target.tar}} {{file}}"),
+            [
+                ("tar cf ", false),
+                ("target.tar", true),
+                (" ", false),
+                ("file", true)
+            ]
+        );
+        assert_eq!(split_placeholders("ls"), [("ls", false)]);
+    }
+
+    #[test]
+    fn test_usage_line() {
+        assert_eq!(
+            usage_line("tar cf {{path/to/target.tar}} {{file}}"),
+            "tar cf TARGET.TAR FILE"
+        );
+        assert_eq!(
+            usage_line("cp {{[-r|--recursive]}} {{source_directory}} {{target}}"),
+            "cp [-r|--recursive] SOURCE_DIRECTORY TARGET"
+        );
+        assert_eq!(usage_line("cat {{file1 file2 ...}}"), "cat FILE1 FILE2 ...");
+        assert_eq!(usage_line("ls"), "ls");
+    }
 }
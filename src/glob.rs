@@ -0,0 +1,181 @@
+//! Expansion of glob patterns (e.g. `pages/**/*.md`) into file paths.
+
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::{Context, Result};
+use walkdir::WalkDir;
+
+/// Return whether `pattern` contains any wildcards.
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?'])
+}
+
+/// Return the files matching `pattern`, sorted by path.
+///
+/// The following wildcards are supported:
+///
+/// - `?` matches any single character (but not a path separator)
+/// - `*` matches any number of characters (but not a path separator)
+/// - `**` as a whole path component matches any number of directories
+pub fn expand_glob(pattern: &Path) -> Result<Vec<PathBuf>> {
+    // The leading components without wildcards are the directory to search
+    let mut base_dir = PathBuf::new();
+    let mut components = pattern.components().peekable();
+    while let Some(component) = components.peek() {
+        match component {
+            Component::Normal(name) if is_glob(&name.to_string_lossy()) => break,
+            component => base_dir.push(component),
+        }
+        components.next();
+    }
+    let patterns: Vec<String> = components
+        .map(|component| component.as_os_str().to_string_lossy().into_owned())
+        .collect();
+    if patterns.is_empty() {
+        // There are no wildcards, so the pattern is a plain path
+        return Ok(if pattern.is_file() {
+            vec![pattern.to_path_buf()]
+        } else {
+            vec![]
+        });
+    }
+    let patterns: Vec<&str> = patterns.iter().map(String::as_str).collect();
+
+    let walk_dir = if base_dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        &base_dir
+    };
+    if !walk_dir.is_dir() {
+        return Ok(vec![]);
+    }
+    let mut paths = Vec::new();
+    for entry in WalkDir::new(walk_dir).min_depth(1) {
+        let entry =
+            entry.with_context(|| format!("Could not read directory {}", walk_dir.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative_path = entry.path().strip_prefix(walk_dir).unwrap_or(entry.path());
+        let names: Vec<String> = relative_path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        if matches_path(&patterns, &names) {
+            paths.push(base_dir.join(relative_path));
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// Return whether the path components `names` match the pattern components
+/// `patterns`.
+fn matches_path(patterns: &[&str], names: &[&str]) -> bool {
+    match (patterns.first(), names.first()) {
+        (None, None) => true,
+        (Some(&"**"), _) => {
+            // Match zero directories, or one directory and try again
+            matches_path(&patterns[1..], names)
+                || (!names.is_empty() && matches_path(patterns, &names[1..]))
+        }
+        (Some(pattern), Some(name)) => {
+            matches_name(pattern, name) && matches_path(&patterns[1..], &names[1..])
+        }
+        _ => false,
+    }
+}
+
+/// Return whether the file name `name` matches the pattern `pattern`.
+fn matches_name(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Iterative wildcard matching, backtracking to the last `*` on a mismatch
+    let (mut p, mut n) = (0, 0);
+    let mut last_star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                last_star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match last_star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    last_star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    #[test]
+    fn test_matches_name() {
+        assert!(matches_name("*.md", "tar.md"));
+        assert!(matches_name("*.md", ".md"));
+        assert!(!matches_name("*.md", "tar.page"));
+        assert!(matches_name("t?r*", "tar.md"));
+        assert!(!matches_name("t?r", "tr"));
+        assert!(matches_name("*a*b*", "xaybzb"));
+        assert!(!matches_name("*a*b", "xaybz"));
+        assert!(matches_name("git-*", "git-log.md"));
+    }
+
+    #[test]
+    fn test_matches_path() {
+        assert!(matches_path(&["**", "*.md"], &["tar.md"]));
+        assert!(matches_path(&["**", "*.md"], &["common", "tar.md"]));
+        assert!(matches_path(&["*", "*.md"], &["linux", "tar.md"]));
+        assert!(!matches_path(&["*", "*.md"], &["tar.md"]));
+        assert!(!matches_path(&["*.md"], &["common", "tar.md"]));
+        assert!(matches_path(&["**"], &["a", "b"]));
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        let dir = tempfile::tempdir().unwrap();
+        for path in [
+            "common/tar.md",
+            "common/git.md",
+            "linux/ip.md",
+            "linux/ip.txt",
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, "").unwrap();
+        }
+
+        let expand = |pattern: &str| {
+            expand_glob(&dir.path().join(pattern))
+                .unwrap()
+                .into_iter()
+                .map(|path| path.strip_prefix(dir.path()).unwrap().to_path_buf())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            expand("**/*.md"),
+            ["common/git.md", "common/tar.md", "linux/ip.md"].map(PathBuf::from)
+        );
+        assert_eq!(
+            expand("linux/ip.*"),
+            ["linux/ip.md", "linux/ip.txt"].map(PathBuf::from)
+        );
+        assert!(expand("*.md").is_empty());
+        assert!(expand("missing/*.md").is_empty());
+    }
+}
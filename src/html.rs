@@ -0,0 +1,121 @@
+//! Rendering of pages as standalone HTML documents (see `--export-html`).
+
+use std::fmt::Write;
+
+use crate::{formatter::split_placeholders, line_iterator::Page};
+
+/// The stylesheet that is embedded into every HTML page.
+const STYLESHEET: &str = "\
+body { font-family: sans-serif; max-width: 50em; margin: 2em auto; padding: 0 1em; }
+blockquote { margin: 0; padding-left: 1em; border-left: 3px solid #ccc; color: #555; }
+ul.examples { list-style: none; padding: 0; }
+ul.examples li { margin-bottom: 1.5em; }
+ul.examples p { margin: 0 0 0.5em; }
+pre { background: #f4f4f4; padding: 0.5em 1em; overflow-x: auto; }
+var { color: #0366d6; }";
+
+/// Escape the characters that have a special meaning in HTML.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Return the example command `code` as HTML, with the placeholders in
+/// `<var>` elements.
+fn code_html(code: &str) -> String {
+    let mut html = String::new();
+    for (piece, is_variable) in split_placeholders(code) {
+        if is_variable {
+            let _ = write!(html, "<var>{}</var>", escape_html(piece));
+        } else {
+            html.push_str(&escape_html(piece));
+        }
+    }
+    html
+}
+
+/// Render `page` as an HTML document. `name` is the name of the page, which
+/// is used as the title if the page has none, and `language` is the language
+/// of the page (e.g. `en`).
+pub fn page_html(page: &Page, name: &str, language: &str) -> String {
+    let title = escape_html(page.title.as_deref().unwrap_or(name));
+    let mut html = String::new();
+    let _ = writeln!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{title}</title>\n<style>\n{STYLESHEET}\n</style>\n</head>\n<body>\n<article>\n\
+         <h1>{title}</h1>",
+        escape_html(language),
+    );
+    for line in &page.description {
+        let _ = writeln!(html, "<p>{}</p>", escape_html(line));
+    }
+    for callout in &page.callouts {
+        let _ = writeln!(html, "<blockquote>{}</blockquote>", escape_html(callout));
+    }
+    if !page.aliases.is_empty() {
+        let _ = writeln!(
+            html,
+            "<p class=\"aliases\">Aliases: {}</p>",
+            escape_html(&page.aliases.join(", "))
+        );
+    }
+    if !page.examples.is_empty() {
+        html.push_str("<ul class=\"examples\">\n");
+        for example in &page.examples {
+            let _ = writeln!(html, "<li>\n<p>{}</p>", escape_html(&example.text));
+            for command in &example.commands {
+                let _ = writeln!(html, "<pre><code>{}</code></pre>", code_html(command));
+            }
+            html.push_str("</li>\n");
+        }
+        html.push_str("</ul>\n");
+    }
+    html.push_str("</article>\n</body>\n</html>\n");
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::line_iterator::LineIterator;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("<a href=\"x\">Tom & Jerry's</a>"),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+    }
+
+    #[test]
+    fn test_page_html() {
+        let input = "# tar\n\n> Archiving utility.\n> > Note: Old.\n\
+                     > More information: <https://example.com>.\n\n\
+                     - Create an archive:\n\n`tar cf {{target.tar}} {{file1 file2}}`\n";
+        let page = LineIterator::new(input.as_bytes()).parse();
+        let html = page_html(&page, "tar", "en");
+
+        assert!(html.starts_with("<!DOCTYPE html>\n<html lang=\"en\">\n"));
+        assert!(html.contains("<title>tar</title>"));
+        assert!(html.contains("<h1>tar</h1>\n<p>Archiving utility.</p>\n"));
+        assert!(html.contains("<p>More information: &lt;https://example.com&gt;.</p>\n"));
+        assert!(html.contains("<blockquote>Note: Old.</blockquote>\n"));
+        assert!(html.contains(
+            "<li>\n<p>Create an archive:</p>\n\
+             <pre><code>tar cf <var>target.tar</var> <var>file1 file2</var></code></pre>\n</li>\n"
+        ));
+        assert!(html.ends_with("</html>\n"));
+    }
+}
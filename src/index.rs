@@ -0,0 +1,202 @@
+//! An index of the pages in the cache (with the `page-index` feature), which
+//! allows listing and searching the pages without scanning the page
+//! directories.
+
+use std::{
+    fs::{self, File},
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::{line_iterator::LineIterator, types::LineType};
+
+/// The name of the index file in the pages directory.
+pub const INDEX_FILE: &str = "index.json";
+
+/// The version of the index format. Indexes with other versions are ignored.
+const INDEX_VERSION: u32 = 1;
+
+/// A single page of the index.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// The name of the page (e.g. `tar`)
+    pub name: String,
+    /// The platform directory of the page (e.g. `linux` or `common`)
+    pub platform: String,
+    /// The language of the page (e.g. `en`)
+    pub language: String,
+    /// The path of the page, relative to the pages directory
+    pub path: PathBuf,
+    /// The first line of the page's description
+    pub description: String,
+}
+
+/// The index of all pages in a pages directory.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageIndex {
+    version: u32,
+    entries: Vec<IndexEntry>,
+}
+
+impl PageIndex {
+    /// Build the index of the pages in `pages_dir` (which contains the
+    /// language directories, e.g. `pages` and `pages.de`).
+    pub fn build(pages_dir: &Path) -> Result<Self> {
+        let mut entries = Vec::new();
+        for entry in WalkDir::new(pages_dir)
+            .min_depth(3)
+            .max_depth(3)
+            .sort_by_file_name()
+        {
+            let entry = entry
+                .with_context(|| format!("Could not read directory {}", pages_dir.display()))?;
+            let path = entry.path();
+            if !entry.file_type().is_file() || path.extension().map_or(true, |ext| ext != "md") {
+                continue;
+            }
+            let relative_path = path.strip_prefix(pages_dir).unwrap_or(path).to_path_buf();
+            let mut components = relative_path.iter().map(|c| c.to_string_lossy());
+            let (lang_dir, platform) = match (components.next(), components.next()) {
+                (Some(lang_dir), Some(platform)) => (lang_dir, platform.into_owned()),
+                _ => continue,
+            };
+            let language = match lang_dir.strip_prefix("pages") {
+                Some("") => "en".to_string(),
+                Some(suffix) => match suffix.strip_prefix('.') {
+                    Some(language) => language.to_string(),
+                    None => continue,
+                },
+                None => continue,
+            };
+            let name = match path.file_stem() {
+                Some(stem) => stem.to_string_lossy().into_owned(),
+                None => continue,
+            };
+            let file = File::open(path)
+                .with_context(|| format!("Could not open page file at {}", path.display()))?;
+            let description = LineIterator::new(BufReader::new(file))
+                .find_map(|line| match line {
+                    LineType::Description(text) => Some(text),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            entries.push(IndexEntry {
+                name,
+                platform,
+                language,
+                path: relative_path,
+                description,
+            });
+        }
+        Ok(Self {
+            version: INDEX_VERSION,
+            entries,
+        })
+    }
+
+    /// Build the index of the pages in `pages_dir` and write it to the index
+    /// file in that directory.
+    pub fn rebuild(pages_dir: &Path) -> Result<()> {
+        let index = Self::build(pages_dir)?;
+        let path = pages_dir.join(INDEX_FILE);
+        let json = serde_json::to_string(&index).context("Could not serialize the page index")?;
+        fs::write(&path, json)
+            .with_context(|| format!("Could not write the page index to {}", path.display()))
+    }
+
+    /// Load the index file of `pages_dir`. `None` is returned if there is no
+    /// index, or if it cannot be read (so that the caller falls back to
+    /// scanning the page directories).
+    pub fn load(pages_dir: &Path) -> Option<Self> {
+        let path = pages_dir.join(INDEX_FILE);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                debug!("Could not read the page index at {}: {e}", path.display());
+                return None;
+            }
+        };
+        match serde_json::from_str::<Self>(&contents) {
+            Ok(index) if index.version == INDEX_VERSION => Some(index),
+            Ok(index) => {
+                debug!("Ignoring page index with version {}", index.version);
+                None
+            }
+            Err(e) => {
+                debug!("Could not parse the page index at {}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    /// Return the pages in the given language and platform directories, in
+    /// order of the platforms and then by name.
+    pub fn pages<'a>(
+        &'a self,
+        language: &'a str,
+        platforms: &'a [&str],
+    ) -> impl Iterator<Item = &'a IndexEntry> {
+        platforms.iter().flat_map(move |platform| {
+            self.entries
+                .iter()
+                .filter(move |entry| entry.language == language && entry.platform == *platform)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, contents) in [
+            ("pages/common/tar.md", "# tar\n\n> Archiving utility.\n"),
+            ("pages/linux/ip.md", "# ip\n\n> Show interfaces.\n> More.\n"),
+            (
+                "pages.de/common/tar.md",
+                "# tar\n\n> Archivierungsprogramm.\n",
+            ),
+            ("pages/common/notes.txt", ""),
+        ] {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        fs::write(dir.path().join("VERSION"), "1").unwrap();
+
+        assert_eq!(PageIndex::load(dir.path()), None);
+        PageIndex::rebuild(dir.path()).unwrap();
+        let index = PageIndex::load(dir.path()).unwrap();
+
+        let pages: Vec<_> = index
+            .pages("en", &["linux", "common"])
+            .map(|entry| (entry.name.as_str(), entry.description.as_str()))
+            .collect();
+        assert_eq!(
+            pages,
+            [("ip", "Show interfaces."), ("tar", "Archiving utility.")]
+        );
+        let german: Vec<_> = index.pages("de", &["linux", "common"]).collect();
+        assert_eq!(german.len(), 1);
+        assert_eq!(german[0].path, Path::new("pages.de/common/tar.md"));
+        assert_eq!(index.pages("en", &["osx"]).count(), 0);
+    }
+
+    #[test]
+    fn test_load_invalid() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(INDEX_FILE), "not json").unwrap();
+        assert_eq!(PageIndex::load(dir.path()), None);
+
+        fs::write(dir.path().join(INDEX_FILE), r#"{"version":0,"entries":[]}"#).unwrap();
+        assert_eq!(PageIndex::load(dir.path()), None);
+    }
+}
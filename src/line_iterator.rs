@@ -4,7 +4,10 @@ use std::io::BufRead;
 
 use log::warn;
 
-use crate::types::LineType;
+use crate::{
+    extensions::Dedup,
+    types::{split_platform_tag, LineType, PlatformType},
+};
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum TldrFormat {
@@ -42,6 +45,39 @@ where
             format: TldrFormat::Undecided,
         }
     }
+
+    /// Read the whole page and return its structured contents. To render a
+    /// page, iterate over the lines instead.
+    pub fn parse(self) -> Page {
+        Page::from(GroupedLines::new(self))
+    }
+
+    /// As long as `code` ends with a line continuation (a trailing backslash),
+    /// read the next line and append it to `code`, separated by a space.
+    fn join_continuation_lines(&mut self, mut code: String) -> String {
+        while has_line_continuation(&code) {
+            self.current_line.clear();
+            match self.reader.read_line(&mut self.current_line) {
+                Ok(0) => break,
+                Err(e) => {
+                    warn!("Could not read line from reader: {e:?}");
+                    break;
+                }
+                Ok(_) => {}
+            }
+
+            // Remove the backslash and join the lines
+            code.pop();
+            let continuation = match self.format {
+                TldrFormat::V1 => self.current_line.trim().trim_end_matches('`'),
+                _ => self.current_line.trim(),
+            };
+            code.truncate(code.trim_end().len());
+            code.push(' ');
+            code.push_str(continuation);
+        }
+        code
+    }
 }
 
 impl<R: BufRead> Iterator for LineIterator<R> {
@@ -78,20 +114,260 @@ impl<R: BufRead> Iterator for LineIterator<R> {
                 self.first_line = false;
 
                 // Convert line to a `LineType` instance
-                match self.format {
-                    TldrFormat::V1 => Some(LineType::from_v1(&self.current_line[..])),
-                    TldrFormat::V2 => Some(LineType::from(&self.current_line[..])),
+                let line = match self.format {
+                    TldrFormat::V1 => LineType::from_v1(&self.current_line[..]),
+                    TldrFormat::V2 => LineType::from(&self.current_line[..]),
                     TldrFormat::Undecided => panic!("Could not determine page format version"),
+                };
+
+                // Join example commands that span multiple lines
+                match line {
+                    LineType::ExampleCode(code) => {
+                        Some(LineType::ExampleCode(self.join_continuation_lines(code)))
+                    }
+                    other => Some(other),
                 }
             }
         }
     }
 }
 
+/// A page whose lines have been grouped into examples.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct GroupedLines {
+    /// All lines before the first example (title, description, ...). Trailing
+    /// empty lines are not included.
+    pub header: Vec<LineType>,
+    /// The examples of the page, in order of appearance.
+    pub examples: Vec<Example>,
+}
+
+/// A single example, consisting of the example text and the lines that follow
+/// it until the next example. Trailing empty lines are not included.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Example {
+    pub lines: Vec<LineType>,
+}
+
+impl Example {
+    /// Return whether the text or the code of this example contains
+    /// `keyword` (case insensitive).
+    pub fn matches(&self, keyword: &str) -> bool {
+        let keyword = keyword.to_lowercase();
+        self.lines.iter().any(|line| match line {
+            LineType::ExampleText(text) | LineType::ExampleCode(text) => {
+                text.to_lowercase().contains(&keyword)
+            }
+            _ => false,
+        })
+    }
+
+    /// Return the platforms this example is restricted to by a platform tag
+    /// (e.g. `- List files: <!-- platform: linux -->`). An empty list means
+    /// that the example applies to all platforms.
+    pub fn platforms(&self) -> Vec<PlatformType> {
+        match self.lines.first() {
+            Some(LineType::ExampleText(text)) => split_platform_tag(text).1,
+            _ => vec![],
+        }
+    }
+
+    /// Return whether this example applies to `platform`.
+    pub fn is_for_platform(&self, platform: PlatformType) -> bool {
+        let platforms = self.platforms();
+        platforms.is_empty() || platforms.contains(&platform)
+    }
+
+    /// Return the code lines of this example.
+    pub fn commands(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                LineType::ExampleCode(code) => Some(code.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl GroupedLines {
+    /// Group the lines yielded by `lines` into the page header and examples.
+    /// Every `ExampleText` line starts a new example.
+    pub fn new(lines: impl Iterator<Item = LineType>) -> Self {
+        let mut grouped = Self::default();
+        for line in lines {
+            match (line, grouped.examples.last_mut()) {
+                (line @ LineType::ExampleText(_), _) => {
+                    grouped.examples.push(Example { lines: vec![line] });
+                }
+                (line, Some(example)) => example.lines.push(line),
+                (line, None) => grouped.header.push(line),
+            }
+        }
+        trim_empty_lines(&mut grouped.header);
+        for example in &mut grouped.examples {
+            trim_empty_lines(&mut example.lines);
+        }
+        grouped
+    }
+
+    /// Append the examples of `other` to the examples of this page, skipping
+    /// examples that are already part of this page. The header of `other` is
+    /// discarded.
+    pub fn merge(&mut self, other: Self) {
+        self.examples.extend(other.examples);
+        self.examples.clear_duplicates();
+    }
+
+    /// Append the examples of `other` whose commands are not part of any
+    /// example of this page (e.g. the examples that are missing from a
+    /// translation). The header of `other` is discarded.
+    pub fn fill_from(&mut self, other: Self) {
+        let missing: Vec<Example> = other
+            .examples
+            .into_iter()
+            .filter(|example| {
+                let commands = example.commands();
+                !self
+                    .examples
+                    .iter()
+                    .any(|existing| existing.commands() == commands)
+            })
+            .collect();
+        self.examples.extend(missing);
+    }
+
+    /// Remove the examples whose commands are identical to the commands of an
+    /// earlier example (so the first description is kept). Examples without
+    /// any commands are always kept.
+    pub fn dedup_examples(&mut self) {
+        self.examples.clear_duplicates_by(|kept, example| {
+            let commands = example.commands();
+            !commands.is_empty() && kept.commands() == commands
+        });
+    }
+
+    /// Only keep the first `max_lines` lines of the description, and end the
+    /// last one with `marker` if any lines were removed.
+    pub fn truncate_description(&mut self, max_lines: usize, marker: &str) {
+        let mut description_lines = 0;
+        let mut truncated = false;
+        self.header.retain(|line| match line {
+            LineType::Description(_) if description_lines >= max_lines => {
+                truncated = true;
+                false
+            }
+            LineType::Description(_) => {
+                description_lines += 1;
+                true
+            }
+            _ => true,
+        });
+        if truncated {
+            let last = self.header.iter_mut().rev().find_map(|line| match line {
+                LineType::Description(text) => Some(text),
+                _ => None,
+            });
+            if let Some(text) = last {
+                text.push_str(marker);
+            }
+        }
+    }
+
+    /// Turn the grouped lines back into a sequence of lines, separating the
+    /// header and the examples by an empty line.
+    pub fn into_lines(self) -> impl Iterator<Item = LineType> {
+        let has_header = !self.header.is_empty();
+        let examples = self
+            .examples
+            .into_iter()
+            .enumerate()
+            .flat_map(move |(i, example)| {
+                let separator = (i > 0 || has_header).then_some(LineType::Empty);
+                separator.into_iter().chain(example.lines)
+            });
+        self.header.into_iter().chain(examples)
+    }
+}
+
+/// The structured contents of a page, see `LineIterator::parse`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Page {
+    pub title: Option<String>,
+    /// The lines of the description
+    pub description: Vec<String>,
+    /// Quoted lines within the description (e.g. `> > Note: ...`)
+    pub callouts: Vec<String>,
+    pub aliases: Vec<String>,
+    pub examples: Vec<PageExample>,
+}
+
+/// A single example of a `Page`.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PageExample {
+    /// The example text, without the platform tag
+    pub text: String,
+    /// The platforms from the platform tag (empty if the example applies to
+    /// all platforms)
+    pub platforms: Vec<PlatformType>,
+    /// The example commands, with the placeholders still in curly braces
+    pub commands: Vec<String>,
+}
+
+impl From<GroupedLines> for Page {
+    fn from(grouped: GroupedLines) -> Self {
+        let mut page = Self::default();
+        for line in grouped.header {
+            match line {
+                LineType::Title(title) => page.title = Some(title),
+                LineType::Description(text) => page.description.push(text),
+                LineType::Callout(text) => page.callouts.push(text),
+                LineType::Aliases(aliases) => page.aliases.extend(aliases),
+                _ => {}
+            }
+        }
+        page.examples = grouped
+            .examples
+            .into_iter()
+            .map(|example| {
+                let mut page_example = PageExample::default();
+                for line in example.lines {
+                    match line {
+                        LineType::ExampleText(text) => {
+                            let (text, platforms) = split_platform_tag(&text);
+                            page_example.text = text.to_string();
+                            page_example.platforms = platforms;
+                        }
+                        LineType::ExampleCode(code) => page_example.commands.push(code),
+                        _ => {}
+                    }
+                }
+                page_example
+            })
+            .collect();
+        page
+    }
+}
+
+/// Remove all trailing empty lines.
+fn trim_empty_lines(lines: &mut Vec<LineType>) {
+    while lines.last() == Some(&LineType::Empty) {
+        lines.pop();
+    }
+}
+
+/// Return whether the line ends with an unescaped backslash.
+///
+/// An even number of trailing backslashes (e.g. `\\`) is an escaped
+/// backslash and does not continue the line.
+fn has_line_continuation(line: &str) -> bool {
+    line.chars().rev().take_while(|&chr| chr == '\\').count() % 2 == 1
+}
+
 #[cfg(test)]
 mod test {
-    use super::LineIterator;
-    use crate::types::LineType;
+    use super::{GroupedLines, LineIterator, Page, PageExample};
+    use crate::types::{LineType, PlatformType};
 
     #[test]
     fn test_first_line_old_format() {
@@ -112,4 +388,219 @@ mod test {
         let empty = lines.next().unwrap();
         assert_eq!(empty, LineType::Empty);
     }
+
+    #[test]
+    fn test_line_continuation() {
+        let input = "The Title\n=========\n\n    tar -c \\\n        -f {{archive}} \\\n        {{file}}\n\nNext\n";
+        let lines: Vec<_> = LineIterator::new(input.as_bytes()).collect();
+        assert_eq!(
+            lines,
+            [
+                LineType::Title("The Title".to_string()),
+                LineType::Empty,
+                LineType::ExampleCode("tar -c -f {{archive}} {{file}}".to_string()),
+                LineType::Empty,
+                LineType::ExampleText("Next".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_escaped_backslash_does_not_continue() {
+        let input = "The Title\n=========\n\n    echo \\\\\n    ls\n";
+        let lines: Vec<_> = LineIterator::new(input.as_bytes()).collect();
+        assert_eq!(
+            lines,
+            [
+                LineType::Title("The Title".to_string()),
+                LineType::Empty,
+                LineType::ExampleCode("echo \\\\".to_string()),
+                LineType::ExampleCode("ls".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_line_continuation_old_format() {
+        let input = "# tar\n\n- Create:\n\n`tar -c \\`\n    -f {{archive}} \\`\n    {{file}}`\n\n\
+                     - Print:\n\n`echo \\\\`\n`echo a \\`\n    \\\\`\n`ls`\n";
+        let lines: Vec<_> = LineIterator::new(input.as_bytes()).collect();
+        assert_eq!(
+            lines,
+            [
+                LineType::Title("tar".to_string()),
+                LineType::Empty,
+                LineType::ExampleText("Create:".to_string()),
+                LineType::Empty,
+                LineType::ExampleCode("tar -c -f {{archive}} {{file}}".to_string()),
+                LineType::Empty,
+                LineType::ExampleText("Print:".to_string()),
+                LineType::Empty,
+                // An escaped backslash before the closing backtick does not
+                // continue the line
+                LineType::ExampleCode("echo \\\\".to_string()),
+                LineType::ExampleCode("echo a \\\\".to_string()),
+                LineType::ExampleCode("ls".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        let input = "# tar\n\n> Archiving utility.\n> Aliases: gtar\n> > Note: Old.\n\
+                     > More information: <https://example.com>.\n\n\
+                     - Create an archive: <!-- platform: linux -->\n\n`tar cf {{target.tar}} {{file}}`\n\n\
+                     - Extract an archive:\n\n`tar xf {{source.tar}}`\n";
+        let page = LineIterator::new(input.as_bytes()).parse();
+        assert_eq!(
+            page,
+            Page {
+                title: Some("tar".to_string()),
+                description: vec![
+                    "Archiving utility.".to_string(),
+                    "More information: <https://example.com>.".to_string(),
+                ],
+                callouts: vec!["Note: Old.".to_string()],
+                aliases: vec!["gtar".to_string()],
+                examples: vec![
+                    PageExample {
+                        text: "Create an archive:".to_string(),
+                        platforms: vec![PlatformType::Linux],
+                        commands: vec!["tar cf {{target.tar}} {{file}}".to_string()],
+                    },
+                    PageExample {
+                        text: "Extract an archive:".to_string(),
+                        platforms: vec![],
+                        commands: vec!["tar xf {{source.tar}}".to_string()],
+                    },
+                ],
+            }
+        );
+
+        // The new format results in the same structure
+        let input = "tar\n===\n\n> Archiving utility.\n\n- Extract an archive:\n\n    tar xf {{source.tar}}\n";
+        let page = LineIterator::new(input.as_bytes()).parse();
+        assert_eq!(page.title.as_deref(), Some("tar"));
+        assert_eq!(page.description, ["Archiving utility."]);
+        assert_eq!(page.examples[0].commands, ["tar xf {{source.tar}}"]);
+    }
+
+    #[test]
+    fn test_grouped_lines() {
+        let input = "# foo\n\n> Foo.\n\n- Do a:\n\n`foo a`\n\n- Do b:\n\n`foo b`\n";
+        let grouped = GroupedLines::new(LineIterator::new(input.as_bytes()));
+        assert_eq!(
+            grouped.header,
+            [
+                LineType::Title("foo".to_string()),
+                LineType::Empty,
+                LineType::Description("Foo.".to_string()),
+            ]
+        );
+        assert_eq!(grouped.examples.len(), 2);
+        assert_eq!(
+            grouped.examples[0].lines,
+            [
+                LineType::ExampleText("Do a:".to_string()),
+                LineType::Empty,
+                LineType::ExampleCode("foo a".to_string()),
+            ]
+        );
+        assert!(grouped.examples[1].matches("B:"));
+        assert!(!grouped.examples[1].matches("foo a"));
+
+        // Regrouping does not change the lines
+        let lines: Vec<_> = grouped.into_lines().collect();
+        let original: Vec<_> = LineIterator::new(input.as_bytes()).collect();
+        assert_eq!(lines, original);
+    }
+
+    #[test]
+    fn test_truncate_description() {
+        let input = "# foo\n\n> Foo.\n> Bar.\n> Baz.\n\n- Do a:\n\n`foo a`\n";
+        let mut grouped = GroupedLines::new(LineIterator::new(input.as_bytes()));
+        grouped.truncate_description(2, " …");
+        assert_eq!(
+            grouped.header,
+            [
+                LineType::Title("foo".to_string()),
+                LineType::Empty,
+                LineType::Description("Foo.".to_string()),
+                LineType::Description("Bar. …".to_string()),
+            ]
+        );
+        assert_eq!(grouped.examples.len(), 1);
+
+        // Short descriptions are not changed
+        let mut grouped = GroupedLines::new(LineIterator::new(input.as_bytes()));
+        grouped.truncate_description(3, " …");
+        assert_eq!(grouped.header[4], LineType::Description("Baz.".to_string()));
+    }
+
+    #[test]
+    fn test_merge_grouped_lines() {
+        let common = "# foo\n\n> Foo.\n\n- Do a:\n\n`foo a`\n";
+        let platform = "# foo\n\n> Foo on Linux.\n\n- Do a:\n\n`foo a`\n\n- Do b:\n\n`foo b`\n";
+        let mut grouped = GroupedLines::new(LineIterator::new(common.as_bytes()));
+        grouped.merge(GroupedLines::new(LineIterator::new(platform.as_bytes())));
+
+        assert_eq!(grouped.header[2], LineType::Description("Foo.".to_string()));
+        let texts: Vec<_> = grouped
+            .examples
+            .iter()
+            .map(|example| &example.lines[0])
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                &LineType::ExampleText("Do a:".to_string()),
+                &LineType::ExampleText("Do b:".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fill_grouped_lines() {
+        let german = "# foo\n\n> Foo.\n\n- Mache a:\n\n`foo a`\n";
+        let english = "# foo\n\n> Foo.\n\n- Do a:\n\n`foo a`\n\n- Do b:\n\n`foo b`\n";
+        let mut grouped = GroupedLines::new(LineIterator::new(german.as_bytes()));
+        grouped.fill_from(GroupedLines::new(LineIterator::new(english.as_bytes())));
+
+        let texts: Vec<_> = grouped
+            .examples
+            .iter()
+            .map(|example| &example.lines[0])
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                &LineType::ExampleText("Mache a:".to_string()),
+                &LineType::ExampleText("Do b:".to_string()),
+            ]
+        );
+        assert_eq!(grouped.examples[1].commands(), ["foo b"]);
+    }
+
+    #[test]
+    fn test_dedup_examples() {
+        let input = "# foo\n\n> Foo.\n\n- Do a:\n\n`foo a`\n\n- Do b:\n\n`foo b`\n\n\
+                     - Also do a:\n\n`foo a`\n\n- Note:\n\n- Another note:\n";
+        let mut grouped = GroupedLines::new(LineIterator::new(input.as_bytes()));
+        grouped.dedup_examples();
+
+        let texts: Vec<_> = grouped
+            .examples
+            .iter()
+            .map(|example| &example.lines[0])
+            .collect();
+        assert_eq!(
+            texts,
+            [
+                &LineType::ExampleText("Do a:".to_string()),
+                &LineType::ExampleText("Do b:".to_string()),
+                &LineType::ExampleText("Note:".to_string()),
+                &LineType::ExampleText("Another note:".to_string()),
+            ]
+        );
+    }
 }
@@ -24,30 +24,52 @@ compile_error!(
     "exactly one of feature \"native-roots\" and feature \"webpki-roots\" must be enabled"
 );
 
-use std::{env, process};
+use std::{
+    env,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process, thread,
+};
 
+use anyhow::{ensure, Context, Result};
 use app_dirs::AppInfo;
 use atty::Stream;
-use clap::Parser;
+use clap::{ErrorKind, Parser};
+use once_cell::sync::OnceCell;
+use serde_derive::Serialize;
 
 mod cache;
 mod cli;
 mod config;
 pub mod extensions;
 mod formatter;
+mod glob;
+mod html;
+#[cfg(feature = "page-index")]
+mod index;
 mod line_iterator;
 mod output;
+mod render_cache;
+mod split_command;
 mod types;
 mod utils;
 
 use crate::{
-    cache::{Cache, CacheFreshness, PageLookupResult, TLDR_PAGES_DIR},
+    cache::{Cache, CacheFreshness, PageLookupResult, PageSource, TLDR_PAGES_DIR},
     cli::Args,
-    config::{get_config_dir, get_config_path, make_default_config, Config},
+    config::{describe_style, get_config_dir, get_config_path, make_default_config, Config},
     extensions::Dedup,
-    output::print_page,
-    types::{ColorOptions, PlatformType},
-    utils::{print_error, print_warning},
+    glob::{expand_glob, is_glob},
+    line_iterator::LineIterator,
+    output::{
+        export_page, print_file_pages, print_page, print_page_list, print_platform_pages,
+        RenderOptions,
+    },
+    split_command::split_command,
+    types::{ColorDepth, ColorOptions, OutputFormat, PlatformType},
+    utils::{print_error, print_info, print_warning, print_warning_to},
 };
 
 const NAME: &str = "tealdeer";
@@ -56,29 +78,67 @@ const APP_INFO: AppInfo = AppInfo {
     author: NAME,
 };
 const ARCHIVE_URL: &str = "https://tldr.sh/assets/tldr.zip";
+/// The archive URL if `updates.archive_ref` is set.
+const ARCHIVE_REF_URL: &str = "https://github.com/tldr-pages/tldr/archive/{ref}.zip";
+/// Where missing pages can be submitted (`{{docs_url}}` in
+/// `display.not_found_message`).
+const PAGES_REPO_URL: &str = "https://github.com/tldr-pages/tldr";
+/// The page that is rendered by `--demo`.
+const DEMO_PAGE: &str = include_str!("demo.md");
 
 /// The cache should be updated if it was explicitly requested,
 /// or if an automatic update is due and allowed.
-fn should_update_cache(args: &Args, config: &Config) -> bool {
+///
+/// Automatic updates are skipped when we are offline (see
+/// `Cache::is_offline`) or when the cache is immutable. An explicitly
+/// requested update is attempted anyways, so that it fails with an error.
+fn should_update_cache(cache: &Cache, args: &Args, config: &Config) -> bool {
     args.update
         || (!args.no_auto_update
+            && !Cache::is_immutable()
             && config.updates.auto_update
-            && Cache::last_update().map_or(true, |ago| ago >= config.updates.auto_update_interval))
+            && match Cache::last_update() {
+                Ok(Some(ago)) => ago >= config.updates.auto_update_interval,
+                Ok(None) => true,
+                // The error will be reported when checking the cache
+                Err(_) => false,
+            }
+            && !cache.is_offline())
+}
+
+/// Check whether the cache is old enough to be cleared automatically.
+fn should_auto_clear_cache(config: &Config) -> bool {
+    if Cache::is_immutable() {
+        return false;
+    }
+    config.updates.auto_clear_after.map_or(false, |max_age| {
+        // Errors will be reported when checking the cache
+        matches!(Cache::last_update(), Ok(Some(ago)) if ago >= max_age)
+    })
 }
 
 #[derive(PartialEq)]
 enum CheckCacheResult {
-    CacheFound,
-    CacheMissing,
+    Found,
+    Missing,
+    Unreadable,
 }
 
 /// Check the cache for freshness. If it's stale or missing, show a warning.
-fn check_cache(args: &Args, enable_styles: bool) -> CheckCacheResult {
-    match Cache::freshness() {
-        CacheFreshness::Fresh => CheckCacheResult::CacheFound,
-        CacheFreshness::Stale(_) if args.quiet => CheckCacheResult::CacheFound,
+fn check_cache(args: &Args, config: &Config, enable_styles: bool) -> CheckCacheResult {
+    let freshness = match Cache::freshness() {
+        Ok(freshness) => freshness,
+        Err(e) => {
+            print_error(enable_styles, &e.context("Could not check the page cache"));
+            return CheckCacheResult::Unreadable;
+        }
+    };
+    match freshness {
+        CacheFreshness::Fresh => CheckCacheResult::Found,
+        CacheFreshness::Stale(_) if args.quiet => CheckCacheResult::Found,
         CacheFreshness::Stale(age) => {
-            print_warning(
+            print_warning_to(
+                config.updates.warning_stream,
                 enable_styles,
                 &format!(
                     "The cache hasn't been updated for {} days.\n\
@@ -86,7 +146,7 @@ fn check_cache(args: &Args, enable_styles: bool) -> CheckCacheResult {
                     age.as_secs() / 24 / 3600
                 ),
             );
-            CheckCacheResult::CacheFound
+            CheckCacheResult::Found
         }
         CacheFreshness::Missing => {
             print_error(
@@ -103,7 +163,7 @@ fn check_cache(args: &Args, enable_styles: bool) -> CheckCacheResult {
             println!("To create an initial config file, use `tldr --seed-config`.\n");
             println!("You can find more tips and tricks in our docs:\n");
             println!("  https://dbrgn.github.io/tealdeer/config_updates.html");
-            CheckCacheResult::CacheMissing
+            CheckCacheResult::Missing
         }
     }
 }
@@ -119,14 +179,54 @@ fn clear_cache(quietly: bool, enable_styles: bool) {
     }
 }
 
-/// Update the cache
-fn update_cache(cache: &Cache, quietly: bool, enable_styles: bool) {
-    cache.update().unwrap_or_else(|e| {
+/// Print what clearing the cache would delete, without deleting anything
+fn show_clear_cache_targets(enable_styles: bool) {
+    let targets = Cache::clear_targets().unwrap_or_else(|e| {
+        print_error(enable_styles, &e.context("Could not clear cache"));
+        process::exit(1);
+    });
+    if targets.is_empty() {
+        match Cache::get_cache_dir() {
+            Ok((cache_dir, _)) => println!(
+                "Nothing to delete, there are no pages in {}.",
+                cache_dir.display()
+            ),
+            Err(_) => println!("Nothing to delete."),
+        }
+    }
+    for pages_dir in targets {
+        let size = utils::format_size(Cache::dir_size(&pages_dir));
+        println!("Would delete {} ({size})", pages_dir.display());
+    }
+}
+
+/// Update the cache. If `quiet_success` is set, only the success message is
+/// suppressed (see `updates.quiet_success`), `quietly` suppresses all
+/// messages except for errors and warnings.
+fn update_cache(cache: &Cache, quietly: bool, quiet_success: bool, enable_styles: bool) {
+    let summary = cache.update().unwrap_or_else(|e| {
         print_error(enable_styles, &e.context("Could not update cache"));
         process::exit(1);
     });
-    if !quietly {
-        eprintln!("Successfully updated cache.");
+    if let Err(e) = cache.verify() {
+        print_warning(
+            enable_styles,
+            &format!("{e:#}. The downloaded archive may be malformed."),
+        );
+    }
+    if let Some(ref path) = summary.kept_archive {
+        if !quietly {
+            eprintln!("Saved the downloaded archive to {}.", path.display());
+        }
+    }
+    if quietly {
+        return;
+    }
+    match (summary.mirror_url, quiet_success) {
+        (Some(url), false) => eprintln!("Successfully updated cache (using mirror {url})."),
+        (None, false) => eprintln!("Successfully updated cache."),
+        (Some(url), true) => eprintln!("Downloaded the archive from mirror {url}."),
+        (None, true) => {}
     }
 }
 
@@ -191,6 +291,380 @@ fn show_paths(config: &Config) {
     println!("Cache dir:        {}", cache_dir);
     println!("Pages dir:        {}", pages_dir);
     println!("Custom pages dir: {}", custom_pages_dir);
+    if config.sources.is_empty() {
+        println!("Sources:          [None]");
+    }
+    for (i, source) in config.sources.iter().enumerate() {
+        println!(
+            "{}{} ({}{})",
+            if i == 0 {
+                "Sources:          "
+            } else {
+                "                  "
+            },
+            source.name,
+            source.path.display(),
+            if source.enabled { "" } else { ", disabled" },
+        );
+    }
+}
+
+/// The paths shown by `--show-paths` in a machine readable format. Paths that
+/// are not available (e.g. because of an error) are `None`.
+#[derive(Serialize)]
+struct Paths {
+    config_dir: Option<String>,
+    config_path: Option<String>,
+    cache_dir: Option<String>,
+    pages_dir: Option<String>,
+    custom_pages_dir: Option<String>,
+    sources: Vec<SourcePaths>,
+}
+
+#[derive(Serialize)]
+struct SourcePaths {
+    name: String,
+    path: String,
+    enabled: bool,
+}
+
+/// Show file paths for scripts, either as a JSON object or one per line in
+/// the order config dir, config path, cache dir, pages dir and custom pages
+/// dir. Paths that are not available are printed as empty lines.
+fn show_raw_paths(config: &Config, format: OutputFormat) {
+    let to_string = |path: &Path| path.to_string_lossy().into_owned();
+    let cache_dir = Cache::get_cache_dir().ok().map(|(path, _)| path);
+    let paths = Paths {
+        config_dir: get_config_dir().ok().map(|(path, _)| to_string(&path)),
+        config_path: get_config_path().ok().map(|(path, _)| to_string(&path)),
+        cache_dir: cache_dir.as_deref().map(to_string),
+        pages_dir: cache_dir.map(|path| to_string(&path.join(TLDR_PAGES_DIR))),
+        custom_pages_dir: config
+            .directories
+            .custom_pages_dir
+            .as_deref()
+            .map(to_string),
+        sources: config
+            .sources
+            .iter()
+            .map(|source| SourcePaths {
+                name: source.name.clone(),
+                path: to_string(&source.path),
+                enabled: source.enabled,
+            })
+            .collect(),
+    };
+    match format {
+        OutputFormat::Text => {
+            for path in [
+                paths.config_dir,
+                paths.config_path,
+                paths.cache_dir,
+                paths.pages_dir,
+                paths.custom_pages_dir,
+            ] {
+                println!("{}", path.unwrap_or_default());
+            }
+        }
+        OutputFormat::Json => println!(
+            "{}",
+            serde_json::to_string(&paths).expect("Could not serialize paths")
+        ),
+    }
+}
+
+/// Show the pinned ref the cache was downloaded for, or its version if it
+/// tracks the latest pages (`latest` if the version is unknown).
+fn show_cache_version(config: &Config, enable_styles: bool) {
+    let result = Cache::cached_archive_ref().and_then(|archive_ref| match archive_ref {
+        Some(archive_ref) => Ok(Some(archive_ref)),
+        None => Cache::cached_version().map(|version| version.map(|v| format!("latest ({v})"))),
+    });
+    let cached = match result {
+        Ok(cached) => cached,
+        Err(e) => {
+            print_error(
+                enable_styles,
+                &e.context("Could not read the cache version"),
+            );
+            process::exit(1);
+        }
+    };
+    println!("{}", cached.as_deref().unwrap_or("latest"));
+
+    if let Some(ref archive_ref) = config.updates.archive_ref {
+        if cached.as_deref() != Some(archive_ref) {
+            print_warning(
+                enable_styles,
+                &format!(
+                    "The cache was not downloaded for the pinned ref `{archive_ref}` \
+                     (see `updates.archive_ref`), run `tldr --update` to download it."
+                ),
+            );
+        }
+    }
+}
+
+/// Show the cache contents as a tree, with the number of pages per platform
+fn show_tree(quietly: bool, enable_styles: bool) {
+    let tree = Cache::page_tree().unwrap_or_else(|e| {
+        print_error(
+            enable_styles,
+            &e.context("Could not read the cache directory"),
+        );
+        process::exit(1);
+    });
+    for (language, platforms) in tree {
+        println!("{language}/");
+        let mut platforms = platforms.into_iter().peekable();
+        while let Some((platform, count)) = platforms.next() {
+            let branch = if platforms.peek().is_some() {
+                "├──"
+            } else {
+                "└──"
+            };
+            if quietly {
+                println!("{branch} {platform}/");
+            } else {
+                println!("{branch} {platform}/ ({count})");
+            }
+        }
+    }
+}
+
+/// Replace the `{{name}}` placeholders in `template` with the values of the
+/// `tokens` with the same name. Placeholders without a token are kept as they
+/// are, their names are returned as well.
+fn render_template(template: &str, tokens: &[(&str, &str)]) -> (String, Vec<String>) {
+    let mut rendered = String::new();
+    let mut unknown = Vec::new();
+    let mut rest = template;
+    while let Some((before, after)) = rest.split_once("{{") {
+        rendered.push_str(before);
+        if let Some((name, after_token)) = after.split_once("}}") {
+            if let Some((_, value)) = tokens.iter().find(|(token, _)| *token == name) {
+                rendered.push_str(value);
+            } else {
+                rendered.push_str("{{");
+                rendered.push_str(name);
+                rendered.push_str("}}");
+                unknown.push(name.to_string());
+            }
+            rest = after_token;
+        } else {
+            rendered.push_str("{{");
+            rest = after;
+        }
+    }
+    rendered.push_str(rest);
+    (rendered, unknown)
+}
+
+/// Return the names of up to three pages that are similar to `name`,
+/// separated by commas (see `display.not_found_message`).
+fn similar_pages(
+    cache: &Cache,
+    name: &str,
+    languages: &[String],
+    custom_pages_dir: Option<&Path>,
+) -> String {
+    let mut pages: Vec<String> = languages
+        .iter()
+        .filter_map(|language| cache.list_pages(language, custom_pages_dir).ok())
+        .flatten()
+        .collect();
+    pages.sort();
+    pages.dedup();
+    utils::similar_names(name, pages.iter().map(String::as_str), 3).join(", ")
+}
+
+/// Return the first line of the description of a page, if it can be read.
+fn page_description(lookup_result: &PageLookupResult) -> Option<String> {
+    let reader = lookup_result.reader().ok()?;
+    LineIterator::new(reader)
+        .parse()
+        .description
+        .into_iter()
+        .next()
+}
+
+/// Export all pages in `language` into a single markdown file at `path` and
+/// return the number of exported pages.
+fn export_pages(
+    cache: &Cache,
+    path: &Path,
+    language: &str,
+    custom_pages_dir: Option<&Path>,
+) -> Result<usize> {
+    let pages = cache
+        .list_pages(language, custom_pages_dir)
+        .context("Could not get list of pages")?;
+    let file = File::create(path)
+        .with_context(|| format!("Could not create export file {}", path.display()))?;
+    let mut writer = BufWriter::new(file);
+
+    writeln!(writer, "# tldr pages\n").context("Could not write the exported page")?;
+    let languages = [language.to_string()];
+    let mut count = 0;
+    for page in &pages {
+        if let Some(lookup_result) = cache.find_page(page, &languages, custom_pages_dir) {
+            export_page(&mut writer, &lookup_result)
+                .with_context(|| format!("Could not export page `{page}`"))?;
+            count += 1;
+        }
+    }
+    writer
+        .flush()
+        .with_context(|| format!("Could not write export file {}", path.display()))?;
+    Ok(count)
+}
+
+/// Export all pages in `language` into one HTML file per page in the
+/// directory `dir` (which is created if needed), and return the number of
+/// exported pages.
+fn export_html_pages(
+    cache: &Cache,
+    dir: &Path,
+    language: &str,
+    custom_pages_dir: Option<&Path>,
+) -> Result<usize> {
+    let pages = cache
+        .list_pages(language, custom_pages_dir)
+        .context("Could not get list of pages")?;
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Could not create export directory {}", dir.display()))?;
+
+    let languages = [language.to_string()];
+    let mut count = 0;
+    for page in &pages {
+        if let Some(lookup_result) = cache.find_page(page, &languages, custom_pages_dir) {
+            let parsed = LineIterator::new(lookup_result.reader()?).parse();
+            let path = dir.join(format!("{page}.html"));
+            fs::write(&path, html::page_html(&parsed, page, language))
+                .with_context(|| format!("Could not write export file {}", path.display()))?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Print the paths that are considered when looking up a page, in order of
+/// precedence, each prefixed with its status (`chosen`, `exists` or
+/// `missing`). If the chosen page is patched, the patch is printed last.
+///
+/// Returns whether a page was found.
+fn explain_page(
+    cache: &Cache,
+    name: &str,
+    languages: &[String],
+    custom_pages_dir: Option<&Path>,
+) -> Result<bool> {
+    let mut chosen = None;
+    let candidates = cache.page_candidates(name, languages, custom_pages_dir)?;
+    for (i, candidate) in candidates.iter().enumerate() {
+        let status = if !candidate.path.is_file() {
+            "missing"
+        } else if chosen.is_some() {
+            "exists"
+        } else {
+            chosen = Some(i);
+            "chosen"
+        };
+        println!("{status}\t{}", candidate.path.display());
+    }
+
+    // Patches are not applied to custom pages and pages from sources, which
+    // always come first
+    let chosen_candidate = chosen.map(|i| &candidates[i]);
+    let unpatched_page_chosen = (custom_pages_dir.is_some() && chosen == Some(0))
+        || chosen_candidate.map_or(false, |candidate| candidate.source_name.is_some());
+    if let Some(source_name) = chosen_candidate.and_then(|c| c.source_name.as_deref()) {
+        println!("source\t{source_name}");
+    }
+    if chosen.is_some() && !unpatched_page_chosen {
+        if let Some(patch_path) = Cache::patch_path(name, custom_pages_dir) {
+            println!("patch\t{}", patch_path.display());
+        }
+    }
+    Ok(chosen.is_some())
+}
+
+/// Look up a page only in the page source named `source_name` (see
+/// `--source`).
+fn find_page_in_source(
+    cache: &Cache,
+    config: &Config,
+    source_name: &str,
+    name: &str,
+    platform: PlatformType,
+) -> Result<PageLookupResult> {
+    let source = config
+        .sources
+        .iter()
+        .find(|source| source.name == source_name)
+        .with_context(|| {
+            let names: Vec<&str> = config.sources.iter().map(|s| s.name.as_str()).collect();
+            format!(
+                "Unknown source `{source_name}` (configured sources: {})",
+                if names.is_empty() {
+                    "none".to_string()
+                } else {
+                    names.join(", ")
+                }
+            )
+        })?;
+    ensure!(source.enabled, "The source `{source_name}` is disabled");
+    let candidate = cache
+        .find_source_page(&format!("{name}.page"), Some(source_name))
+        .with_context(|| format!("Page `{name}` not found in source `{source_name}`"))?;
+    Ok(PageLookupResult::with_candidate(candidate)
+        .with_target_platform(Some(platform))
+        .with_source(PageSource::Custom))
+}
+
+/// A `tldr` function for bash and zsh that updates the cache once per day
+/// before showing a page (see `--print-shell-hook`).
+const POSIX_SHELL_HOOK: &str = r#"tldr() {
+    local stamp="${XDG_CACHE_HOME:-$HOME/.cache}/tealdeer-hook-updated"
+    local today
+    today="$(date +%Y-%m-%d)"
+    if [ "$(cat "$stamp" 2>/dev/null)" != "$today" ]; then
+        mkdir -p "$(dirname "$stamp")"
+        command tldr --update --quiet && printf '%s\n' "$today" > "$stamp"
+    fi
+    command tldr "$@"
+}
+"#;
+
+/// The fish version of `POSIX_SHELL_HOOK`.
+const FISH_SHELL_HOOK: &str = r#"function tldr --wraps tldr --description 'tldr, updating the cache once per day'
+    set -q XDG_CACHE_HOME; and set -l cache_dir $XDG_CACHE_HOME; or set -l cache_dir $HOME/.cache
+    set -l stamp $cache_dir/tealdeer-hook-updated
+    set -l today (date +%Y-%m-%d)
+    set -l last_update (cat $stamp 2>/dev/null)
+    if test "$last_update" != "$today"
+        mkdir -p $cache_dir
+        command tldr --update --quiet; and printf '%s\n' $today > $stamp
+    end
+    command tldr $argv
+end
+"#;
+
+/// Return the shell hook for `shell` (one of the values accepted by
+/// `--print-shell-hook`).
+fn shell_hook(shell: &str) -> &'static str {
+    match shell {
+        "fish" => FISH_SHELL_HOOK,
+        _ => POSIX_SHELL_HOOK,
+    }
+}
+
+/// Show the style that is applied to the given style target.
+fn show_style(config: &Config, target: &str) {
+    // The target has already been validated by clap
+    let style = config.style.get(target).expect("Invalid style target");
+    println!("{}", style.paint(target));
+    println!("{}", describe_style(&style));
 }
 
 /// Create seed config file and exit
@@ -210,6 +684,91 @@ fn create_config_and_exit(enable_styles: bool) {
     }
 }
 
+/// Return the editor command (program and arguments) from the values of
+/// `$VISUAL` and `$EDITOR`, in that order of preference.
+fn get_editor(env_visual: Option<&str>, env_editor: Option<&str>) -> Result<Vec<String>> {
+    let editor = [env_visual, env_editor]
+        .into_iter()
+        .flatten()
+        .find(|editor| !editor.trim().is_empty())
+        .context("Neither $VISUAL nor $EDITOR is set")?;
+    split_command(editor)
+}
+
+/// Open the custom page for the given command in the user's editor.
+fn edit_page(config: &Config, command: &str) -> Result<()> {
+    let custom_pages_dir = config
+        .directories
+        .custom_pages_dir
+        .as_deref()
+        .context("To edit pages, `directories.custom_pages_dir` must be configured")?;
+    fs::create_dir_all(custom_pages_dir).with_context(|| {
+        format!(
+            "Could not create custom pages directory {}",
+            custom_pages_dir.display()
+        )
+    })?;
+    let page_path = custom_pages_dir.join(format!("{command}.page"));
+
+    let editor = get_editor(
+        env::var("VISUAL").ok().as_deref(),
+        env::var("EDITOR").ok().as_deref(),
+    )?;
+    let status = process::Command::new(&editor[0])
+        .args(&editor[1..])
+        .arg(&page_path)
+        .status()
+        .with_context(|| format!("Could not launch editor {:?}", editor[0]))?;
+    ensure!(status.success(), "Editor exited with {status}");
+    Ok(())
+}
+
+/// How often the file is checked for changes in `--watch` mode.
+#[cfg(feature = "watch")]
+const WATCH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Render the file at `path`, and render it again (after clearing the screen)
+/// whenever its modification time changes. This never returns, the loop is
+/// ended by interrupting the process (e.g. with Ctrl-C).
+#[cfg(feature = "watch")]
+fn watch_file(
+    path: &Path,
+    grep: Option<&str>,
+    options: &RenderOptions,
+    enable_styles: bool,
+    config: &Config,
+) -> ! {
+    // A pager would block the loop
+    let mut config = config.clone();
+    config.display.use_pager = false;
+
+    let mut last_modified = None;
+    loop {
+        // The file may be missing for a moment while an editor saves it
+        let modified = fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        if modified.is_some() && modified != last_modified {
+            last_modified = modified;
+            // Clear the screen and move the cursor to the top left corner
+            print!("\x1b[2J\x1b[H");
+            if let Err(ref e) = print_page(
+                &PageLookupResult::with_page(path.to_path_buf()),
+                None,
+                grep,
+                options,
+                enable_styles,
+                false,
+                &config,
+            ) {
+                print_error(enable_styles, e);
+            }
+            std::io::stdout().flush().ok();
+        }
+        thread::sleep(WATCH_INTERVAL);
+    }
+}
+
 #[cfg(feature = "logging")]
 fn init_log() {
     env_logger::init();
@@ -247,11 +806,69 @@ fn get_languages(env_lang: Option<&str>, env_language: Option<&str>) -> Vec<Stri
     lang_list.into_iter().map(str::to_string).collect()
 }
 
-fn get_languages_from_env() -> Vec<String> {
-    get_languages(
-        std::env::var("LANG").ok().as_deref(),
-        std::env::var("LANGUAGE").ok().as_deref(),
-    )
+/// Return the locale for messages, following the POSIX precedence: `LC_ALL`
+/// overrides `LC_MESSAGES`, which overrides `LANG`. Empty values are ignored.
+fn get_messages_locale<'a>(
+    env_lc_all: Option<&'a str>,
+    env_lc_messages: Option<&'a str>,
+    env_lang: Option<&'a str>,
+) -> Option<&'a str> {
+    [env_lc_all, env_lc_messages, env_lang]
+        .into_iter()
+        .flatten()
+        .find(|locale| !locale.is_empty())
+}
+
+/// Return the languages from the `LC_ALL`, `LC_MESSAGES`, `LANG` and
+/// `LANGUAGE` env variables. The variables are only parsed once, subsequent
+/// calls return the same list.
+fn get_languages_from_env() -> &'static [String] {
+    static LANGUAGES: OnceCell<Vec<String>> = OnceCell::new();
+    LANGUAGES.get_or_init(|| {
+        let var = |name| std::env::var(name).ok();
+        let (lc_all, lc_messages, lang) = (var("LC_ALL"), var("LC_MESSAGES"), var("LANG"));
+        get_languages(
+            get_messages_locale(lc_all.as_deref(), lc_messages.as_deref(), lang.as_deref()),
+            var("LANGUAGE").as_deref(),
+        )
+    })
+}
+
+/// Print `languages` one per line, marking the languages that are missing
+/// from the cache (unless the cache is missing entirely).
+fn show_languages(languages: &[String]) {
+    let available_languages = Cache::available_languages().unwrap_or_default();
+    for language in languages {
+        if available_languages.is_empty() || available_languages.contains(language) {
+            println!("{language}");
+        } else {
+            println!("{language} (not in cache)");
+        }
+    }
+}
+
+/// Split a trailing `@<language>` (e.g. `tar@de`) off the last command
+/// argument and return the language.
+///
+/// The suffix is only split off if it is one of the `available_languages`,
+/// so that command names containing an `@` are still looked up as they are.
+fn split_language_suffix(command: &mut [String], available_languages: &[String]) -> Option<String> {
+    let last = command.last_mut()?;
+    let (name, language) = last.rsplit_once('@')?;
+    if name.is_empty() || !available_languages.iter().any(|lang| lang == language) {
+        return None;
+    }
+    let language = language.to_string();
+    let name_len = name.len();
+    last.truncate(name_len);
+    Some(language)
+}
+
+/// Return whether the `TEALDEER_IGNORE_BAD_CONFIG` env variable is set (to any
+/// value except `0`), which has the same effect as `--ignore-bad-config`.
+fn ignore_bad_config_from_env() -> bool {
+    env::var_os("TEALDEER_IGNORE_BAD_CONFIG")
+        .map_or(false, |value| !value.is_empty() && value != "0")
 }
 
 fn main() {
@@ -259,14 +876,43 @@ fn main() {
     init_log();
 
     // Parse arguments
-    let mut args = Args::parse();
+    let mut args = match Args::try_parse() {
+        Ok(args) => args,
+        // Show the default command instead of the usage, if one is configured
+        Err(e) if e.kind() == ErrorKind::DisplayHelpOnMissingArgumentOrSubcommand => {
+            let default_command = match Config::load() {
+                Ok(config) => config.display.default_command,
+                Err(load_error) => {
+                    print_warning(
+                        false,
+                        &format!(
+                            "Could not load config, ignoring `display.default_command`: \
+                             {load_error:#}"
+                        ),
+                    );
+                    None
+                }
+            };
+            // The command has been validated when loading the config
+            match default_command.and_then(|command| split_command(&command).ok()) {
+                Some(words) => Args::parse_from(
+                    ["tldr".to_string(), "--".to_string()]
+                        .into_iter()
+                        .chain(words),
+                ),
+                None => e.exit(),
+            }
+        }
+        Err(e) => e.exit(),
+    };
+    utils::set_json_messages(args.format == Some(OutputFormat::Json));
 
     // Determine the usage of styles
     #[cfg(target_os = "windows")]
     let ansi_support = ansi_term::enable_ansi_support().is_ok();
     #[cfg(not(target_os = "windows"))]
     let ansi_support = true;
-    let enable_styles = match args.color.unwrap_or_default() {
+    let use_styles = |color: ColorOptions| match color {
         // Attempt to use styling if instructed
         ColorOptions::Always => true,
         // Enable styling if:
@@ -280,6 +926,52 @@ fn main() {
         ColorOptions::Never => false,
     };
 
+    // Only the paths have a plain text format
+    if args.format == Some(OutputFormat::Text) && !args.show_paths {
+        print_error(
+            use_styles(args.color.unwrap_or_default()),
+            &anyhow::anyhow!("The `text` format requires --show-paths"),
+        );
+        process::exit(1);
+    }
+
+    // Look up config file, if none is found fall back to default config.
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(e) if args.ignore_bad_config || ignore_bad_config_from_env() => {
+            let enable_styles = use_styles(args.color.unwrap_or_default());
+            print_warning(
+                enable_styles,
+                &format!("Could not load config, using the default config instead: {e:#}"),
+            );
+            Config::default()
+        }
+        Err(e) => {
+            let enable_styles = use_styles(args.color.unwrap_or_default());
+            print_error(enable_styles, &e.context("Could not load config"));
+            process::exit(1);
+        }
+    };
+
+    // The `--color` flag takes precedence over the config
+    let enable_styles = use_styles(args.color.unwrap_or(config.display.color));
+    if enable_styles {
+        // The `--theme` flag takes precedence over the config as well
+        let theme = args.theme.unwrap_or(config.display.theme);
+        if theme.is_light(env::var("COLORFGBG").ok().as_deref()) {
+            config.use_light_theme();
+        }
+        let depth = args.color_depth.unwrap_or_else(|| {
+            ColorDepth::detect(
+                env::var("COLORTERM").ok().as_deref(),
+                env::var("TERM").ok().as_deref(),
+            )
+        });
+        config.limit_color_depth(depth);
+    } else {
+        config.disable_styles();
+    }
+
     // Handle renamed arguments
     if args.markdown {
         args.raw = true;
@@ -305,18 +997,61 @@ fn main() {
         show_config_path(enable_styles);
     }
 
-    // Look up config file, if none is found fall back to default config.
-    let config = match Config::load(enable_styles) {
-        Ok(config) => config,
-        Err(e) => {
-            print_error(enable_styles, &e.context("Could not load config"));
-            process::exit(1);
-        }
+    // Suppress informational notes while rendering
+    if args.quiet {
+        config.display.empty_page_note = false;
+        config.display.warn_unknown_syntax = false;
+    }
+    if args.max_lines == Some(0) {
+        print_error(
+            enable_styles,
+            &anyhow::anyhow!("The number of lines must be at least 1"),
+        );
+        process::exit(1);
+    }
+    let options = RenderOptions {
+        raw: args.raw,
+        highlight_markdown: enable_styles,
+        reverse_examples: args.reverse,
+        example_command: args.example.filter(|_| args.raw_command),
+        line_numbers: args.line_numbers,
+        debug_width: args.debug_width && !args.quiet,
+        debug_tokens: args.debug_tokens,
+        max_lines: args.max_lines,
     };
 
     // Show various paths
     if args.show_paths {
-        show_paths(&config);
+        match args.format.or(if args.quiet {
+            Some(OutputFormat::Text)
+        } else {
+            None
+        }) {
+            Some(format) => show_raw_paths(&config, format),
+            None => show_paths(&config),
+        }
+    }
+
+    // Show the language precedence and exit
+    if args.show_languages {
+        let languages = args
+            .language
+            .clone()
+            .map_or_else(|| get_languages_from_env().to_vec(), |lang| vec![lang]);
+        show_languages(&languages);
+        process::exit(0);
+    }
+
+    // Show the resolved style of a line type and exit
+    if let Some(ref target) = args.show_style {
+        show_style(&config, target);
+        process::exit(0);
+    }
+
+    // Print the shell hook and exit
+    if let Some(ref shell) = args.print_shell_hook {
+        print!("{}", shell_hook(shell));
+        process::exit(0);
     }
 
     // Create a basic config and exit
@@ -329,8 +1064,82 @@ fn main() {
 
     // If a local file was passed in, render it and exit
     if let Some(file) = args.render {
+        // Render all files matching a glob pattern (unless a file with that
+        // name exists)
+        if !file.exists() && is_glob(&file.to_string_lossy()) {
+            let paths = expand_glob(&file).unwrap_or_else(|e| {
+                print_error(
+                    enable_styles,
+                    &e.context("Could not expand the glob pattern"),
+                );
+                process::exit(1);
+            });
+            if paths.is_empty() {
+                print_error(
+                    enable_styles,
+                    &anyhow::anyhow!("No files match the pattern `{}`", file.display()),
+                );
+                process::exit(1);
+            }
+            let success = print_file_pages(
+                &paths,
+                args.grep.as_deref(),
+                &options,
+                enable_styles,
+                args.pager,
+                &config,
+            );
+            process::exit(i32::from(!success));
+        }
+
+        #[cfg(feature = "watch")]
+        if args.watch {
+            if !file.is_file() {
+                print_error(
+                    enable_styles,
+                    &anyhow::anyhow!("Could not watch {}: Not a file", file.display()),
+                );
+                process::exit(1);
+            }
+            watch_file(
+                &file,
+                args.grep.as_deref(),
+                &options,
+                enable_styles,
+                &config,
+            );
+        }
+
         let path = PageLookupResult::with_page(file);
-        if let Err(ref e) = print_page(&path, args.raw, enable_styles, args.pager, &config) {
+        if let Err(ref e) = print_page(
+            &path,
+            None,
+            args.grep.as_deref(),
+            &options,
+            enable_styles,
+            args.pager,
+            &config,
+        ) {
+            print_error(enable_styles, e);
+            process::exit(1);
+        } else {
+            process::exit(0);
+        };
+    }
+
+    // Render the built-in demo page and exit (this does not need a cache)
+    if args.demo {
+        let path = PageLookupResult::with_page(PathBuf::from("tldr.md"))
+            .with_source(PageSource::Embedded(DEMO_PAGE));
+        if let Err(ref e) = print_page(
+            &path,
+            None,
+            args.grep.as_deref(),
+            &options,
+            enable_styles,
+            args.pager,
+            &config,
+        ) {
             print_error(enable_styles, e);
             process::exit(1);
         } else {
@@ -339,16 +1148,99 @@ fn main() {
     }
 
     // Initialize cache
-    let cache = Cache::new(ARCHIVE_URL, platform);
+    // Note: An explicitly requested platform takes precedence over the
+    // configured fallback order.
+    let platform_fallback = if args.platform.is_some() {
+        None
+    } else {
+        config.directories.platform_fallback.clone().or_else(|| {
+            // On WSL, fall back to Windows pages for Windows tools
+            (config.directories.wsl_prefer_windows && PlatformType::is_wsl())
+                .then(|| ["linux", "common", "windows"].map(String::from).to_vec())
+        })
+    };
+    let archive_urls = &config.updates.archive_urls;
+    let default_archive_url = if config.updates.archive_ref.is_some() {
+        ARCHIVE_REF_URL
+    } else {
+        ARCHIVE_URL
+    };
+    let cache = Cache::new(
+        archive_urls
+            .first()
+            .map_or(default_archive_url, String::as_str),
+        platform,
+    )
+    .with_mirror_urls(archive_urls.iter().skip(1).cloned().collect())
+    .with_keep_archive(args.keep_archive.clone())
+    .with_max_archive_size(config.updates.max_archive_size)
+    .with_cache_dir_mode(config.updates.cache_dir_mode)
+    .with_pages_subdir(config.updates.pages_subdir.clone())
+    .with_archive_ref(config.updates.archive_ref.clone())
+    .with_update_languages(
+        // `--update --language` only updates the given language
+        match (args.update, &args.language) {
+            (true, Some(language)) => Some(vec![language.clone()]),
+            _ => config.updates.languages.clone(),
+        },
+    )
+    .with_delta_url(config.updates.delta_url.clone())
+    .with_platform_fallback(platform_fallback)
+    .with_merge_common(config.directories.merge_common)
+    .with_fill_from_english(config.display.fill_from_english)
+    .with_case_insensitive(config.directories.case_insensitive)
+    .with_normalize_separators(config.directories.normalize_separators)
+    .with_skip_broken_symlinks(config.directories.skip_broken_symlinks)
+    .with_sources(
+        config
+            .sources
+            .iter()
+            .filter(|source| source.enabled)
+            .map(|source| (source.name.clone(), source.path.clone()))
+            .collect(),
+    );
 
     // Clear cache, pass through
-    if args.clear_cache {
+    if args.clear_cache && args.dry_run {
+        show_clear_cache_targets(enable_styles);
+    } else if args.clear_cache {
         clear_cache(args.quiet, enable_styles);
     }
 
+    // Clear very old cache, pass through
+    if should_auto_clear_cache(&config) {
+        if !args.quiet {
+            print_info(&format!(
+                "The cache has not been updated for more than {} hours, clearing it.",
+                config
+                    .updates
+                    .auto_clear_after
+                    .unwrap_or_default()
+                    .as_secs()
+                    / 3600
+            ));
+        }
+        clear_cache(args.quiet, enable_styles);
+    }
+
+    // Edit the custom page and exit
+    if args.edit {
+        let command = args.command.join("-").to_lowercase();
+        if let Err(ref e) = edit_page(&config, &command) {
+            print_error(enable_styles, e);
+            process::exit(1);
+        }
+        process::exit(0);
+    }
+
     // Cache update, pass through
-    let cache_updated = if should_update_cache(&args, &config) {
-        update_cache(&cache, args.quiet, enable_styles);
+    let cache_updated = if should_update_cache(&cache, &args, &config) {
+        update_cache(
+            &cache,
+            args.quiet,
+            config.updates.quiet_success,
+            enable_styles,
+        );
         true
     } else {
         false
@@ -356,63 +1248,376 @@ fn main() {
 
     // Check cache presence and freshness
     if !cache_updated
-        && (args.list || !args.command.is_empty())
-        && check_cache(&args, enable_styles) == CheckCacheResult::CacheMissing
+        && (args.list
+            || args.count
+            || args.tree
+            || args.cache_version
+            || args.export.is_some()
+            || args.export_html.is_some()
+            || args.search.is_some()
+            || !args.command.is_empty())
+        && check_cache(&args, &config, enable_styles) != CheckCacheResult::Found
     {
         process::exit(1);
     }
 
     // List cached commands and exit
-    if args.list {
-        // Get list of pages
+    if args.list || args.count {
+        let language = args.language.as_deref().unwrap_or("en");
+        let custom_pages_dir = config.directories.custom_pages_dir.as_deref();
+        let separator = if args.print0 { '\0' } else { '\n' };
+        // The number of listed pages
+        let result = if args.with_description {
+            cache
+                .list_pages(language, custom_pages_dir)
+                .and_then(|pages| {
+                    let languages = [language.to_string()];
+                    let pages: Vec<(String, Option<String>)> = pages
+                        .into_iter()
+                        .map(|page| {
+                            let description = cache
+                                .find_page(&page, &languages, custom_pages_dir)
+                                .and_then(|lookup_result| page_description(&lookup_result));
+                            (page, description)
+                        })
+                        .collect();
+                    print_page_list(&pages, &config).map(|()| pages.len())
+                })
+        } else if args.count || args.sorted {
+            cache.list_pages(language, custom_pages_dir).map(|pages| {
+                if args.count {
+                    println!("{}", pages.len());
+                } else {
+                    for page in &pages {
+                        print!("{page}{separator}");
+                    }
+                }
+                pages.len()
+            })
+        } else {
+            // Print the pages as soon as they are found
+            let mut count = 0;
+            cache
+                .for_each_page(language, custom_pages_dir, |page| {
+                    print!("{page}{separator}");
+                    count += 1;
+                })
+                .map(|()| count)
+        };
+        match result {
+            // A count of zero is a valid answer, but an empty list is most
+            // likely caused by a broken cache
+            Ok(0) if !args.count => {
+                print_error(
+                    enable_styles,
+                    &anyhow::anyhow!("No pages found, try `tldr --update`"),
+                );
+                process::exit(1);
+            }
+            Ok(_) => {}
+            Err(e) => {
+                print_error(enable_styles, &e.context("Could not get list of pages"));
+                process::exit(1);
+            }
+        }
+        process::exit(0);
+    }
+
+    // Search pages and exit
+    if let Some(ref query) = args.search {
+        let languages = args
+            .language
+            .clone()
+            .map_or_else(|| get_languages_from_env().to_vec(), |lang| vec![lang]);
+        let concurrency = args
+            .concurrency
+            .or_else(|| thread::available_parallelism().ok())
+            .map_or(1, NonZeroUsize::get);
         let pages = cache
-            .list_pages(config.directories.custom_pages_dir.as_deref())
+            .search_pages(
+                query,
+                &languages,
+                config.directories.custom_pages_dir.as_deref(),
+                concurrency,
+            )
             .unwrap_or_else(|e| {
-                print_error(enable_styles, &e.context("Could not get list of pages"));
+                print_error(enable_styles, &e.context("Could not search pages"));
                 process::exit(1);
             });
+        for page in pages {
+            println!("{page}");
+        }
+        process::exit(0);
+    }
 
-        // Print pages
-        println!("{}", pages.join("\n"));
+    // Export all pages and exit
+    if let Some(ref path) = args.export {
+        let language = args.language.as_deref().unwrap_or("en");
+        match export_pages(
+            &cache,
+            path,
+            language,
+            config.directories.custom_pages_dir.as_deref(),
+        ) {
+            Ok(count) => {
+                if !args.quiet {
+                    eprintln!("Exported {count} pages to {}.", path.display());
+                }
+                process::exit(0);
+            }
+            Err(e) => {
+                print_error(enable_styles, &e.context("Could not export pages"));
+                process::exit(1);
+            }
+        }
+    }
+
+    // Export all pages as HTML files and exit
+    if let Some(ref dir) = args.export_html {
+        let language = args.language.as_deref().unwrap_or("en");
+        match export_html_pages(
+            &cache,
+            dir,
+            language,
+            config.directories.custom_pages_dir.as_deref(),
+        ) {
+            Ok(count) => {
+                if !args.quiet {
+                    eprintln!("Exported {count} pages to {}.", dir.display());
+                }
+                process::exit(0);
+            }
+            Err(e) => {
+                print_error(enable_styles, &e.context("Could not export pages"));
+                process::exit(1);
+            }
+        }
+    }
+
+    // Show the version of the cache and exit
+    if args.cache_version {
+        show_cache_version(&config, enable_styles);
+        process::exit(0);
+    }
+
+    // Show cache tree and exit
+    if args.tree {
+        show_tree(args.quiet, enable_styles);
         process::exit(0);
     }
 
     // Show command from cache
     if !args.command.is_empty() {
+        // Parse an inline language override (e.g. `tar@de`)
+        let inline_language = if args.command.last().map_or(false, |arg| arg.contains('@')) {
+            let available_languages = Cache::available_languages().unwrap_or_default();
+            split_language_suffix(&mut args.command, &available_languages)
+        } else {
+            None
+        };
+
         // Note: According to the TLDR client spec, page names must be transparently
         // lowercased before lookup:
         // https://github.com/tldr-pages/tldr/blob/main/CLIENT-SPECIFICATION.md#page-names
         let command = args.command.join("-").to_lowercase();
+        let original_command = args.command.join(" ");
 
         // Collect languages
-        let languages = args
-            .language
-            .map_or_else(get_languages_from_env, |lang| vec![lang]);
+        let languages = inline_language
+            .or(args.language)
+            .map_or_else(|| get_languages_from_env().to_vec(), |lang| vec![lang]);
 
-        // Search for command in cache
-        if let Some(lookup_result) = cache.find_page(
-            &command,
-            &languages,
-            config.directories.custom_pages_dir.as_deref(),
-        ) {
-            if let Err(ref e) =
-                print_page(&lookup_result, args.raw, enable_styles, args.pager, &config)
-            {
+        // Only look up the page in the requested source
+        if let Some(ref source_name) = args.source {
+            let result = find_page_in_source(&cache, &config, source_name, &command, platform)
+                .and_then(|lookup_result| {
+                    print_page(
+                        &lookup_result,
+                        Some(&original_command),
+                        args.grep.as_deref(),
+                        &options,
+                        enable_styles,
+                        args.pager,
+                        &config,
+                    )
+                });
+            if let Err(ref e) = result {
+                print_error(enable_styles, e);
+                process::exit(1);
+            }
+            process::exit(0);
+        }
+
+        // Explain the page lookup and exit
+        if args.explain {
+            match explain_page(
+                &cache,
+                &command,
+                &languages,
+                config.directories.custom_pages_dir.as_deref(),
+            ) {
+                Ok(found) => process::exit(i32::from(!found)),
+                Err(e) => {
+                    print_error(enable_styles, &e.context("Could not explain page lookup"));
+                    process::exit(1);
+                }
+            }
+        }
+
+        // Show the pages of all platforms
+        if args.all_platforms {
+            let lookup_results = cache
+                .find_page_all_platforms(
+                    &command,
+                    &languages,
+                    config.directories.custom_pages_dir.as_deref(),
+                )
+                .unwrap_or_else(|e| {
+                    print_error(enable_styles, &e.context("Could not look up page"));
+                    process::exit(1);
+                });
+            if let Err(ref e) = print_platform_pages(
+                &lookup_results,
+                Some(&original_command),
+                args.grep.as_deref(),
+                &options,
+                enable_styles,
+                args.pager,
+                &config,
+            ) {
+                print_error(enable_styles, e);
+                process::exit(1);
+            }
+            if !lookup_results.is_empty() {
+                process::exit(0);
+            }
+        }
+
+        // Search for command in cache, then for a page listing it as an alias
+        let custom_pages_dir = config.directories.custom_pages_dir.as_deref();
+        if let Some(lookup_result) = cache
+            .find_page(&command, &languages, custom_pages_dir)
+            .or_else(|| {
+                config
+                    .directories
+                    .resolve_aliases
+                    .then(|| cache.find_page_by_alias(&command, &languages, custom_pages_dir))
+                    .flatten()
+            })
+        {
+            if let Err(ref e) = print_page(
+                &lookup_result,
+                Some(&original_command),
+                args.grep.as_deref(),
+                &options,
+                enable_styles,
+                args.pager,
+                &config,
+            ) {
                 print_error(enable_styles, e);
                 process::exit(1);
             }
             process::exit(0);
         } else {
+            // Try to download the missing page, if requested
+            if args.fetch_missing {
+                match cache.fetch_page(
+                    &command,
+                    &languages,
+                    config.directories.custom_pages_dir.as_deref(),
+                ) {
+                    Ok(Some(lookup_result)) => {
+                        if let Err(ref e) = print_page(
+                            &lookup_result,
+                            Some(&original_command),
+                            args.grep.as_deref(),
+                            &options,
+                            enable_styles,
+                            args.pager,
+                            &config,
+                        ) {
+                            print_error(enable_styles, e);
+                            process::exit(1);
+                        }
+                        process::exit(0);
+                    }
+                    Ok(None) => {}
+                    Err(e) => {
+                        print_error(enable_styles, &e.context("Could not fetch missing page"));
+                        process::exit(1);
+                    }
+                }
+            }
+
+            // For a command with subcommands (e.g. `git log`), fall back to
+            // the page of the main command
+            if args.command.len() > 1 {
+                let main_command = args.command[0].to_lowercase();
+                if let Some(lookup_result) =
+                    cache.find_page(&main_command, &languages, custom_pages_dir)
+                {
+                    if !args.quiet {
+                        print_warning(
+                            enable_styles,
+                            &format!(
+                                "Page `{command}` not found in cache, showing `{main_command}` \
+                                 instead (`{}` might be a subcommand of `{main_command}`).",
+                                args.command[1..].join(" "),
+                            ),
+                        );
+                    }
+                    if let Err(ref e) = print_page(
+                        &lookup_result,
+                        Some(&args.command[0]),
+                        args.grep.as_deref(),
+                        &options,
+                        enable_styles,
+                        args.pager,
+                        &config,
+                    ) {
+                        print_error(enable_styles, e);
+                        process::exit(1);
+                    }
+                    process::exit(0);
+                }
+            }
+
             if !args.quiet {
-                print_warning(
-                    enable_styles,
-                    &format!(
+                let message = match config.display.not_found_message {
+                    Some(ref template) => {
+                        // Only look for similar pages if they are shown
+                        let suggestions = if template.contains("{{suggestions}}") {
+                            similar_pages(&cache, &command, &languages, custom_pages_dir)
+                        } else {
+                            String::new()
+                        };
+                        let (message, unknown_tokens) = render_template(
+                            template,
+                            &[
+                                ("command", &command),
+                                ("suggestions", &suggestions),
+                                ("docs_url", PAGES_REPO_URL),
+                            ],
+                        );
+                        for token in unknown_tokens {
+                            print_warning(
+                                enable_styles,
+                                &format!(
+                                    "Unknown placeholder `{{{{{token}}}}}` in \
+                                     `display.not_found_message`."
+                                ),
+                            );
+                        }
+                        message
+                    }
+                    None => format!(
                         "Page `{}` not found in cache.\n\
                          Try updating with `tldr --update`, or submit a pull request to:\n\
-                         https://github.com/tldr-pages/tldr",
+                         {PAGES_REPO_URL}",
                         &command
                     ),
-                );
+                };
+                print_warning(enable_styles, &message);
             }
             process::exit(1);
         }
@@ -421,7 +1626,84 @@ fn main() {
 
 #[cfg(test)]
 mod test {
-    use crate::get_languages;
+    use crate::{
+        get_editor, get_languages, get_languages_from_env, get_messages_locale, render_template,
+        split_language_suffix,
+    };
+
+    #[test]
+    fn test_languages_from_env_are_memoized() {
+        let first = get_languages_from_env();
+        assert!(!first.is_empty());
+        assert!(std::ptr::eq(first, get_languages_from_env()));
+    }
+
+    #[test]
+    fn test_get_editor() {
+        assert_eq!(
+            get_editor(Some("code --wait"), Some("vim")).unwrap(),
+            ["code", "--wait"]
+        );
+        assert_eq!(get_editor(Some(" "), Some("vim")).unwrap(), ["vim"]);
+        assert_eq!(
+            get_editor(None, Some("'/opt/my editor' -n")).unwrap(),
+            ["/opt/my editor", "-n"]
+        );
+        assert!(get_editor(None, None).is_err());
+        assert!(get_editor(Some(""), Some("")).is_err());
+    }
+
+    #[test]
+    fn test_render_template() {
+        let tokens = [("command", "tra"), ("suggestions", "tar")];
+        assert_eq!(
+            render_template(
+                "`{{command}}` not found. Did you mean {{suggestions}}?",
+                &tokens
+            ),
+            ("`tra` not found. Did you mean tar?".to_string(), vec![])
+        );
+        assert_eq!(
+            render_template("{{command}} {{team}} {{command}}", &tokens),
+            ("tra {{team}} tra".to_string(), vec!["team".to_string()])
+        );
+        assert_eq!(
+            render_template("{{ unterminated", &tokens),
+            ("{{ unterminated".to_string(), vec![])
+        );
+    }
+
+    mod language_suffix {
+        use super::*;
+
+        fn split(command: &[&str]) -> (Vec<String>, Option<String>) {
+            let mut command: Vec<String> = command.iter().map(ToString::to_string).collect();
+            let available_languages = ["de".to_string(), "en".to_string()];
+            let language = split_language_suffix(&mut command, &available_languages);
+            (command, language)
+        }
+
+        #[test]
+        fn known_language() {
+            assert_eq!(split(&["tar@de"]), (vec!["tar".into()], Some("de".into())));
+            assert_eq!(
+                split(&["git", "log@en"]),
+                (vec!["git".into(), "log".into()], Some("en".into()))
+            );
+        }
+
+        #[test]
+        fn unknown_language() {
+            assert_eq!(split(&["npm@latest"]), (vec!["npm@latest".into()], None));
+            assert_eq!(split(&["tar@fr"]), (vec!["tar@fr".into()], None));
+        }
+
+        #[test]
+        fn no_suffix() {
+            assert_eq!(split(&["tar"]), (vec!["tar".into()], None));
+            assert_eq!(split(&["@de"]), (vec!["@de".into()], None));
+        }
+    }
 
     mod language {
         use super::*;
@@ -460,6 +1742,24 @@ mod test {
             assert_eq!(lang_list, ["en"]);
         }
 
+        #[test]
+        fn messages_locale_precedence() {
+            let locale = get_messages_locale(Some("de_DE.UTF-8"), Some("fr_FR"), Some("en_US"));
+            assert_eq!(locale, Some("de_DE.UTF-8"));
+            let locale = get_messages_locale(None, Some("fr_FR"), Some("en_US"));
+            assert_eq!(locale, Some("fr_FR"));
+            let locale = get_messages_locale(Some(""), Some(""), Some("en_US"));
+            assert_eq!(locale, Some("en_US"));
+            assert_eq!(get_messages_locale(None, None, None), None);
+        }
+
+        #[test]
+        fn lc_all_overrides_lang() {
+            let locale = get_messages_locale(Some("de_DE.UTF-8"), None, Some("fr_FR.UTF-8"));
+            let lang_list = get_languages(locale, None);
+            assert_eq!(lang_list, ["de_DE", "de", "en"]);
+        }
+
         #[test]
         fn no_duplicates() {
             let lang_list = get_languages(Some("de"), Some("fr:de:cn:de"));
@@ -1,96 +1,1860 @@
 //! Functions for printing pages to the terminal
 
-use std::io::{self, BufRead, Write};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    mem,
+    ops::Range,
+    path::Path,
+    path::PathBuf,
+    process::{Command, Stdio},
+    thread,
+};
 
-use anyhow::{Context, Result};
+use ansi_term::Style;
+use anyhow::{bail, ensure, Context, Result};
+use log::debug;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 use crate::{
-    cache::PageLookupResult,
-    config::{Config, StyleConfig},
-    formatter::{highlight_lines, PageSnippet},
-    line_iterator::LineIterator,
+    cache::{Cache, PageLookupResult, PageSource},
+    config::{Config, Overflow},
+    formatter::{highlight_lines, strip_placeholders, usage_line, PageSnippet},
+    line_iterator::{GroupedLines, LineIterator, Page},
+    render_cache,
+    split_command::split_command,
+    types::{split_platform_tag, LineType, PlatformType},
+    utils::{print_error, print_info, print_warning, terminal_width},
 };
 
+/// A piece of text of a rendered line, along with its style.
+type Segment = (Style, String);
+
+/// Buffers the styled segments of a rendered line until the line is complete,
+/// then writes it while applying the configured overflow handling.
+struct LineWriter<W: Write> {
+    writer: W,
+    overflow: Overflow,
+    width: Option<usize>,
+    segments: Vec<Segment>,
+    link: Option<String>,
+    background: Option<(Style, usize)>,
+    line_numbers: Option<LineNumbers>,
+    surround_blank_lines: Option<SurroundBlankLines>,
+    max_lines: Option<MaxLines>,
+}
+
+/// The state of the line limit of a `LineWriter` (see `--max-lines`).
+struct MaxLines {
+    /// The maximum number of lines, including the truncation marker
+    max: usize,
+    /// The number of lines that have been written (or held back) so far
+    count: usize,
+    /// The last line that fits into the limit, which is held back until it
+    /// is known whether it has to be replaced by the truncation marker
+    last_line: Option<Vec<u8>>,
+}
+
+/// The marker that replaces the last line of a page that is cut off at the
+/// maximum number of lines.
+const TRUNCATION_MARKER: &str = "... (truncated)";
+
+/// The state of the blank line normalization of a `LineWriter` (see
+/// `display.surround_blank_lines`).
+struct SurroundBlankLines {
+    /// The number of blank lines before the first and after the last line
+    count: usize,
+    /// Whether a line with content has been written yet
+    started: bool,
+    /// The number of blank lines that are held back, because they may be at
+    /// the end of the page
+    pending: usize,
+}
+
+/// The state of the line number gutter of a `LineWriter`.
+struct LineNumbers {
+    style: Style,
+    /// The width of the numbers in columns
+    digits: usize,
+    /// The number of the next line
+    next: usize,
+}
+
+/// The separator between the line number gutter and the line.
+const GUTTER_SEPARATOR: &str = " │ ";
+
+/// The width of the section rule (see `display.section_rule`) if the
+/// terminal width is unknown.
+const DEFAULT_RULE_WIDTH: usize = 40;
+
+impl<W: Write> LineWriter<W> {
+    /// Create a line writer. Wrapping is disabled if the terminal is
+    /// narrower than `min_wrap_width` columns.
+    fn new(writer: W, overflow: Overflow, min_wrap_width: usize) -> Self {
+        let width = match overflow {
+            Overflow::None => None,
+            Overflow::Wrap => terminal_width().filter(|&width| width >= min_wrap_width),
+            Overflow::Truncate => terminal_width(),
+        };
+        Self {
+            writer,
+            overflow,
+            width,
+            segments: Vec::new(),
+            link: None,
+            background: None,
+            line_numbers: None,
+            surround_blank_lines: None,
+            max_lines: None,
+        }
+    }
+
+    /// Write at most `max` lines (counting the continuations of wrapped
+    /// lines). If there are more, the last line is replaced by a truncation
+    /// marker, which is written by `finish`.
+    fn limit_lines(&mut self, max: usize) {
+        self.max_lines = Some(MaxLines {
+            max,
+            count: 0,
+            last_line: None,
+        });
+    }
+
+    /// Replace the blank lines before the first and after the last line with
+    /// content by exactly `count` blank lines. The trailing blank lines are
+    /// written by `finish`.
+    fn surround_blank_lines(&mut self, count: usize) {
+        self.surround_blank_lines = Some(SurroundBlankLines {
+            count,
+            started: false,
+            pending: 0,
+        });
+    }
+
+    /// Prefix every line with its number in a gutter that is wide enough
+    /// for numbers up to `max_number`. The gutter is subtracted from the
+    /// width available for the lines.
+    fn number_lines(&mut self, style: Style, max_number: usize) {
+        let digits = max_number.to_string().len();
+        let gutter_width = digits + GUTTER_SEPARATOR.width();
+        self.width = self.width.map(|width| width.saturating_sub(gutter_width));
+        self.background = self
+            .background
+            .map(|(style, width)| (style, width.saturating_sub(gutter_width)));
+        self.line_numbers = Some(LineNumbers {
+            style,
+            digits,
+            next: 1,
+        });
+    }
+
+    /// Return the number of columns that are available for a line (the
+    /// terminal width without the line number gutter), if known.
+    fn available_width(&self) -> Option<usize> {
+        self.width.or_else(|| {
+            let gutter_width = self.line_numbers.as_ref().map_or(0, |line_numbers| {
+                line_numbers.digits + GUTTER_SEPARATOR.width()
+            });
+            terminal_width().map(|width| width.saturating_sub(gutter_width))
+        })
+    }
+
+    /// Fill every line up to the terminal width with the background color of
+    /// `style`. Nothing is filled if the terminal width is unknown.
+    fn fill_background(&mut self, style: Style) {
+        self.background = terminal_width().map(|width| (style, width));
+    }
+
+    /// Write the line number gutter. Continuation lines of a wrapped line
+    /// get an empty gutter, and empty lines no trailing whitespace.
+    fn write_gutter(
+        &mut self,
+        out: &mut impl Write,
+        continuation: bool,
+        empty: bool,
+    ) -> io::Result<()> {
+        if let Some(ref mut line_numbers) = self.line_numbers {
+            let number = if continuation {
+                String::new()
+            } else {
+                line_numbers.next += 1;
+                (line_numbers.next - 1).to_string()
+            };
+            let separator = if empty {
+                GUTTER_SEPARATOR.trim_end()
+            } else {
+                GUTTER_SEPARATOR
+            };
+            let gutter = format!("{number:>digits$}{separator}", digits = line_numbers.digits);
+            write!(out, "{}", line_numbers.style.paint(gutter))?;
+        }
+        Ok(())
+    }
+
+    /// Render the current line as a hyperlink to `url` (using the OSC 8
+    /// escape sequence).
+    fn set_link(&mut self, url: &str) {
+        self.link = Some(url.to_string());
+    }
+
+    /// Append a styled piece of text to the current line.
+    fn push(&mut self, style: Style, text: &str) {
+        if !text.is_empty() {
+            self.segments.push((style, text.to_string()));
+        }
+    }
+
+    /// Return whether the current line contains any text yet.
+    fn has_content(&self) -> bool {
+        !self.segments.is_empty()
+    }
+
+    /// Write the current line, followed by a newline.
+    fn end_line(&mut self) -> io::Result<()> {
+        let segments = mem::take(&mut self.segments);
+        if let Some(ref mut surround) = self.surround_blank_lines {
+            if segments.is_empty() {
+                // Leading blank lines are dropped, the others are held back
+                if surround.started {
+                    surround.pending += 1;
+                }
+                return Ok(());
+            }
+            let blank_lines = if surround.started {
+                surround.pending
+            } else {
+                surround.count
+            };
+            surround.started = true;
+            surround.pending = 0;
+            for _ in 0..blank_lines {
+                self.write_line(Vec::new())?;
+            }
+        }
+        self.write_line(segments)
+    }
+
+    /// Write the trailing blank lines of the page, if they are normalized
+    /// (see `surround_blank_lines`), and the last line or the truncation
+    /// marker if the lines are limited (see `limit_lines`).
+    fn finish(&mut self) -> io::Result<()> {
+        if let Some(SurroundBlankLines {
+            count,
+            started: true,
+            ..
+        }) = self.surround_blank_lines
+        {
+            for _ in 0..count {
+                self.write_line(Vec::new())?;
+            }
+        }
+        if let Some(max_lines) = self.max_lines.take() {
+            if max_lines.count > max_lines.max {
+                writeln!(self.writer, "  {TRUNCATION_MARKER}")?;
+            } else if let Some(line) = max_lines.last_line {
+                self.writer.write_all(&line)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write `segments` as a line (wrapped or truncated according to the
+    /// overflow handling), followed by a newline.
+    fn write_line(&mut self, segments: Vec<Segment>) -> io::Result<()> {
+        let lines = match (self.overflow, self.width) {
+            (Overflow::Truncate, Some(width)) => vec![truncate_segments(segments, width)],
+            (Overflow::Wrap, Some(width)) => wrap_segments(&segments, width),
+            _ => vec![segments],
+        };
+        let link = self.link.take();
+        let line_count = lines.len();
+        for (i, mut line) in lines.into_iter().enumerate() {
+            let mut out = Vec::new();
+            self.write_gutter(&mut out, i > 0, line.is_empty())?;
+            if let (Some(url), 0) = (&link, i) {
+                write!(out, "\x1b]8;;{url}\x1b\\")?;
+            }
+            if let Some((background, width)) = self.background {
+                line = fill_background(line, background, width);
+            }
+            for (style, text) in &line {
+                write!(out, "{}", style.paint(text.as_str()))?;
+            }
+            if link.is_some() && i + 1 == line_count {
+                write!(out, "\x1b]8;;\x1b\\")?;
+            }
+            writeln!(out)?;
+            self.emit(out)?;
+        }
+        Ok(())
+    }
+
+    /// Write a rendered line (including the newline), unless it exceeds the
+    /// line limit (see `limit_lines`).
+    fn emit(&mut self, line: Vec<u8>) -> io::Result<()> {
+        if let Some(ref mut max_lines) = self.max_lines {
+            max_lines.count += 1;
+            if max_lines.count >= max_lines.max {
+                // Only the last line that fits is kept, until the next one
+                // shows that the page has to be truncated
+                max_lines.last_line = (max_lines.count == max_lines.max).then_some(line);
+                return Ok(());
+            }
+        }
+        self.writer.write_all(&line)
+    }
+}
+
+/// Cut the segments so that they fit into `width` columns, ending with an
+/// ellipsis. Cutting happens between characters, so that escape sequences
+/// are never split.
+fn truncate_segments(segments: Vec<Segment>, width: usize) -> Vec<Segment> {
+    if segments.iter().map(|(_, text)| text.width()).sum::<usize>() <= width {
+        return segments;
+    }
+
+    // Leave room for the ellipsis
+    let mut remaining = width.saturating_sub(1);
+    let mut truncated = Vec::with_capacity(segments.len());
+    for (style, text) in segments {
+        let mut end = 0;
+        for (i, chr) in text.char_indices() {
+            let chr_width = chr.width().unwrap_or(0);
+            if chr_width > remaining {
+                break;
+            }
+            remaining -= chr_width;
+            end = i + chr.len_utf8();
+        }
+        if end < text.len() {
+            truncated.push((style, format!("{}…", &text[..end])));
+            break;
+        }
+        truncated.push((style, text));
+    }
+    truncated
+}
+
+/// Give the segments without a background color the background color of
+/// `background`, and pad them to `width` columns with spaces in that style.
+/// Every segment is painted (and reset) on its own, so the background ends
+/// with the line.
+fn fill_background(mut segments: Vec<Segment>, background: Style, width: usize) -> Vec<Segment> {
+    let line_width: usize = segments.iter().map(|(_, text)| text.width()).sum();
+    for (style, _) in &mut segments {
+        if style.background.is_none() {
+            style.background = background.background;
+        }
+    }
+    if line_width < width {
+        segments.push((background, " ".repeat(width - line_width)));
+    }
+    segments
+}
+
+/// Split the segments into lines of at most `width` columns, breaking at
+/// whitespace where possible. Continuation lines are indented like the first
+/// line.
+fn wrap_segments(segments: &[Segment], width: usize) -> Vec<Vec<Segment>> {
+    let text: String = segments.iter().map(|(_, text)| text.as_str()).collect();
+    let indent = &text[..text.len() - text.trim_start().len()];
+    if indent.width() >= width {
+        return vec![segments.to_vec()];
+    }
+
+    // Determine the byte ranges of the wrapped lines
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    let mut line_start = 0;
+    let mut content_start = indent.len();
+    let mut line_width = 0;
+    let mut last_whitespace = None;
+    let mut available = width;
+    for (i, chr) in text.char_indices() {
+        if i < line_start {
+            continue;
+        }
+        let chr_width = chr.width().unwrap_or(0);
+        if line_width + chr_width > available && i > line_start {
+            let end = last_whitespace.unwrap_or(i);
+            ranges.push(line_start..end);
+
+            // Skip the whitespace at the line break
+            let rest = &text[end..];
+            line_start = end + rest.len() - rest.trim_start().len();
+            content_start = line_start;
+            last_whitespace = None;
+            available = width - indent.width();
+            if i < line_start {
+                line_width = 0;
+                continue;
+            }
+            line_width = text[line_start..i].width();
+        }
+        if chr.is_whitespace() && i > content_start {
+            last_whitespace = Some(i);
+        }
+        line_width += chr_width;
+    }
+    ranges.push(line_start..text.len());
+
+    // Split the segments along the line ranges
+    ranges
+        .into_iter()
+        .enumerate()
+        .map(|(line_index, range)| {
+            let mut line = Vec::new();
+            if line_index > 0 {
+                line.push((Style::default(), indent.to_string()));
+            }
+            let mut segment_start = 0;
+            for (style, segment) in segments {
+                let segment_end = segment_start + segment.len();
+                let start = range.start.max(segment_start);
+                let end = range.end.min(segment_end);
+                if start < end {
+                    line.push((*style, text[start..end].to_string()));
+                }
+                segment_start = segment_end;
+            }
+            line
+        })
+        .collect()
+}
+
+/// The pager that is used if neither `display.pager_command` nor `$PAGER` is
+/// set.
+#[cfg(not(target_os = "windows"))]
+const DEFAULT_PAGER: &str = "less -R";
+
+/// Return the pager command (program and arguments) from the configured
+/// `display.pager_command` and the value of `$PAGER`, in that order of
+/// preference, falling back to `less -R`.
+#[cfg(not(target_os = "windows"))]
+fn get_pager(configured: Option<&str>, env_pager: Option<&str>) -> Result<Vec<String>> {
+    let pager = [configured, env_pager]
+        .into_iter()
+        .flatten()
+        .find(|pager| !pager.trim().is_empty())
+        .unwrap_or(DEFAULT_PAGER);
+    let words = split_command(pager)?;
+    // The pager is started by splitting the command line at whitespace
+    // again, so the words themselves must not contain any
+    ensure!(
+        !words.iter().any(|word| word.contains(char::is_whitespace)),
+        "Arguments containing whitespace are not supported in the pager command {pager:?}"
+    );
+    Ok(words)
+}
+
 /// Set up display pager
 ///
 /// SAFETY: this function may be called multiple times
 #[cfg(not(target_os = "windows"))]
-fn configure_pager(_: bool) {
+fn configure_pager(enable_styles: bool, config: &Config) {
     use std::sync::Once;
     static INIT: Once = Once::new();
-    INIT.call_once(|| pager::Pager::with_default_pager("less -R").setup());
+    INIT.call_once(|| {
+        let env_pager = std::env::var("PAGER").ok();
+        let words = get_pager(
+            config.display.pager_command.as_deref(),
+            env_pager.as_deref(),
+        )
+        .unwrap_or_else(|e| {
+            print_warning(
+                enable_styles,
+                &format!("{e:#}, using `{DEFAULT_PAGER}` instead"),
+            );
+            split_command(DEFAULT_PAGER).unwrap_or_default()
+        });
+        debug!("Using pager {:?}", words);
+        // `Pager::with_pager` only takes a command line, which it splits at
+        // whitespace. This is lossless, since `get_pager` rejects words
+        // containing whitespace.
+        pager::Pager::with_pager(&words.join(" ")).setup();
+    });
 }
 
 #[cfg(target_os = "windows")]
-fn configure_pager(enable_styles: bool) {
-    use crate::utils::print_warning;
+fn configure_pager(enable_styles: bool, _: &Config) {
     print_warning(enable_styles, "--pager flag not available on Windows!");
 }
 
+/// Render settings that are given on the command line for a single
+/// invocation, unlike the settings of the config file.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RenderOptions {
+    /// Display the raw markdown instead of rendering it (`--raw`)
+    pub raw: bool,
+    /// Highlight the raw markdown (if colors are enabled)
+    pub highlight_markdown: bool,
+    /// Show the examples in reverse order (`--reverse`)
+    pub reverse_examples: bool,
+    /// Only print the plain command of the example with this (1-based)
+    /// number (`--example` with `--raw-command`)
+    pub example_command: Option<usize>,
+    /// Prefix every rendered line with its number (`--line-numbers`)
+    pub line_numbers: bool,
+    /// Print the detected and effective render width to stderr
+    /// (`--debug-width`)
+    pub debug_width: bool,
+    /// Print the highlighted snippets instead of rendering them
+    /// (`--debug-tokens`)
+    pub debug_tokens: bool,
+    /// The maximum number of rendered lines (`--max-lines`)
+    pub max_lines: Option<usize>,
+}
+
+/// Print the pages of several platforms, each introduced by a header line
+/// with the name of the platform.
+pub fn print_platform_pages(
+    lookup_results: &[PageLookupResult],
+    command: Option<&str>,
+    grep: Option<&str>,
+    options: &RenderOptions,
+    enable_styles: bool,
+    use_pager: bool,
+    config: &Config,
+) -> Result<()> {
+    // Configure the pager before printing the first header
+    if use_pager || config.display.use_pager {
+        configure_pager(enable_styles, config);
+    }
+
+    for lookup_result in lookup_results {
+        if let Some(platform) = lookup_result.platforms().first() {
+            print_header(&format!("Platform: {platform}"), config)?;
+        }
+        print_page(
+            lookup_result,
+            command,
+            grep,
+            options,
+            enable_styles,
+            use_pager,
+            config,
+        )?;
+    }
+    Ok(())
+}
+
+/// Print the given local files, each introduced by a header line with the
+/// path of the file.
+///
+/// A file that cannot be rendered does not stop the others from being
+/// rendered, the error is printed instead. Returns whether all files have
+/// been rendered successfully.
+pub fn print_file_pages(
+    paths: &[PathBuf],
+    grep: Option<&str>,
+    options: &RenderOptions,
+    enable_styles: bool,
+    use_pager: bool,
+    config: &Config,
+) -> bool {
+    // Configure the pager before printing the first header
+    if use_pager || config.display.use_pager {
+        configure_pager(enable_styles, config);
+    }
+
+    let mut success = true;
+    for path in paths {
+        let result = print_header(&format!("File: {}", path.display()), config).and_then(|()| {
+            print_page(
+                &PageLookupResult::with_page(path.clone()),
+                None,
+                grep,
+                options,
+                enable_styles,
+                use_pager,
+                config,
+            )
+        });
+        if let Err(e) = result {
+            print_error(
+                enable_styles,
+                &e.context(format!("Could not render {}", path.display())),
+            );
+            success = false;
+        }
+    }
+    success
+}
+
+/// Print the names of pages with their descriptions (see `--list
+/// --with-description`), with the descriptions aligned in a column.
+pub fn print_page_list(pages: &[(String, Option<String>)], config: &Config) -> Result<()> {
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    for line in format_page_list(pages, config.display.max_list_name_width) {
+        writeln!(handle, "{line}").context("Could not write to stdout")?;
+    }
+    handle.flush().context("Could not flush stdout")
+}
+
+/// Format the lines of a page list with descriptions. The descriptions start
+/// in the column after the longest name, but at most after `max_name_width`
+/// columns. The description of a longer name is moved to the next line.
+fn format_page_list(pages: &[(String, Option<String>)], max_name_width: usize) -> Vec<String> {
+    const GAP: &str = "  ";
+    let name_width = pages
+        .iter()
+        .filter(|(_, description)| description.is_some())
+        .map(|(name, _)| name.width())
+        .filter(|&width| width <= max_name_width)
+        .max()
+        .unwrap_or(0);
+    let mut lines = Vec::new();
+    for (name, description) in pages {
+        match description {
+            Some(description) if name.width() <= name_width => {
+                let padding = " ".repeat(name_width - name.width());
+                lines.push(format!("{name}{padding}{GAP}{description}"));
+            }
+            Some(description) => {
+                lines.push(name.clone());
+                let indent = " ".repeat(name_width);
+                lines.push(format!("{indent}{GAP}{description}"));
+            }
+            None => lines.push(name.clone()),
+        }
+    }
+    lines
+}
+
+/// Print a header line that introduces one of several pages, using the
+/// `page_header` style.
+fn print_header(header: &str, config: &Config) -> Result<()> {
+    writeln!(io::stdout(), "{}", config.style.page_header.paint(header))
+        .context("Could not write to stdout")
+}
+
 /// Print page by path
+///
+/// If `command` is set, it is the command as it was entered by the user
+/// (e.g. `git log`), which may be rendered as the title instead of the page's
+/// own heading.
 pub fn print_page(
     lookup_result: &PageLookupResult,
-    enable_markdown: bool,
+    command: Option<&str>,
+    grep: Option<&str>,
+    options: &RenderOptions,
     enable_styles: bool,
     use_pager: bool,
     config: &Config,
 ) -> Result<()> {
+    debug!(
+        "Rendering page {:?} (source: {:?} {:?}, patch: {:?}, platforms: {:?}, languages: {:?}, {} candidate(s))",
+        lookup_result.primary_path(),
+        lookup_result.source(),
+        lookup_result.source_name(),
+        lookup_result.patch_path(),
+        lookup_result.platforms(),
+        lookup_result.languages(),
+        lookup_result.candidates().len(),
+    );
+
     // Create reader from file(s)
     let reader = lookup_result.reader()?;
 
+    if let Some(number) = options.example_command {
+        return print_example_command(lookup_result, reader, number);
+    }
+    if options.debug_tokens {
+        return print_page_snippets(lookup_result, reader);
+    }
+
     // Configure pager if applicable
     if use_pager || config.display.use_pager {
-        configure_pager(enable_styles);
+        configure_pager(enable_styles, config);
     }
 
     // Lock stdout only once, this improves performance considerably
     let stdout = io::stdout();
     let mut handle = stdout.lock();
 
-    if enable_markdown {
+    if let Some(filter) = &config.display.post_render_command {
+        // Render the page into a buffer, so that it can be written to the
+        // filter (or as it is, if the filter fails)
+        let mut rendered = Vec::new();
+        write_page(
+            lookup_result,
+            reader,
+            &mut rendered,
+            command,
+            grep,
+            options,
+            config,
+        )?;
+        let output = filter_output(filter, &rendered).unwrap_or_else(|e| {
+            print_warning(
+                enable_styles,
+                &format!("{e:#}. Showing the page without the post-render command."),
+            );
+            rendered
+        });
+        handle
+            .write_all(&output)
+            .context("Could not write to stdout")?;
+    } else {
+        write_page(
+            lookup_result,
+            reader,
+            &mut handle,
+            command,
+            grep,
+            options,
+            config,
+        )?;
+    }
+
+    // We're done outputting data, flush stdout now!
+    handle.flush().context("Could not flush stdout")?;
+
+    Ok(())
+}
+
+/// Write the page read from `reader` to `handle`, as raw markdown if
+/// `options.raw` is true and rendered otherwise. Notes about the rendered
+/// page are printed to stderr.
+fn write_page<W: Write>(
+    lookup_result: &PageLookupResult,
+    reader: impl BufRead,
+    mut handle: W,
+    command: Option<&str>,
+    grep: Option<&str>,
+    options: &RenderOptions,
+    config: &Config,
+) -> Result<()> {
+    if options.raw {
         // Print the raw markdown of the file.
-        for line in reader.lines() {
+        for (i, line) in reader.lines().enumerate() {
             let line = line.context("Error while reading from a page")?;
-            writeln!(handle, "{}", line).context("Could not write to stdout")?;
-        }
-    } else {
-        // Closure that processes a page snippet and writes it to stdout
-        let mut process_snippet = |snip: PageSnippet<'_>| {
-            if snip.is_empty() {
-                Ok(())
+            if options.highlight_markdown {
+                let highlighted = highlight_markdown_line(&line, i == 0, config);
+                writeln!(handle, "{highlighted}")
             } else {
-                print_snippet(&mut handle, snip, &config.style).context("Failed to print snippet")
+                writeln!(handle, "{line}")
             }
+            .context("Could not write to stdout")?;
+        }
+    } else {
+        let summary = if config.display.cache_rendered {
+            render_page_cached(
+                lookup_result,
+                reader,
+                &mut handle,
+                command,
+                grep,
+                options,
+                config,
+            )?
+        } else {
+            render_page(
+                lookup_result,
+                reader,
+                &mut handle,
+                command,
+                grep,
+                options,
+                config,
+            )?
         };
+        if summary.no_matches {
+            print_info(&format!(
+                "Note: No examples match {:?}.",
+                grep.unwrap_or_default()
+            ));
+        } else if !summary.has_examples && config.display.empty_page_note {
+            print_info("Note: Page exists but has no examples.");
+        }
+        if summary.unknown_lines > 0 && config.display.warn_unknown_syntax {
+            print_info(&format!(
+                "Note: {} line{} of this page could not be understood and {} not shown. \
+                 Upgrading tealdeer may help.",
+                summary.unknown_lines,
+                if summary.unknown_lines == 1 { "" } else { "s" },
+                if summary.unknown_lines == 1 {
+                    "is"
+                } else {
+                    "are"
+                },
+            ));
+        }
+    }
+    Ok(())
+}
 
-        // Print highlighted lines
-        highlight_lines(
+/// Render the page read from `reader` (see `render_lines`), including the
+/// merged and English pages of the lookup result.
+fn render_page<W: Write>(
+    lookup_result: &PageLookupResult,
+    reader: impl BufRead,
+    writer: W,
+    command: Option<&str>,
+    grep: Option<&str>,
+    options: &RenderOptions,
+    config: &Config,
+) -> Result<RenderSummary> {
+    let title_link = if config.display.title_link {
+        lookup_result.source_url()
+    } else {
+        None
+    };
+    if lookup_result.merge_path().is_some() || lookup_result.english_path().is_some() {
+        render_lines(
+            group_page(lookup_result, reader)?.into_lines(),
+            writer,
+            command,
+            title_link.as_deref(),
+            grep,
+            lookup_result.target_platform(),
+            options,
+            config,
+        )
+    } else {
+        render_lines(
             LineIterator::new(reader),
-            &mut process_snippet,
-            !config.display.compact,
+            writer,
+            command,
+            title_link.as_deref(),
+            grep,
+            lookup_result.target_platform(),
+            options,
+            config,
         )
+    }
+    .context("Could not write to stdout")
+}
+
+/// Render the page like `render_page`, but take the output from the render
+/// cache if the page has been rendered with the same config and parameters
+/// before (see `display.cache_rendered`). Problems with the render cache are
+/// only logged, the page is rendered as usual then.
+fn render_page_cached<W: Write>(
+    lookup_result: &PageLookupResult,
+    reader: impl BufRead,
+    mut writer: W,
+    command: Option<&str>,
+    grep: Option<&str>,
+    options: &RenderOptions,
+    config: &Config,
+) -> Result<RenderSummary> {
+    // Everything that the rendered output depends on, besides the pages
+    let parameters = format!(
+        "{config:?} {command:?} {grep:?} {options:?} {:?} {:?}",
+        lookup_result.target_platform(),
+        terminal_width(),
+    );
+    let paths: Vec<&Path> = [
+        Some(lookup_result.primary_path()),
+        lookup_result.patch_path(),
+        lookup_result.merge_path(),
+        lookup_result.english_path(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    let entry_path = match (lookup_result.source(), Cache::get_cache_dir()) {
+        (PageSource::Embedded(_), _) => None,
+        (_, Ok((cache_dir, _))) => render_cache::entry_path(
+            &cache_dir.join(render_cache::RENDER_CACHE_DIR),
+            &paths,
+            &parameters,
+        ),
+        (_, Err(e)) => {
+            debug!("Not using the render cache: {e:#}");
+            None
+        }
+    };
+
+    if let Some(entry) = entry_path.as_deref().and_then(render_cache::load) {
+        if let Some((summary, output)) = RenderSummary::decode(&entry) {
+            debug!("Using the rendered page from the render cache");
+            writer
+                .write_all(output)
+                .context("Could not write to stdout")?;
+            return Ok(summary);
+        }
+    }
+
+    let mut rendered = Vec::new();
+    let summary = render_page(
+        lookup_result,
+        reader,
+        &mut rendered,
+        command,
+        grep,
+        options,
+        config,
+    )?;
+    if let Some(ref entry_path) = entry_path {
+        if Cache::is_immutable() {
+            debug!("Not storing the rendered page, the cache is immutable");
+        } else if let Err(e) = render_cache::store(entry_path, &summary.encode(&rendered)) {
+            debug!("Could not store the rendered page: {e:#}");
+        }
+    }
+    writer
+        .write_all(&rendered)
         .context("Could not write to stdout")?;
+    Ok(summary)
+}
+
+/// Pipe the rendered page `input` through the command line `filter` (see
+/// `display.post_render_command`) and return its output.
+fn filter_output(filter: &str, input: &[u8]) -> Result<Vec<u8>> {
+    let words = split_command(filter)?;
+    let (program, args) = match words.split_first() {
+        Some(words) => words,
+        None => bail!("The post-render command is empty"),
     };
+    debug!("Filtering the page through {:?}", words);
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Could not run the post-render command {filter:?}"))?;
 
-    // We're done outputting data, flush stdout now!
-    handle.flush().context("Could not flush stdout")?;
+    // Write the input from another thread, so that the command cannot block
+    // on a full output pipe while we are still writing
+    let mut stdin = child
+        .stdin
+        .take()
+        .context("Could not open stdin of the post-render command")?;
+    let input = input.to_vec();
+    let writer = thread::spawn(move || stdin.write_all(&input));
+    let output = child
+        .wait_with_output()
+        .with_context(|| format!("Could not run the post-render command {filter:?}"))?;
+    // A command that exits without reading all of its input is fine
+    let _ = writer.join();
+    ensure!(
+        output.status.success(),
+        "The post-render command {filter:?} failed ({})",
+        output.status
+    );
+    Ok(output.stdout)
+}
+
+/// Highlight a line of the raw markdown of a page, using the same styles as
+/// for the rendered page. In the new page format, the title is the first line
+/// (underlined with `=`).
+fn highlight_markdown_line(line: &str, is_first_line: bool, config: &Config) -> String {
+    let style = &config.style;
+    let is_underline = !line.is_empty() && line.chars().all(|c| c == '=');
+    if is_first_line || line.starts_with('#') || is_underline {
+        style.command_name.paint(line).to_string()
+    } else if line.trim_start_matches('>').trim_start().starts_with('>') {
+        style.callout.paint(line).to_string()
+    } else if line.starts_with('>') {
+        style.description.paint(line).to_string()
+    } else if line.starts_with('`') || line.starts_with("    ") {
+        // Highlight the placeholders, including their curly braces
+        let mut highlighted = String::new();
+        let mut rest = line;
+        while let Some((code, after)) = rest.split_once("{{") {
+            let (variable, after) = after.split_once("}}").unwrap_or((after, ""));
+            highlighted.push_str(&style.example_code.paint(code).to_string());
+            highlighted.push_str(
+                &style
+                    .example_variable
+                    .paint(format!("{{{{{variable}}}}}"))
+                    .to_string(),
+            );
+            rest = after;
+        }
+        highlighted.push_str(&style.example_code.paint(rest).to_string());
+        highlighted
+    } else if !line.is_empty() {
+        style.example_text.paint(line).to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Group the lines of the page read from `reader`, including the examples of
+/// the merged (common) page and the English page (see
+/// `PageLookupResult::merge_path` and `PageLookupResult::english_path`).
+fn group_page(lookup_result: &PageLookupResult, reader: impl BufRead) -> Result<GroupedLines> {
+    let open = |path: &Path| {
+        let file = File::open(path)
+            .with_context(|| format!("Could not open page file at {}", path.display()))?;
+        Ok::<_, anyhow::Error>(GroupedLines::new(LineIterator::new(BufReader::new(file))))
+    };
+    let mut grouped = if let Some(merge_path) = lookup_result.merge_path() {
+        // Append the examples of the page to the examples of the merged
+        // (common) page
+        let mut grouped = open(merge_path)?;
+        grouped.merge(GroupedLines::new(LineIterator::new(reader)));
+        grouped
+    } else {
+        GroupedLines::new(LineIterator::new(reader))
+    };
+    if let Some(english_path) = lookup_result.english_path() {
+        grouped.fill_from(open(english_path)?);
+    }
+    Ok(grouped)
+}
+
+/// Print the plain command of the example with the (1-based) `number`,
+/// without any styling and with the placeholders as plain text. This is meant
+/// for scripting (see `--raw-command`).
+fn print_example_command(
+    lookup_result: &PageLookupResult,
+    reader: impl BufRead,
+    number: usize,
+) -> Result<()> {
+    let mut grouped = group_page(lookup_result, reader)?;
+    if let Some(platform) = lookup_result.target_platform() {
+        grouped
+            .examples
+            .retain(|example| example.is_for_platform(platform));
+    }
+
+    let page = Page::from(grouped);
+    let example_count = page.examples.len();
+    ensure!(number >= 1, "Example numbers start at 1");
+    let example = page.examples.into_iter().nth(number - 1).with_context(|| {
+        format!(
+            "Cannot show example {number}, the page only has {example_count} example{}",
+            if example_count == 1 { "" } else { "s" }
+        )
+    })?;
+
+    let mut command = String::new();
+    for code in &example.commands {
+        command.push_str(&strip_placeholders(code));
+        command.push('\n');
+    }
+
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    handle
+        .write_all(command.as_bytes())
+        .and_then(|()| handle.flush())
+        .context("Could not write to stdout")
+}
+
+/// Print the snippets that the lines of the page are split into for
+/// highlighting, one per line with their type (see `--debug-tokens`).
+fn print_page_snippets(lookup_result: &PageLookupResult, reader: impl BufRead) -> Result<()> {
+    let lines: Box<dyn Iterator<Item = LineType>> =
+        if lookup_result.merge_path().is_some() || lookup_result.english_path().is_some() {
+            Box::new(group_page(lookup_result, reader)?.into_lines())
+        } else {
+            Box::new(LineIterator::new(reader))
+        };
+
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    highlight_lines(
+        lines,
+        &mut |snippet: PageSnippet<'_>| {
+            // Empty snippets are not rendered either
+            if snippet.is_empty() {
+                return Ok(());
+            }
+            writeln!(handle, "{snippet:?}")
+        },
+        true,
+    )
+    .and_then(|()| handle.flush())
+    .context("Could not write to stdout")
+}
+
+/// Write the raw markdown of a page to `writer`, as a section of a document
+/// that contains multiple pages (see `--export`).
+///
+/// The page is read line by line, and its headings are demoted by one level
+/// so that the pages are sections below the document title.
+pub fn export_page<W: Write>(writer: &mut W, lookup_result: &PageLookupResult) -> Result<()> {
+    let reader = lookup_result.reader()?;
+    for line in reader.lines() {
+        let line = line.context("Error while reading from a page")?;
+        if line.starts_with('#') {
+            writeln!(writer, "#{line}")
+        } else {
+            writeln!(writer, "{line}")
+        }
+        .context("Could not write the exported page")?;
+    }
+    writeln!(writer).context("Could not write the exported page")
+}
+
+/// Information about a rendered page.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct RenderSummary {
+    /// Whether the rendered page contained examples
+    has_examples: bool,
+    /// Whether the page contained examples, but none of them matched `grep`
+    no_matches: bool,
+    /// The number of lines that could not be parsed, and were not rendered
+    unknown_lines: usize,
+}
+
+impl RenderSummary {
+    /// Prepend the summary to the rendered page `output` as a header line,
+    /// for the render cache.
+    fn encode(self, output: &[u8]) -> Vec<u8> {
+        let mut entry = format!(
+            "{} {} {}\n",
+            u8::from(self.has_examples),
+            u8::from(self.no_matches),
+            self.unknown_lines
+        )
+        .into_bytes();
+        entry.extend_from_slice(output);
+        entry
+    }
+
+    /// Split an entry of the render cache into the summary and the rendered
+    /// page (see `encode`).
+    fn decode(entry: &[u8]) -> Option<(Self, &[u8])> {
+        let header_end = entry.iter().position(|&byte| byte == b'\n')?;
+        let header = std::str::from_utf8(&entry[..header_end]).ok()?;
+        let mut fields = header.split(' ');
+        let mut flag = || match fields.next()? {
+            "0" => Some(false),
+            "1" => Some(true),
+            _ => None,
+        };
+        let (has_examples, no_matches) = (flag()?, flag()?);
+        let unknown_lines = fields.next()?.parse().ok()?;
+        Some((
+            Self {
+                has_examples,
+                no_matches,
+                unknown_lines,
+            },
+            &entry[header_end + 1..],
+        ))
+    }
+}
+
+/// Render the lines of a page to `writer`.
+///
+/// See `print_page` for the meaning of `command` and `grep`. If `title_link`
+/// is set, the title is rendered as a link to it. If `platform` is set,
+/// examples that are tagged for other platforms are skipped.
+#[allow(clippy::too_many_arguments)]
+fn render_lines<W: Write>(
+    lines: impl Iterator<Item = LineType>,
+    writer: W,
+    command: Option<&str>,
+    title_link: Option<&str>,
+    grep: Option<&str>,
+    platform: Option<PlatformType>,
+    options: &RenderOptions,
+    config: &Config,
+) -> Result<RenderSummary> {
+    let mut line_writer = LineWriter::new(
+        writer,
+        config.display.overflow,
+        config.display.min_wrap_width,
+    );
+    if config.display.full_width_background && config.style.background.background.is_some() {
+        line_writer.fill_background(config.style.background);
+    }
+    if let Some(count) = config.display.surround_blank_lines {
+        line_writer.surround_blank_lines(count);
+    }
+    if let Some(max_lines) = options.max_lines {
+        line_writer.limit_lines(max_lines);
+    }
+    let mut summary = RenderSummary::default();
+    let mut example_number = 0;
+    let mut has_description = false;
+
+    // Only filter by platform if the page contains platform tags
+    let lines: Vec<LineType> = lines.collect();
+    summary.unknown_lines = lines
+        .iter()
+        .filter(|line| matches!(line, LineType::Other(_)))
+        .count();
+    if options.line_numbers {
+        // Every line of the page results in at most one rendered line (not
+        // counting the continuations of wrapped lines, which are not numbered)
+        // The section rule adds up to two lines
+        let rule_lines = if config.display.section_rule.is_empty() {
+            0
+        } else {
+            2
+        };
+        line_writer.number_lines(config.style.line_number, lines.len().max(1) + rule_lines);
+    }
+    if options.debug_width {
+        let describe = |width: Option<usize>, unknown: &str| {
+            width.map_or_else(|| unknown.to_string(), |width| format!("{width} columns"))
+        };
+        // The effective width is unlimited unless lines are wrapped or
+        // truncated (see `display.overflow`)
+        eprintln!(
+            "Render width: terminal {}, effective {}",
+            describe(terminal_width(), "unknown"),
+            describe(line_writer.width, "unlimited"),
+        );
+    }
+    let platform = platform.filter(|_| {
+        lines.iter().any(|line| match line {
+            LineType::ExampleText(text) => !split_platform_tag(text).1.is_empty(),
+            _ => false,
+        })
+    });
+
+    let mut no_matches = false;
+    let mut align_width = None;
+    let lines: Box<dyn Iterator<Item = LineType>> = if grep.is_some()
+        || config.display.inline_examples
+        || config.display.align_examples
+        || platform.is_some()
+        || config.display.max_description_lines.is_some()
+        || options.reverse_examples
+        || config.display.show_usage
+        || config.display.dedup_examples
+    {
+        let mut grouped = GroupedLines::new(lines.into_iter());
+
+        if config.display.dedup_examples {
+            grouped.dedup_examples();
+        }
+
+        // Derive the usage from the first command of the page (before any
+        // examples are filtered out)
+        if config.display.show_usage {
+            insert_usage(&mut grouped);
+        }
+
+        if let Some(max_lines) = config.display.max_description_lines {
+            grouped.truncate_description(max_lines, " …");
+        }
+
+        if let Some(platform) = platform {
+            grouped
+                .examples
+                .retain(|example| example.is_for_platform(platform));
+        }
+
+        // Only keep the examples matching the keyword, if any
+        if let Some(keyword) = grep {
+            let example_count = grouped.examples.len();
+            grouped.examples.retain(|example| example.matches(keyword));
+            no_matches = example_count > 0 && grouped.examples.is_empty();
+        }
+
+        if options.reverse_examples {
+            grouped.examples.reverse();
+        }
+
+        // Render the text and the code of an example on the same line
+        if config.display.inline_examples || config.display.align_examples {
+            for example in &mut grouped.examples {
+                example.lines.retain(|line| *line != LineType::Empty);
+            }
+        }
+
+        // Measure the example texts, so that the commands can be aligned
+        if config.display.align_examples {
+            align_width = grouped
+                .examples
+                .iter()
+                .enumerate()
+                .filter_map(|(i, example)| match example.lines.first() {
+                    Some(LineType::ExampleText(text)) => Some(
+                        example_bullet(config, i + 1).width() + split_platform_tag(text).0.width(),
+                    ),
+                    _ => None,
+                })
+                .max()
+                .map(|width| width.min(config.display.max_align_width));
+        }
+
+        Box::new(grouped.into_lines())
+    } else {
+        Box::new(lines.into_iter())
+    };
+
+    let inline = config.display.inline_examples || config.display.align_examples;
+    let mut inline_text_open = false;
+
+    // Closure that processes a page snippet and writes it to the writer
+    let mut process_snippet = |snip: PageSnippet<'_>| {
+        // In inline mode, an example text is only continued by the code of its example
+        if inline_text_open && !matches!(snip, PageSnippet::CodePrefix) {
+            line_writer
+                .end_line()
+                .context("Failed to end the example line")?;
+        }
+        inline_text_open = inline && matches!(snip, PageSnippet::Text(s) if !s.is_empty());
+        if let PageSnippet::CommandName(_) | PageSnippet::Variable(_) | PageSnippet::NormalCode(_) =
+            snip
+        {
+            summary.has_examples = true;
+        }
+        if let PageSnippet::Description(_) = snip {
+            has_description = true;
+        }
+        if let PageSnippet::Text(_) = snip {
+            example_number += 1;
+
+            // Separate the description from the first example
+            if example_number == 1 && has_description && !config.display.section_rule.is_empty() {
+                print_section_rule(&mut line_writer, config)
+                    .context("Failed to print the section rule")?;
+            }
+        }
+        let snip = match snip {
+            PageSnippet::Title(_) if !config.display.show_title => return Ok(()),
+            PageSnippet::Title(title) if config.display.title_original => {
+                PageSnippet::Title(command.unwrap_or(title))
+            }
+            snip => snip,
+        };
+        if snip.is_empty() {
+            Ok(())
+        } else {
+            if let (PageSnippet::Title(_), Some(url)) = (&snip, title_link) {
+                line_writer.set_link(url);
+            }
+            print_snippet(&mut line_writer, snip, example_number, align_width, config)
+                .context("Failed to print snippet")
+        }
+    };
+
+    // Print highlighted lines
+    highlight_lines(lines, &mut process_snippet, !config.display.compact)?;
+    line_writer.finish().context("Failed to finish the page")?;
+
+    summary.no_matches = no_matches;
+    Ok(summary)
+}
 
+/// Print the section rule (see `display.section_rule`) across the available
+/// width, followed by an empty line unless the output is compact.
+fn print_section_rule<W: Write>(writer: &mut LineWriter<W>, config: &Config) -> io::Result<()> {
+    let width = writer
+        .available_width()
+        .unwrap_or(DEFAULT_RULE_WIDTH)
+        .saturating_sub(2);
+    writer.push(Style::default(), "  ");
+    writer.push(
+        config.style.description,
+        &section_rule(&config.display.section_rule, width),
+    );
+    writer.end_line()?;
+    if !config.display.compact {
+        writer.end_line()?;
+    }
     Ok(())
 }
 
-fn print_snippet(
-    writer: &mut impl Write,
+/// Repeat `rule` as often as it fits into `width` columns.
+fn section_rule(rule: &str, width: usize) -> String {
+    rule.repeat(width / rule.width().max(1))
+}
+
+/// Insert a usage line derived from the first example command of the page
+/// before the description (see `formatter::usage_line`). Nothing is inserted
+/// if the page has no example commands.
+fn insert_usage(grouped: &mut GroupedLines) {
+    let first_command = grouped
+        .examples
+        .iter()
+        .find_map(|example| example.commands().first().map(|code| usage_line(code)));
+    if let Some(usage) = first_command {
+        let header = &mut grouped.header;
+        let usage = LineType::Usage(usage);
+        match header
+            .iter()
+            .position(|line| matches!(line, LineType::Description(_)))
+        {
+            Some(position) => {
+                header.splice(position..position, [usage, LineType::Empty]);
+            }
+            None if header.is_empty() => header.push(usage),
+            None => header.extend([LineType::Empty, usage]),
+        }
+    }
+}
+
+/// Return the example bullet (followed by a space) for the example with the
+/// given (1-based) number, or an empty string if no bullet is configured.
+fn example_bullet(config: &Config, example_number: usize) -> String {
+    if config.display.example_bullet.is_empty() {
+        String::new()
+    } else {
+        let bullet = config
+            .display
+            .example_bullet
+            .replace("{n}", &example_number.to_string());
+        format!("{bullet} ")
+    }
+}
+
+/// Print a snippet. `example_number` is the (1-based) number of the current
+/// example, which is used for the example bullet. If `align_width` is set,
+/// example texts are padded to that many columns, so that the commands that
+/// follow them line up.
+fn print_snippet<W: Write>(
+    writer: &mut LineWriter<W>,
     snip: PageSnippet<'_>,
-    style: &StyleConfig,
+    example_number: usize,
+    align_width: Option<usize>,
+    config: &Config,
 ) -> io::Result<()> {
     use PageSnippet::*;
 
+    let style = &config.style;
     match snip {
-        CommandName(s) => write!(writer, "{}", style.command_name.paint(s)),
-        Variable(s) => write!(writer, "{}", style.example_variable.paint(s)),
-        NormalCode(s) => write!(writer, "{}", style.example_code.paint(s)),
-        Description(s) => writeln!(writer, "  {}", style.description.paint(s)),
-        Text(s) => writeln!(writer, "  {}", style.example_text.paint(s)),
-        Linebreak => writeln!(writer),
+        Title(s) => {
+            writer.push(Style::default(), "  ");
+            writer.push(style.command_name, s);
+            return writer.end_line();
+        }
+        CommandName(s) => writer.push(style.command_name, s),
+        Variable(s) => {
+            let variable_style = if config.display.style_placeholders {
+                style.example_variable
+            } else {
+                style.example_code
+            };
+            writer.push(variable_style, &config.display.placeholder_case.apply(s));
+        }
+        NormalCode(s) => writer.push(style.example_code, s),
+        Description(s) => {
+            writer.push(Style::default(), "  ");
+            writer.push(style.description, s);
+            return writer.end_line();
+        }
+        Callout(s) => {
+            writer.push(Style::default(), "  ");
+            writer.push(style.callout, &config.display.callout_marker);
+            writer.push(style.callout, s);
+            return writer.end_line();
+        }
+        Aliases(s) => {
+            writer.push(Style::default(), "  ");
+            writer.push(style.aliases, "Aliases: ");
+            writer.push(style.aliases, s);
+            return writer.end_line();
+        }
+        Usage(s) => {
+            writer.push(Style::default(), "  ");
+            writer.push(style.description, "Usage: ");
+            writer.push(style.example_code, s);
+            return writer.end_line();
+        }
+        CodePrefix => {
+            // In inline mode, the code follows the example text on the same line
+            let inline = config.display.inline_examples || config.display.align_examples;
+            if inline && writer.has_content() {
+                writer.push(style.example_text, &config.display.inline_delimiter);
+            } else {
+                writer.push(style.example_code, &config.display.command_prefix);
+            }
+        }
+        Text(s) => {
+            writer.push(Style::default(), "  ");
+            let bullet = example_bullet(config, example_number);
+            writer.push(style.example_text, &bullet);
+            writer.push(style.example_text, s);
+            if let Some(width) = align_width {
+                let padding = width.saturating_sub(bullet.width() + s.width());
+                writer.push(Style::default(), &" ".repeat(padding));
+            }
+            if config.display.inline_examples || config.display.align_examples {
+                // The line is ended by the caller if no code follows
+                return Ok(());
+            }
+            return writer.end_line();
+        }
+        Linebreak => return writer.end_line(),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, fs, path::Path};
+
+    /// Render the fixture page `tests/<fixture>` with `config` and compare
+    /// the output (including ANSI escape codes) to the snapshot
+    /// `tests/snapshots/<snapshot>.snap`.
+    ///
+    /// If the `TEALDEER_UPDATE_SNAPSHOTS` env variable is set, the snapshot
+    /// is written instead.
+    fn assert_snapshot(fixture: &str, snapshot: &str, config: &Config) {
+        let tests_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests");
+        let page = fs::read(tests_dir.join(fixture)).unwrap();
+        let mut rendered = Vec::new();
+        render_lines(
+            LineIterator::new(&page[..]),
+            &mut rendered,
+            None,
+            None,
+            None,
+            None,
+            &RenderOptions::default(),
+            config,
+        )
+        .unwrap();
+        let rendered = String::from_utf8(rendered).unwrap();
+
+        let snapshot_path = tests_dir.join("snapshots").join(format!("{snapshot}.snap"));
+        if env::var_os("TEALDEER_UPDATE_SNAPSHOTS").is_some() {
+            fs::create_dir_all(snapshot_path.parent().unwrap()).unwrap();
+            fs::write(&snapshot_path, rendered).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(&snapshot_path).unwrap_or_else(|_| {
+            panic!(
+                "Snapshot {} does not exist, run the tests with TEALDEER_UPDATE_SNAPSHOTS=1 to create it",
+                snapshot_path.display()
+            )
+        });
+        assert!(
+            rendered == expected,
+            "Rendered output does not match snapshot {}, run the tests with \
+             TEALDEER_UPDATE_SNAPSHOTS=1 to update it\n\nRendered:\n{rendered:?}\n\nExpected:\n{expected:?}",
+            snapshot_path.display()
+        );
+    }
+
+    #[test]
+    fn test_snapshot_default() {
+        assert_snapshot("inkscape-v2.md", "inkscape-default", &Config::default());
+    }
+
+    #[test]
+    fn test_snapshot_compact_with_title() {
+        let mut config = Config::default();
+        config.display.compact = true;
+        config.display.show_title = true;
+        assert_snapshot("inkscape-v1.md", "inkscape-compact-title", &config);
+    }
+
+    #[test]
+    fn test_snapshot_inline_examples() {
+        let mut config = Config::default();
+        config.display.inline_examples = true;
+        config.style.description = Style::new().bold();
+        assert_snapshot("inkscape-v2.md", "inkscape-inline", &config);
+    }
+
+    #[test]
+    fn test_inline_example_without_code() {
+        let render = |compact| {
+            let mut config = Config::default();
+            config.display.inline_examples = true;
+            config.display.compact = compact;
+            config.style.command_name = Style::new();
+            config.style.example_text = Style::new();
+            config.style.example_code = Style::new();
+            let mut rendered = Vec::new();
+            let page = "# foo\n\n> Foo.\n\n- No code here\n\n- Run foo:\n\n`foo`\n\n- Last one\n";
+            let lines = LineIterator::new(page.as_bytes());
+            render_lines(
+                lines,
+                &mut rendered,
+                None,
+                None,
+                None,
+                None,
+                &RenderOptions::default(),
+                &config,
+            )
+            .unwrap();
+            String::from_utf8(rendered).unwrap()
+        };
+
+        assert_eq!(
+            render(false),
+            "\n  Foo.\n\n  No code here\n\n  Run foo: foo\n\n  Last one\n\n"
+        );
+        assert_eq!(
+            render(true),
+            "  Foo.\n  No code here\n  Run foo: foo\n  Last one\n\n"
+        );
+    }
+
+    #[test]
+    fn test_surround_blank_lines() {
+        let render = |page: &str, count| {
+            let mut config = Config::default();
+            config.display.surround_blank_lines = Some(count);
+            config.style.command_name = Style::new();
+            config.style.example_text = Style::new();
+            config.style.example_code = Style::new();
+            let mut rendered = Vec::new();
+            let lines = LineIterator::new(page.as_bytes());
+            render_lines(
+                lines,
+                &mut rendered,
+                None,
+                None,
+                None,
+                None,
+                &RenderOptions::default(),
+                &config,
+            )
+            .unwrap();
+            String::from_utf8(rendered).unwrap()
+        };
+
+        let expected = "\n  Foo.\n\n  Run foo:\n\n      foo\n\n";
+        // Extra blank lines at the start and end
+        let padded = "# foo\n\n\n\n> Foo.\n\n- Run foo:\n\n`foo`\n\n\n\n";
+        assert_eq!(render(padded, 1), expected);
+        // No blank lines at all
+        let tight = "# foo\n> Foo.\n\n- Run foo:\n\n`foo`";
+        assert_eq!(render(tight, 1), expected);
+        assert_eq!(render(padded, 0), "  Foo.\n\n  Run foo:\n\n      foo\n");
+        // Blank lines between the lines are kept
+        assert_eq!(
+            render(tight, 2),
+            "\n\n  Foo.\n\n  Run foo:\n\n      foo\n\n\n"
+        );
+        assert_eq!(render("", 1), "");
+    }
+
+    #[test]
+    fn test_platform_tags() {
+        let page = "# foo\n\n> Foo.\n\n- Do a: <!-- platform: linux -->\n\n`foo a`\n\n\
+                    - Do b: <!-- platform: windows -->\n\n`foo b`\n\n- Do c:\n\n`foo c`\n";
+        let render = |platform| {
+            let mut rendered = Vec::new();
+            let lines = LineIterator::new(page.as_bytes());
+            render_lines(
+                lines,
+                &mut rendered,
+                None,
+                None,
+                None,
+                platform,
+                &RenderOptions::default(),
+                &Config::default(),
+            )
+            .unwrap();
+            String::from_utf8(rendered).unwrap()
+        };
+
+        let linux = render(Some(PlatformType::Linux));
+        assert!(linux.contains("Do a:"));
+        assert!(!linux.contains("Do b:"));
+        assert!(linux.contains("Do c:"));
+        assert!(!linux.contains("<!--"));
+
+        // Without a platform, all examples are shown
+        let all = render(None);
+        assert!(all.contains("Do a:") && all.contains("Do b:") && all.contains("Do c:"));
+        assert!(!all.contains("<!--"));
+    }
+
+    #[test]
+    fn test_highlight_markdown_line() {
+        let mut config = Config::default();
+        config.style.command_name = Style::new().bold();
+        config.style.description = Style::new().italic();
+        config.style.example_text = Style::new().fg(ansi_term::Color::Green);
+        config.style.example_code = Style::new().fg(ansi_term::Color::Red);
+        config.style.example_variable = Style::new().underline();
+        let highlight = |line, is_first_line| highlight_markdown_line(line, is_first_line, &config);
+
+        assert_eq!(
+            highlight("# tar", true),
+            Style::new().bold().paint("# tar").to_string()
+        );
+        assert_eq!(
+            highlight("tar", true),
+            Style::new().bold().paint("tar").to_string()
+        );
+        assert_eq!(
+            highlight("===", false),
+            Style::new().bold().paint("===").to_string()
+        );
+        assert_eq!(
+            highlight("> Archiver.", false),
+            Style::new().italic().paint("> Archiver.").to_string()
+        );
+        assert_eq!(
+            highlight("- Create:", false),
+            config.style.example_text.paint("- Create:").to_string()
+        );
+        assert_eq!(
+            highlight("`tar cf {{file}}`", false),
+            format!(
+                "{}{}{}",
+                config.style.example_code.paint("`tar cf "),
+                Style::new().underline().paint("{{file}}"),
+                config.style.example_code.paint("`"),
+            )
+        );
+        assert_eq!(highlight("", false), "");
+    }
+
+    #[test]
+    #[cfg(not(target_os = "windows"))]
+    fn test_get_pager() {
+        assert_eq!(get_pager(None, None).unwrap(), ["less", "-R"]);
+        assert_eq!(get_pager(None, Some(" ")).unwrap(), ["less", "-R"]);
+        assert_eq!(get_pager(None, Some("most")).unwrap(), ["most"]);
+        assert_eq!(
+            get_pager(Some("less -R '-Ps'"), Some("most")).unwrap(),
+            ["less", "-R", "-Ps"]
+        );
+        let error = get_pager(Some("less \"-Ps x\""), None).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("Arguments containing whitespace are not supported"));
+        assert!(get_pager(Some("'/opt/My Pager/bin/pager'"), None).is_err());
+        assert!(get_pager(Some("less 'unterminated"), None).is_err());
+    }
+
+    #[test]
+    fn test_style_placeholders_disabled() {
+        let page = "# foo\n\n> Foo.\n\n- Copy a file:\n\n`foo {{file}}`\n";
+        let mut config = Config::default();
+        config.style.example_code = Style::new().fg(ansi_term::Color::Red);
+        config.style.example_variable = Style::new().underline();
+        let render = |config: &Config| {
+            let mut rendered = Vec::new();
+            let lines = LineIterator::new(page.as_bytes());
+            render_lines(
+                lines,
+                &mut rendered,
+                None,
+                None,
+                None,
+                None,
+                &RenderOptions::default(),
+                config,
+            )
+            .unwrap();
+            String::from_utf8(rendered).unwrap()
+        };
+
+        let styled = render(&config);
+        assert!(styled.contains(&Style::new().underline().paint("file").to_string()));
+
+        config.display.style_placeholders = false;
+        let unstyled = render(&config);
+        assert!(!unstyled.contains("\x1b[4m"));
+        assert!(unstyled.contains(
+            &Style::new()
+                .fg(ansi_term::Color::Red)
+                .paint("file")
+                .to_string()
+        ));
+    }
+
+    fn plain(text: &str) -> Segment {
+        (Style::default(), text.to_string())
+    }
+
+    fn to_strings(lines: Vec<Vec<Segment>>) -> Vec<String> {
+        lines
+            .into_iter()
+            .map(|line| line.into_iter().map(|(_, text)| text).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_truncate_segments() {
+        let bold = Style::new().bold();
+        let segments = vec![plain("  "), (bold, "tar".into()), plain(" -xf archive")];
+
+        assert_eq!(truncate_segments(segments.clone(), 80), segments);
+        assert_eq!(
+            truncate_segments(segments, 8),
+            [plain("  "), (bold, "tar".into()), plain(" -…")]
+        );
+    }
+
+    #[test]
+    fn test_truncate_segments_wide_chars() {
+        let segments = vec![plain("日本語のテキスト")];
+        assert_eq!(truncate_segments(segments, 6), [plain("日本…")]);
+    }
+
+    #[test]
+    fn test_fill_background() {
+        let black = Style::new().on(ansi_term::Color::Black);
+        let bold = Style::new().bold();
+        let red = Style::new().on(ansi_term::Color::Red);
+        let segments = vec![plain("  "), (bold, "tar".into()), (red, " -x".into())];
+        assert_eq!(
+            fill_background(segments, black, 12),
+            [
+                (black, "  ".into()),
+                (bold.on(ansi_term::Color::Black), "tar".into()),
+                (red, " -x".into()),
+                (black, "    ".into()),
+            ]
+        );
+        assert_eq!(fill_background(vec![], black, 3), [(black, "   ".into())]);
+        assert_eq!(
+            fill_background(vec![plain("abc")], black, 2),
+            [(black, "abc".into())]
+        );
+    }
+
+    #[test]
+    fn test_wrap_segments() {
+        let bold = Style::new().bold();
+        let segments = vec![plain("  "), (bold, "tar".into()), plain(" -x -f archive")];
+
+        let lines = wrap_segments(&segments, 12);
+        assert_eq!(to_strings(lines.clone()), ["  tar -x -f", "  archive"]);
+        assert_eq!(lines[0][1], (bold, "tar".into()));
+    }
+
+    #[test]
+    fn test_wrap_segments_long_word() {
+        let segments = vec![plain("  abcdefghij")];
+        assert_eq!(
+            to_strings(wrap_segments(&segments, 6)),
+            ["  abcd", "  efgh", "  ij"]
+        );
+    }
+
+    #[test]
+    fn test_section_rule() {
+        assert_eq!(section_rule("─", 5), "─────");
+        assert_eq!(section_rule("-=", 5), "-=-=");
+        assert_eq!(section_rule("═", 0), "");
+        // Wide characters take two columns
+        assert_eq!(section_rule("一", 5), "一一");
+    }
+
+    #[test]
+    fn test_format_page_list() {
+        let description = |text: &str| Some(text.to_string());
+        let pages = [
+            ("ls".to_string(), description("List files.")),
+            ("tar".to_string(), description("Archive files.")),
+            ("x".repeat(12), description("Very long name.")),
+            ("zip".to_string(), None),
+        ];
+        assert_eq!(
+            format_page_list(&pages, 10),
+            [
+                "ls   List files.",
+                "tar  Archive files.",
+                "xxxxxxxxxxxx",
+                "     Very long name.",
+                "zip",
+            ]
+        );
+        assert_eq!(
+            format_page_list(&pages, 20),
+            [
+                "ls            List files.",
+                "tar           Archive files.",
+                "xxxxxxxxxxxx  Very long name.",
+                "zip",
+            ]
+        );
     }
 }
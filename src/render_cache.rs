@@ -0,0 +1,123 @@
+//! A cache of rendered pages (see `display.cache_rendered`), which allows
+//! showing a page that was rendered before without parsing it again.
+//!
+//! Every entry is a file in the render cache directory, named after a hash of
+//! everything the rendered output depends on: the page files (including their
+//! modification times), the config and the render parameters. Entries are
+//! never updated, a change of any of these results in a new entry.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{Context, Result};
+
+/// The name of the render cache directory in the cache directory.
+pub const RENDER_CACHE_DIR: &str = "rendered";
+
+/// A 64-bit FNV-1a hash. Unlike `DefaultHasher` (and the `Hash`
+/// implementations of the standard library), it is the same for every build,
+/// so that entries stay valid after tealdeer is built with another toolchain.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// Add `bytes` to the hash, preceded by their length to keep consecutive
+    /// values apart.
+    fn write(&mut self, bytes: &[u8]) {
+        let len = u64::try_from(bytes.len()).unwrap_or(u64::MAX);
+        for &byte in len.to_le_bytes().iter().chain(bytes) {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
+
+/// Return the path of the entry for the page files `paths` rendered with
+/// `parameters` (e.g. a debug representation of the config) in `cache_dir`.
+///
+/// `None` is returned if the metadata of a page file cannot be read (e.g.
+/// because the page is not a file).
+pub fn entry_path(cache_dir: &Path, paths: &[&Path], parameters: &str) -> Option<PathBuf> {
+    let mut hash = Fnv1a::new();
+    hash.write(env!("CARGO_PKG_VERSION").as_bytes());
+    for path in paths {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        hash.write(path.to_string_lossy().as_bytes());
+        hash.write(&metadata.len().to_le_bytes());
+        hash.write(&modified.as_secs().to_le_bytes());
+        hash.write(&modified.subsec_nanos().to_le_bytes());
+    }
+    hash.write(parameters.as_bytes());
+    Some(cache_dir.join(format!("{:016x}", hash.0)))
+}
+
+/// Read the entry at `path`, if it exists.
+pub fn load(path: &Path) -> Option<Vec<u8>> {
+    fs::read(path).ok()
+}
+
+/// Write the entry at `path`, creating the render cache directory if needed.
+pub fn store(path: &Path, contents: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Could not create directory {}", dir.display()))?;
+    }
+    fs::write(path, contents)
+        .with_context(|| format!("Could not write the rendered page to {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fnv1a() {
+        // The hash must not change between builds
+        let mut hash = Fnv1a::new();
+        hash.write(b"tar");
+        assert_eq!(hash.0, 0x22ec_8ad2_89bb_efa5);
+
+        // The length prefix keeps `ab` + `c` apart from `a` + `bc`
+        let mut split = Fnv1a::new();
+        split.write(b"ab");
+        split.write(b"c");
+        let mut other = Fnv1a::new();
+        other.write(b"a");
+        other.write(b"bc");
+        assert_ne!(split.0, other.0);
+    }
+
+    #[test]
+    fn test_entry_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let page = dir.path().join("tar.md");
+        fs::write(&page, "# tar\n").unwrap();
+        let entry = |parameters| entry_path(dir.path(), &[&page], parameters).unwrap();
+
+        assert_eq!(entry("compact"), entry("compact"));
+        assert_ne!(entry("compact"), entry("not compact"));
+        assert_eq!(entry("compact").parent(), Some(dir.path()));
+
+        // Changing the page invalidates the entry
+        let before = entry("compact");
+        fs::write(&page, "# tar\n\n> Archiver.\n").unwrap();
+        assert_ne!(entry("compact"), before);
+
+        assert_eq!(
+            entry_path(dir.path(), &[&dir.path().join("missing.md")], ""),
+            None
+        );
+
+        let path = entry("compact");
+        assert_eq!(load(&path), None);
+        store(&path, b"rendered").unwrap();
+        assert_eq!(load(&path).as_deref(), Some(&b"rendered"[..]));
+    }
+}
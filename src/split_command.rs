@@ -0,0 +1,100 @@
+//! Splitting of user-provided command lines (e.g. `$EDITOR`) into a program
+//! and its arguments.
+
+use anyhow::{bail, Result};
+
+/// Split a command line into words, following (a subset of) the POSIX shell
+/// quoting rules.
+///
+/// Words are separated by whitespace. Single quotes preserve everything up to
+/// the closing quote, double quotes additionally allow escaping `"` and `\`
+/// with a backslash, and outside of quotes a backslash escapes any character.
+/// No other shell features (variables, globs, ...) are supported.
+pub fn split_command(command: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                words.extend(word.take());
+            }
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => bail!("Unterminated single quote in command {command:?}"),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => bail!("Unterminated double quote in command {command:?}"),
+                        },
+                        Some(c) => word.push(c),
+                        None => bail!("Unterminated double quote in command {command:?}"),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_with(String::new).push(c),
+                None => bail!("Trailing backslash in command {command:?}"),
+            },
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::split_command;
+
+    fn split(command: &str) -> Vec<String> {
+        split_command(command).unwrap()
+    }
+
+    #[test]
+    fn test_split_plain() {
+        assert_eq!(split("vim"), ["vim"]);
+        assert_eq!(split("  code   --wait "), ["code", "--wait"]);
+        assert!(split("").is_empty());
+        assert!(split("   ").is_empty());
+    }
+
+    #[test]
+    fn test_split_quoted() {
+        assert_eq!(
+            split("'/opt/My Editor/bin/edit' --wait"),
+            ["/opt/My Editor/bin/edit", "--wait"]
+        );
+        assert_eq!(
+            split(r#"emacsclient -a "" --eval "(message \"hi\")""#),
+            ["emacsclient", "-a", "", "--eval", r#"(message "hi")"#]
+        );
+        assert_eq!(split(r#"'it''s' "a\b""#), ["its", r"a\b"]);
+        assert_eq!(split(r"my\ editor -x"), ["my editor", "-x"]);
+        assert_eq!(split("--opt='a b'c"), ["--opt=a bc"]);
+    }
+
+    #[test]
+    fn test_split_unterminated() {
+        assert!(split_command("vim 'file").is_err());
+        assert!(split_command("vim \"file").is_err());
+        assert!(split_command("vim \\").is_err());
+    }
+}
@@ -2,6 +2,7 @@
 
 use std::{fmt, str};
 
+use ansi_term::Color;
 use anyhow::{anyhow, Result};
 use serde_derive::{Deserialize, Serialize};
 
@@ -86,9 +87,71 @@ impl PlatformType {
     pub fn current() -> Self {
         Self::Other
     }
+
+    /// Return whether tealdeer is running in the Windows Subsystem for Linux.
+    #[cfg(target_os = "linux")]
+    pub fn is_wsl() -> bool {
+        std::fs::read_to_string("/proc/version").map_or(false, |version| is_wsl_kernel(&version))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn is_wsl() -> bool {
+        false
+    }
+}
+
+/// Split a trailing platform tag (e.g. `<!-- platform: linux, osx -->`) off
+/// an example text and return the text and the platforms listed in the tag.
+///
+/// Unknown platforms are ignored, so an empty list of platforms (no tag, or
+/// only unknown platforms) means that the example applies to all platforms.
+pub fn split_platform_tag(text: &str) -> (&str, Vec<PlatformType>) {
+    let tag = text
+        .strip_suffix("-->")
+        .and_then(|rest| rest.rsplit_once("<!--"))
+        .and_then(|(text, comment)| {
+            let (key, platforms) = comment.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case("platform")
+                .then_some((text.trim_end(), platforms))
+        });
+    match tag {
+        Some((text, platforms)) => (
+            text,
+            platforms
+                .split(',')
+                .filter_map(|platform| platform.trim().to_lowercase().parse().ok())
+                .collect(),
+        ),
+        None => (text, vec![]),
+    }
+}
+
+/// Parse a metadata line of the form `aliases: foo, bar` (case insensitive)
+/// into the list of aliases.
+fn parse_aliases(text: &str) -> Option<Vec<String>> {
+    let (key, value) = text.split_once(':')?;
+    if !key.trim().eq_ignore_ascii_case("aliases") {
+        return None;
+    }
+    let aliases: Vec<String> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|alias| !alias.is_empty())
+        .map(String::from)
+        .collect();
+    (!aliases.is_empty()).then_some(aliases)
 }
 
-#[derive(Debug, Eq, PartialEq, Copy, Clone, Deserialize)]
+/// Return whether the kernel version string (as found in `/proc/version`)
+/// belongs to a WSL kernel.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+fn is_wsl_kernel(version: &str) -> bool {
+    // WSL 1 uses "Microsoft", WSL 2 uses "microsoft"
+    version.to_lowercase().contains("microsoft")
+}
+
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ColorOptions {
     Always,
@@ -118,13 +181,237 @@ impl Default for ColorOptions {
     }
 }
 
-#[derive(Debug, Eq, PartialEq)]
+/// The number of colors a terminal supports.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum ColorDepth {
+    /// 24-bit RGB colors
+    TrueColor,
+    /// The 256 color ANSI palette
+    Ansi256,
+    /// The 16 basic ANSI colors
+    Ansi16,
+}
+
+/// The default RGB values of the 16 basic ANSI colors (as used by xterm).
+const ANSI16_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+/// The channel values of the 6x6x6 color cube in the 256 color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Return the squared distance between two RGB colors.
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let channel = |a: u8, b: u8| u32::from(a.abs_diff(b)).pow(2);
+    channel(r1, r2) + channel(g1, g2) + channel(b1, b2)
+}
+
+/// Return the index of the color in the 256 color palette that is closest to
+/// the given RGB color, using the color cube or the grayscale ramp.
+fn nearest_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    let nearest_level = |value: u8| {
+        (0..6)
+            .min_by_key(|&i: &u8| CUBE_LEVELS[usize::from(i)].abs_diff(value))
+            .unwrap_or(0)
+    };
+    let (r, g, b) = (
+        nearest_level(rgb.0),
+        nearest_level(rgb.1),
+        nearest_level(rgb.2),
+    );
+    let level = |i: u8| CUBE_LEVELS[usize::from(i)];
+    let cube_rgb = (level(r), level(g), level(b));
+
+    let average = (u16::from(rgb.0) + u16::from(rgb.1) + u16::from(rgb.2)) / 3;
+    let gray = u8::try_from(average.saturating_sub(3) / 10).map_or(23, |gray| gray.min(23));
+    let gray_level = 8 + 10 * gray;
+
+    if distance((gray_level, gray_level, gray_level), rgb) < distance(cube_rgb, rgb) {
+        232 + gray
+    } else {
+        16 + 36 * r + 6 * g + b
+    }
+}
+
+/// Return the RGB value of a color in the 256 color palette.
+fn ansi256_to_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI16_COLORS[usize::from(index)],
+        16..=231 => {
+            let index = usize::from(index - 16);
+            (
+                CUBE_LEVELS[index / 36],
+                CUBE_LEVELS[index / 6 % 6],
+                CUBE_LEVELS[index % 6],
+            )
+        }
+        _ => {
+            let level = 8 + 10 * (index - 232);
+            (level, level, level)
+        }
+    }
+}
+
+/// Return the basic ANSI color that is closest to the given RGB color.
+fn nearest_ansi16(rgb: (u8, u8, u8)) -> Color {
+    let index = (0..16)
+        .min_by_key(|&i: &u8| distance(ANSI16_COLORS[usize::from(i)], rgb))
+        .unwrap_or(0);
+    match index {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Purple,
+        6 => Color::Cyan,
+        7 => Color::White,
+        bright => Color::Fixed(bright),
+    }
+}
+
+impl ColorDepth {
+    /// Detect the color depth of the terminal from the values of the
+    /// `COLORTERM` and `TERM` env variables.
+    ///
+    /// If neither is set (e.g. on Windows), nothing is known about the
+    /// terminal and colors are used as configured.
+    pub fn detect(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        match (colorterm, term) {
+            (Some("truecolor" | "24bit"), _) | (None, None) => Self::TrueColor,
+            (_, Some(term)) if term.contains("256color") => Self::Ansi256,
+            _ => Self::Ansi16,
+        }
+    }
+
+    /// Convert a color to the best representation this color depth supports.
+    pub fn convert(self, color: Color) -> Color {
+        match (self, color) {
+            (Self::Ansi256, Color::RGB(r, g, b)) => Color::Fixed(nearest_ansi256((r, g, b))),
+            (Self::Ansi16, Color::RGB(r, g, b)) => nearest_ansi16((r, g, b)),
+            (Self::Ansi16, Color::Fixed(index)) if index >= 16 => {
+                nearest_ansi16(ansi256_to_rgb(index))
+            }
+            (_, color) => color,
+        }
+    }
+}
+
+/// The default styles to use, depending on the background color of the
+/// terminal.
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// Styles for dark backgrounds
+    #[default]
+    Dark,
+    /// Styles for light backgrounds
+    Light,
+    /// Detect the background color, assuming a dark background if unknown
+    Auto,
+}
+
+impl Theme {
+    /// Return whether the styles for light backgrounds should be used. For
+    /// `auto`, the background color is taken from the value of the
+    /// `COLORFGBG` env variable (e.g. `0;15`), which some terminals set.
+    pub fn is_light(self, colorfgbg: Option<&str>) -> bool {
+        match self {
+            Self::Dark => false,
+            Self::Light => true,
+            // The background is the last field, an ANSI color index. Colors
+            // 7 (white) and 9 to 15 (the bright colors) are light.
+            Self::Auto => colorfgbg
+                .and_then(|value| value.rsplit(';').next())
+                .and_then(|background| background.parse::<u8>().ok())
+                .map_or(false, |background| matches!(background, 7 | 9..=15)),
+        }
+    }
+}
+
+impl str::FromStr for Theme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "dark" => Ok(Self::Dark),
+            "light" => Ok(Self::Light),
+            "auto" => Ok(Self::Auto),
+            other => Err(anyhow!(
+                "Unknown theme: {}. Possible values: dark, light, auto",
+                other
+            )),
+        }
+    }
+}
+
+impl str::FromStr for ColorDepth {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "truecolor" => Ok(Self::TrueColor),
+            "256" => Ok(Self::Ansi256),
+            "16" => Ok(Self::Ansi16),
+            other => Err(anyhow!(
+                "Unknown color depth: {}. Possible values: truecolor, 256, 16",
+                other
+            )),
+        }
+    }
+}
+
+/// The format of machine readable output (e.g. of `--show-paths --quiet`).
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
+impl str::FromStr for OutputFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(anyhow!(
+                "Unknown output format: {}. Possible values: text, json",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub enum LineType {
     Empty,
     Title(String),
     Description(String),
+    /// A quoted line within the description (e.g. `> > Note: ...`)
+    Callout(String),
+    /// Alternative names of the command (e.g. `> Aliases: foo, bar`)
+    Aliases(Vec<String>),
     ExampleText(String),
     ExampleCode(String),
+    /// A usage summary derived from an example command (see
+    /// `display.show_usage`). Page files never contain this line.
+    Usage(String),
     Other(String),
 }
 
@@ -140,11 +427,7 @@ impl<'a> From<&'a str> for LineType {
                     .trim_start_matches(|chr: char| chr == '#' || chr.is_whitespace())
                     .into(),
             ),
-            Some('>') => Self::Description(
-                trimmed
-                    .trim_start_matches(|chr: char| chr == '>' || chr.is_whitespace())
-                    .into(),
-            ),
+            Some('>') => Self::from_quote(trimmed),
             Some(' ') => Self::ExampleCode(trimmed.trim_start_matches(char::is_whitespace).into()),
             Some(_) => Self::ExampleText(trimmed.into()),
         }
@@ -152,6 +435,23 @@ impl<'a> From<&'a str> for LineType {
 }
 
 impl LineType {
+    /// Parse a line starting with `>`. A nested quote (`> > text`) is a
+    /// callout and an `aliases:` line lists aliases, all other quoted lines
+    /// are part of the description.
+    fn from_quote(line: &str) -> Self {
+        let text = line.trim_start_matches('>').trim_start();
+        if text.starts_with('>') {
+            Self::Callout(
+                text.trim_start_matches(|chr: char| chr == '>' || chr.is_whitespace())
+                    .into(),
+            )
+        } else if let Some(aliases) = parse_aliases(text) {
+            Self::Aliases(aliases)
+        } else {
+            Self::Description(text.into())
+        }
+    }
+
     /// Support for old format.
     /// TODO: Remove once old format has been phased out!
     pub fn from_v1(line: &str) -> Self {
@@ -164,11 +464,7 @@ impl LineType {
                     .trim_start_matches(|chr: char| chr == '#' || chr.is_whitespace())
                     .into(),
             ),
-            Some('>') => Self::Description(
-                trimmed
-                    .trim_start_matches(|chr: char| chr == '>' || chr.is_whitespace())
-                    .into(),
-            ),
+            Some('>') => Self::from_quote(trimmed),
             Some('-') => Self::ExampleText(
                 trimmed
                     .trim_start_matches(|chr: char| chr == '-' || chr.is_whitespace())
@@ -213,7 +509,91 @@ impl fmt::Display for PathSource {
 
 #[cfg(test)]
 mod test {
-    use super::LineType;
+    use ansi_term::Color;
+
+    use super::{is_wsl_kernel, split_platform_tag, ColorDepth, LineType, PlatformType, Theme};
+
+    #[test]
+    fn test_detect_color_depth() {
+        assert_eq!(
+            ColorDepth::detect(Some("truecolor"), Some("xterm-256color")),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            ColorDepth::detect(None, Some("xterm-256color")),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(ColorDepth::detect(None, Some("linux")), ColorDepth::Ansi16);
+        assert_eq!(ColorDepth::detect(None, None), ColorDepth::TrueColor);
+    }
+
+    #[test]
+    fn test_convert_color_depth() {
+        let orange = Color::RGB(255, 136, 0);
+        assert_eq!(ColorDepth::TrueColor.convert(orange), orange);
+        assert_eq!(ColorDepth::Ansi256.convert(orange), Color::Fixed(208));
+        assert_eq!(
+            ColorDepth::Ansi256.convert(Color::RGB(128, 128, 128)),
+            Color::Fixed(244)
+        );
+        assert_eq!(ColorDepth::Ansi256.convert(Color::Red), Color::Red);
+        assert_eq!(ColorDepth::Ansi16.convert(orange), Color::Yellow);
+        assert_eq!(
+            ColorDepth::Ansi16.convert(Color::RGB(250, 10, 10)),
+            Color::Fixed(9)
+        );
+        assert_eq!(ColorDepth::Ansi16.convert(Color::Fixed(21)), Color::Blue);
+        assert_eq!(
+            ColorDepth::Ansi16.convert(Color::Fixed(12)),
+            Color::Fixed(12)
+        );
+    }
+
+    #[test]
+    fn test_theme_is_light() {
+        assert!(!Theme::Dark.is_light(Some("0;15")));
+        assert!(Theme::Light.is_light(None));
+        assert!(Theme::Auto.is_light(Some("0;15")));
+        assert!(Theme::Auto.is_light(Some("0;default;7")));
+        assert!(!Theme::Auto.is_light(Some("15;0")));
+        assert!(!Theme::Auto.is_light(Some("7;8")));
+        assert!(!Theme::Auto.is_light(Some("15;default")));
+        assert!(!Theme::Auto.is_light(None));
+    }
+
+    #[test]
+    fn test_split_platform_tag() {
+        assert_eq!(
+            split_platform_tag("List files: <!-- platform: linux, macOS -->"),
+            ("List files:", vec![PlatformType::Linux, PlatformType::OsX])
+        );
+        assert_eq!(
+            split_platform_tag("List files:<!--Platform:windows-->"),
+            ("List files:", vec![PlatformType::Windows])
+        );
+        assert_eq!(
+            split_platform_tag("List files: <!-- platform: beos -->"),
+            ("List files:", vec![])
+        );
+        assert_eq!(
+            split_platform_tag("List files: <!-- note -->"),
+            ("List files: <!-- note -->", vec![])
+        );
+        assert_eq!(split_platform_tag("List files:"), ("List files:", vec![]));
+    }
+
+    #[test]
+    fn test_is_wsl_kernel() {
+        assert!(is_wsl_kernel(
+            "Linux version 4.4.0-19041-Microsoft (Microsoft@Microsoft.com) (gcc version 5.4.0 (GCC) )"
+        ));
+        assert!(is_wsl_kernel(
+            "Linux version 5.10.16.3-microsoft-standard-WSL2 (oe-user@oe-host) (x86_64-msft-linux-gcc (GCC) 9.3.0)"
+        ));
+        assert!(!is_wsl_kernel(
+            "Linux version 5.15.0-48-generic (buildd@lcy02-amd64-080) (gcc (Ubuntu 11.2.0-19ubuntu1) 11.2.0)"
+        ));
+    }
 
     #[test]
     fn test_linetype_from_str() {
@@ -227,6 +607,22 @@ mod test {
             LineType::from("> tis a description \n"),
             LineType::Description("tis a description".into())
         );
+        assert_eq!(
+            LineType::from("> > Note: this is a callout"),
+            LineType::Callout("Note: this is a callout".into())
+        );
+        assert_eq!(
+            LineType::from("> Aliases: foo, bar-baz ,"),
+            LineType::Aliases(vec!["foo".into(), "bar-baz".into()])
+        );
+        assert_eq!(
+            LineType::from("> aliases:"),
+            LineType::Description("aliases:".into())
+        );
+        assert_eq!(
+            LineType::from("> More information: <https://example.com>."),
+            LineType::Description("More information: <https://example.com>.".into())
+        );
         assert_eq!(
             LineType::from("some command "),
             LineType::ExampleText("some command".into())
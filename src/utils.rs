@@ -1,20 +1,72 @@
+use std::{
+    env,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
 use ansi_term::{Color, Style};
 
+use crate::config::WarningStream;
+
+/// Whether warnings and errors are printed as JSON objects.
+static JSON_MESSAGES: AtomicBool = AtomicBool::new(false);
+
+/// Print warnings and errors as JSON objects (e.g.
+/// `{"level":"warn","message":"..."}`) instead of plain text, so that they can
+/// be parsed along with JSON output (`--format json`).
+pub fn set_json_messages(enabled: bool) {
+    JSON_MESSAGES.store(enabled, Ordering::Relaxed);
+}
+
+/// Return the message as a JSON object with the given level, if JSON
+/// messages are enabled.
+fn json_message(level: &str, message: &str) -> Option<String> {
+    JSON_MESSAGES
+        .load(Ordering::Relaxed)
+        .then(|| serde_json::json!({ "level": level, "message": message }).to_string())
+}
+
 /// Print a warning to stderr. If `enable_styles` is true, then a yellow
 /// message will be printed.
 pub fn print_warning(enable_styles: bool, message: &str) {
-    print_msg(enable_styles, message, "Warning: ", Color::Yellow);
+    match json_message("warn", message) {
+        Some(json) => eprintln!("{json}"),
+        None => print_msg(enable_styles, message, "Warning: ", Color::Yellow),
+    }
+}
+
+/// Print an informational message (e.g. a note about the rendered page) to
+/// stderr.
+pub fn print_info(message: &str) {
+    match json_message("info", message) {
+        Some(json) => eprintln!("{json}"),
+        None => eprintln!("{message}"),
+    }
+}
+
+/// Print a warning to the given stream. If `enable_styles` is true, then a
+/// yellow message will be printed. JSON warnings are always printed to
+/// stderr, so that they don't mix with the JSON output.
+pub fn print_warning_to(stream: WarningStream, enable_styles: bool, message: &str) {
+    match stream {
+        WarningStream::Stderr => print_warning(enable_styles, message),
+        WarningStream::Stdout if JSON_MESSAGES.load(Ordering::Relaxed) => {
+            print_warning(enable_styles, message);
+        }
+        WarningStream::Stdout if enable_styles => {
+            let style = Style::new().fg(Color::Yellow);
+            println!("{}{}", style.paint("Warning: "), style.paint(message));
+        }
+        WarningStream::Stdout => println!("{message}"),
+    }
 }
 
 /// Print an anyhow error to stderr. If `enable_styles` is true, then a red
 /// message will be printed.
 pub fn print_error(enable_styles: bool, error: &anyhow::Error) {
-    print_msg(
-        enable_styles,
-        &format!("{:?}", error),
-        "Error: ",
-        Color::Red,
-    );
+    match json_message("error", &format!("{error:#}")) {
+        Some(json) => eprintln!("{json}"),
+        None => print_msg(enable_styles, &format!("{error:?}"), "Error: ", Color::Red),
+    }
 }
 
 fn print_msg(enable_styles: bool, message: &str, prefix: &'static str, color: Color) {
@@ -25,3 +77,118 @@ fn print_msg(enable_styles: bool, message: &str, prefix: &'static str, color: Co
         eprintln!("{}", message);
     }
 }
+
+/// Return the edit distance between `a` and `b`: the number of inserted,
+/// deleted or replaced characters and swapped adjacent characters (optimal
+/// string alignment distance).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    // The rows of the distance matrix for the previous two characters of `a`
+    let mut before_previous: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for i in 0..a.len() {
+        let mut current = vec![i + 1];
+        for j in 0..b.len() {
+            let replace = previous[j] + usize::from(a[i] != b[j]);
+            let mut distance = replace.min(previous[j + 1] + 1).min(current[j] + 1);
+            if i > 0 && j > 0 && a[i] == b[j - 1] && a[i - 1] == b[j] {
+                distance = distance.min(before_previous[j - 1] + 1);
+            }
+            current.push(distance);
+        }
+        before_previous = previous;
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Return up to `max` of the `names` that are similar to `name` (e.g. with a
+/// typo fixed), the most similar ones first.
+pub fn similar_names<'a>(
+    name: &str,
+    names: impl IntoIterator<Item = &'a str>,
+    max: usize,
+) -> Vec<&'a str> {
+    // Allow one edit for every three characters, but at least one
+    let max_distance = (name.chars().count() / 3).max(1);
+    let mut similar: Vec<(usize, &str)> = names
+        .into_iter()
+        .filter(|&candidate| candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .collect();
+    similar.sort_unstable();
+    similar.dedup();
+    similar
+        .into_iter()
+        .take(max)
+        .map(|(_, name)| name)
+        .collect()
+}
+
+/// Format a size in bytes for humans (e.g. `1.5 MiB`).
+pub fn format_size(bytes: u64) -> String {
+    let (mut unit, mut unit_name) = (1, "B");
+    for name in ["KiB", "MiB", "GiB", "TiB"] {
+        if bytes / 1024 < unit {
+            break;
+        }
+        unit *= 1024;
+        unit_name = name;
+    }
+    if unit == 1 {
+        format!("{bytes} B")
+    } else {
+        // One decimal place, rounded down
+        let tenths = u128::from(bytes) * 10 / u128::from(unit);
+        format!("{}.{} {unit_name}", tenths / 10, tenths % 10)
+    }
+}
+
+/// Return the width of the terminal in columns.
+///
+/// The `COLUMNS` env variable takes precedence over the width reported by
+/// the terminal. If neither is available (e.g. because stdout is not a
+/// terminal), `None` is returned.
+pub fn terminal_width() -> Option<usize> {
+    env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|&columns: &usize| columns > 0)
+        .or_else(|| terminal_size::terminal_size().map(|(width, _)| usize::from(width.0)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, format_size, similar_names};
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("tar", "tar"), 0);
+        assert_eq!(edit_distance("tar", "tra"), 1);
+        assert_eq!(edit_distance("git", "gti"), 1);
+        assert_eq!(edit_distance("ls", "lsof"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn test_similar_names() {
+        let names = ["tar", "tac", "top", "git", "gzip", "docker", "dock"];
+        assert_eq!(similar_names("tra", names, 3), ["tar"]);
+        assert_eq!(similar_names("dokcer", names, 3), ["docker"]);
+        assert_eq!(similar_names("ta", names, 1), ["tac"]);
+        assert!(similar_names("zzzzzz", names, 3).is_empty());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.0 KiB");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(10 * 1024 * 1024 - 1), "9.9 MiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
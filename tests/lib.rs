@@ -1,8 +1,9 @@
 //! Integration tests.
 
 use std::{
-    fs::{create_dir_all, File},
+    fs::{create_dir_all, read_to_string, File},
     io::Write,
+    path::Path,
     process::Command,
     time::{Duration, SystemTime},
 };
@@ -10,7 +11,7 @@ use std::{
 use assert_cmd::prelude::*;
 use predicates::{
     boolean::PredicateBooleanExt,
-    prelude::predicate::str::{contains, diff, is_empty},
+    prelude::predicate::str::{contains, diff, is_empty, starts_with},
 };
 use tempfile::{Builder, TempDir};
 
@@ -59,11 +60,17 @@ impl TestEnv {
 
     /// Add entry for that environment to an OS-specific subfolder.
     fn add_os_entry(&self, os: &str, name: &str, contents: &str) {
+        self.add_lang_entry("pages", os, name, contents);
+    }
+
+    /// Add entry for that environment to an OS-specific subfolder of a
+    /// language directory (e.g. `pages.de`).
+    fn add_lang_entry(&self, lang_dir: &str, os: &str, name: &str, contents: &str) {
         let dir = self
             .cache_dir
             .path()
             .join(TLDR_PAGES_DIR)
-            .join("pages")
+            .join(lang_dir)
             .join(os);
         create_dir_all(&dir).unwrap();
 
@@ -111,7 +118,7 @@ impl TestEnv {
             build = build.arg("--no-default-features");
         }
         if !self.features.is_empty() {
-            build = build.arg(&format!("--feature {}", self.features.join(",")));
+            build = build.arg(&format!("--features={}", self.features.join(",")));
         }
         let run = build.run().unwrap();
         let mut cmd = run.command();
@@ -120,6 +127,9 @@ impl TestEnv {
             "TEALDEER_CONFIG_DIR",
             self.config_dir.path().to_str().unwrap(),
         );
+        // These take precedence over `LANG`, which is set by some tests
+        cmd.env_remove("LC_ALL");
+        cmd.env_remove("LC_MESSAGES");
         cmd
     }
 }
@@ -173,6 +183,111 @@ fn test_quiet_cache() {
         .stdout(is_empty());
 }
 
+#[test]
+fn test_clear_cache_dry_run() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    let pages_dir = testenv.cache_dir.path().join(TLDR_PAGES_DIR);
+
+    testenv
+        .command()
+        .args(["--clear-cache", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(format!("Would delete {} (14 B)\n", pages_dir.display()));
+    assert!(pages_dir.join("pages/common/foo.md").is_file());
+
+    testenv.command().args(["--clear-cache"]).assert().success();
+    testenv
+        .command()
+        .args(["--clear-cache", "--dry-run"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "Nothing to delete, there are no pages in {}.\n",
+            testenv.cache_dir.path().display()
+        ));
+}
+
+#[test]
+fn test_immutable_cache() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Run foo:\n\n`foo`\n");
+    testenv.write_config("[updates]\nauto_update = true\nauto_update_interval_hours = 0");
+
+    for args in [&["--clear-cache"][..], &["--update"]] {
+        testenv
+            .command()
+            .args(args)
+            .env("TEALDEER_CACHE_IMMUTABLE", "1")
+            .assert()
+            .failure()
+            .stderr(contains(
+                "the cache is immutable because $TEALDEER_CACHE_IMMUTABLE is set",
+            ));
+    }
+    assert!(testenv
+        .cache_dir
+        .path()
+        .join(TLDR_PAGES_DIR)
+        .join("pages/common/foo.md")
+        .is_file());
+
+    // Automatic updates are skipped
+    testenv
+        .command()
+        .args(["foo"])
+        .env("TEALDEER_CACHE_IMMUTABLE", "1")
+        .assert()
+        .success()
+        .stdout(contains("Foo."))
+        .stderr(is_empty());
+}
+
+#[test]
+fn test_update_quiet_success() {
+    let testenv = TestEnv::new();
+    // Only the success message is suppressed, other messages are still printed
+    let url = serve_once(test_archive());
+    testenv.write_config(format!(
+        "[updates]\nquiet_success = true\narchive_url = '{url}'"
+    ));
+    let path = testenv.input_dir.path().join("tldr");
+    testenv
+        .command()
+        .args(["--update", "--keep-archive", path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(
+            contains("Saved the downloaded archive to")
+                .and(contains("Successfully updated cache").not()),
+        );
+
+    let mirror_url = serve_once(test_archive());
+    testenv.write_config(format!(
+        "[updates]\nquiet_success = true\n\
+         archive_urls = ['http://127.0.0.1:1/tldr.zip', '{mirror_url}']"
+    ));
+    testenv
+        .command()
+        .args(["--update"])
+        .assert()
+        .success()
+        .stderr(
+            contains(format!("Downloaded the archive from mirror {mirror_url}."))
+                .and(contains("Successfully updated cache").not()),
+        );
+
+    testenv.write_config("[updates]\nquiet_success = true");
+    testenv
+        .command()
+        .args(["--update"])
+        .assert()
+        .success()
+        .stdout(is_empty())
+        .stderr(is_empty());
+}
+
 #[test]
 fn test_quiet_failures() {
     let testenv = TestEnv::new();
@@ -193,16 +308,37 @@ fn test_quiet_failures() {
 }
 
 #[test]
-fn test_quiet_old_cache() {
+fn test_old_cache_warning_stream() {
     let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    filetime::set_file_mtime(
+        testenv.cache_dir.path().join(TLDR_PAGES_DIR),
+        filetime::FileTime::from_unix_time(1, 0),
+    )
+    .unwrap();
 
     testenv
         .command()
-        .args(["--update", "-q"])
+        .args(["foo"])
         .assert()
         .success()
-        .stdout(is_empty());
+        .stdout(contains("The cache hasn't been updated for ").not())
+        .stderr(contains("The cache hasn't been updated for "));
+
+    testenv.write_config("[updates]\nwarning_stream = 'stdout'");
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .success()
+        .stdout(contains("The cache hasn't been updated for "))
+        .stderr(contains("The cache hasn't been updated for ").not());
+}
 
+#[test]
+fn test_json_warnings() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
     filetime::set_file_mtime(
         testenv.cache_dir.path().join(TLDR_PAGES_DIR),
         filetime::FileTime::from_unix_time(1, 0),
@@ -211,344 +347,2832 @@ fn test_quiet_old_cache() {
 
     testenv
         .command()
-        .args(["tldr"])
+        .args(["--format", "json", "foo"])
         .assert()
         .success()
-        .stderr(contains("The cache hasn't been updated for "));
-
+        .stderr(starts_with(
+            r#"{"level":"warn","message":"The cache hasn't been updated for "#,
+        ))
+        .stderr(contains(
+            r#"{"level":"info","message":"Note: Page exists but has no examples."}"#,
+        ))
+        .stderr(contains("Warning:").not());
+
+    // JSON warnings are printed to stderr, even if configured otherwise
+    testenv.write_config("[updates]\nwarning_stream = 'stdout'");
     testenv
         .command()
-        .args(["tldr", "--quiet"])
+        .args(["--format", "json", "foo"])
         .assert()
         .success()
-        .stderr(contains("The cache hasn't been updated for ").not());
+        .stdout(contains("The cache hasn't been updated for ").not())
+        .stderr(starts_with(r#"{"level":"warn","message":"#));
+
+    testenv.write_config("[display\ncompact = true");
+    testenv
+        .command()
+        .args(["--format", "json", "foo"])
+        .assert()
+        .failure()
+        .stderr(starts_with(
+            r#"{"level":"error","message":"Could not load config"#,
+        ));
 }
 
 #[test]
-fn test_create_cache_directory_path() {
+fn test_text_format_requires_show_paths() {
     let testenv = TestEnv::new();
-    let cache_dir = testenv.cache_dir.path();
-    let internal_cache_dir = cache_dir.join("internal");
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
 
-    let mut command = testenv.command();
-    command.env(CACHE_DIR_ENV_VAR, internal_cache_dir.to_str().unwrap());
+    testenv
+        .command()
+        .args(["--format", "text", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains("The `text` format requires --show-paths"));
+}
 
-    assert!(!internal_cache_dir.exists());
+/// Serve `body` on a local port for a single HTTP request and return the URL.
+fn serve_once(body: Vec<u8>) -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    std::thread::spawn(move || {
+        use std::io::Read;
+
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+            let n = stream.read(&mut buf).unwrap();
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(&body).unwrap();
+    });
+    format!("http://127.0.0.1:{port}/tldr.zip")
+}
 
-    command
-        .arg("-u")
+/// Return a ZIP archive with a `tar` page.
+fn test_archive() -> Vec<u8> {
+    test_archive_in("")
+}
+
+/// Return a ZIP archive with a `tar` page, with the pages directories in
+/// the directory `subdir` of the archive.
+fn test_archive_in(subdir: &str) -> Vec<u8> {
+    let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    archive
+        .start_file(
+            format!("{subdir}pages/common/tar.md"),
+            zip::write::FileOptions::default(),
+        )
+        .unwrap();
+    archive
+        .write_all(b"# tar\n\n> Archiving utility.\n\n- Create:\n\n`tar cf {{a}}`\n")
+        .unwrap();
+    archive.finish().unwrap().into_inner()
+}
+
+#[test]
+fn test_update_languages() {
+    let mut archive = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (path, description) in [
+        ("pages/common/tar.md", "Archiving utility."),
+        ("pages.de/common/tar.md", "Archivierungsprogramm."),
+        ("pages.fr/common/tar.md", "Utilitaire d'archivage."),
+    ] {
+        archive
+            .start_file(path, zip::write::FileOptions::default())
+            .unwrap();
+        write!(archive, "# tar\n\n> {description}\n").unwrap();
+    }
+    let archive = archive.finish().unwrap().into_inner();
+
+    let testenv = TestEnv::new();
+    let url = serve_once(archive);
+    testenv.write_config(format!("[updates]\narchive_url = '{url}'"));
+    testenv
+        .command()
+        .args(["--update", "--language", "de"])
+        .assert()
+        .success();
+    for (language, description) in [
+        ("de", "Archivierungsprogramm."),
+        ("en", "Archiving utility."),
+    ] {
+        testenv
+            .command()
+            .args(["--language", language, "tar"])
+            .assert()
+            .success()
+            .stdout(contains(description));
+    }
+
+    // Other languages are not extracted
+    testenv
+        .command()
+        .args(["--language", "fr", "tar"])
+        .assert()
+        .failure();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_cache_dir_mode() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let testenv = TestEnv::new();
+    let url = serve_once(test_archive());
+    testenv.write_config(format!(
+        "[updates]\narchive_url = '{url}'\ncache_dir_mode = '750'"
+    ));
+    testenv.command().args(["--update"]).assert().success();
+
+    let mode = |path: &Path| path.metadata().unwrap().permissions().mode() & 0o7777;
+    assert_eq!(mode(testenv.cache_dir.path()), 0o750);
+    assert_eq!(mode(&testenv.cache_dir.path().join(TLDR_PAGES_DIR)), 0o750);
+}
+
+#[cfg(feature = "page-index")]
+#[test]
+fn test_page_index() {
+    let testenv = TestEnv::new().with_feature("page-index");
+    let url = serve_once(test_archive());
+    testenv.write_config(format!("[updates]\narchive_url = '{url}'"));
+    testenv.command().args(["--update"]).assert().success();
+    assert!(testenv
+        .cache_dir
+        .path()
+        .join(TLDR_PAGES_DIR)
+        .join("index.json")
+        .is_file());
+
+    // Pages are listed from the index, not from the page directories
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv
+        .command()
+        .args(["--list"])
         .assert()
         .success()
-        .stderr(contains(format!(
-            "Successfully created cache directory path `{}`.",
-            internal_cache_dir.to_str().unwrap()
-        )))
-        .stderr(contains("Successfully updated cache."));
+        .stdout("tar\n");
 
-    assert!(internal_cache_dir.is_dir());
+    // Without an index, the page directories are scanned
+    std::fs::remove_file(
+        testenv
+            .cache_dir
+            .path()
+            .join(TLDR_PAGES_DIR)
+            .join("index.json"),
+    )
+    .unwrap();
+    testenv
+        .command()
+        .args(["--list"])
+        .assert()
+        .success()
+        .stdout("foo\ntar\n");
 }
 
 #[test]
-fn test_cache_location_not_a_directory() {
+fn test_update_pages_subdir() {
+    // The pages directory is detected automatically
     let testenv = TestEnv::new();
-    let cache_dir = testenv.cache_dir.path();
-    let internal_file = cache_dir.join("internal");
-    File::create(&internal_file).unwrap();
+    let url = serve_once(test_archive_in("tldr-main/"));
+    testenv.write_config(format!("[updates]\narchive_url = '{url}'"));
+    testenv.command().args(["--update"]).assert().success();
+    testenv
+        .command()
+        .args(["tar"])
+        .assert()
+        .success()
+        .stdout(contains("Archiving utility."));
 
-    let mut command = testenv.command();
-    command.env(CACHE_DIR_ENV_VAR, internal_file.to_str().unwrap());
+    // A configured subdirectory takes precedence
+    let testenv = TestEnv::new();
+    let url = serve_once(test_archive_in("docs/tldr/"));
+    testenv.write_config(format!(
+        "[updates]\narchive_url = '{url}'\npages_subdir = 'docs/tldr'"
+    ));
+    testenv.command().args(["--update"]).assert().success();
+    testenv
+        .command()
+        .args(["tar"])
+        .assert()
+        .success()
+        .stdout(contains("Archiving utility."));
 
-    command
-        .arg("-u")
+    // A missing subdirectory is an error
+    let testenv = TestEnv::new();
+    let url = serve_once(test_archive());
+    testenv.write_config(format!(
+        "[updates]\narchive_url = '{url}'\npages_subdir = 'docs'"
+    ));
+    testenv
+        .command()
+        .args(["--update"])
         .assert()
         .failure()
+        .stderr(contains("does not contain the pages directory `docs`"));
+}
+
+#[test]
+fn test_update_keep_archive() {
+    let testenv = TestEnv::new();
+    let archive = test_archive();
+    let url = serve_once(archive.clone());
+    testenv.write_config(format!("[updates]\narchive_url = '{url}'"));
+
+    let path = testenv.input_dir.path().join("tldr");
+    let saved_path = testenv.input_dir.path().join("tldr.zip");
+    testenv
+        .command()
+        .args(["--update", "--keep-archive", path.to_str().unwrap()])
+        .assert()
+        .success()
         .stderr(contains(format!(
-            "Path specified by ${} is not a directory",
-            CACHE_DIR_ENV_VAR
+            "Saved the downloaded archive to {}.",
+            saved_path.display()
         )));
+    assert_eq!(std::fs::read(saved_path).unwrap(), archive);
 }
 
 #[test]
-fn test_setup_seed_config() {
+fn test_raw_markdown_highlighting() {
     let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Do it:\n\n`foo {{bar}}`\n");
 
+    // The raw markdown is only highlighted if colors are enabled
     testenv
         .command()
-        .args(["--seed-config"])
+        .args(["--raw", "--color", "always", "foo"])
         .assert()
         .success()
-        .stderr(contains("Successfully created seed config file here"));
+        .stdout(contains("foo \x1b[0m").and(contains("{{bar}}\x1b[0m")));
+    testenv
+        .command()
+        .args(["--raw", "foo"])
+        .assert()
+        .success()
+        .stdout("# foo\n\n> Foo.\n\n- Do it:\n\n`foo {{bar}}`\n");
+
+    testenv.write_config("[display]\ncolor = \"always\"");
+    testenv
+        .command()
+        .args(["--raw", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("{{bar}}\x1b[0m"));
 }
 
 #[test]
-fn test_show_paths() {
+fn test_print_shell_hook() {
     let testenv = TestEnv::new();
 
-    // Show general commands
+    for shell in ["bash", "zsh", "fish"] {
+        testenv
+            .command()
+            .args(["--print-shell-hook", shell])
+            .assert()
+            .success()
+            .stdout(contains("command tldr --update --quiet"))
+            .stdout(contains(r"printf '%s\n'"));
+    }
+
     testenv
         .command()
-        .args(["--show-paths"])
+        .args(["--print-shell-hook", "tcsh"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_raw_command() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "tar",
+        "# tar\n\n> Archiver.\n\n- Create:\n\n`tar cf {{target.tar}} {{file}}`\n\n\
+         - Extract:\n\n`tar xf {{source.tar}}`\n",
+    );
+
+    testenv
+        .command()
+        .args([
+            "--color",
+            "always",
+            "tar",
+            "--example",
+            "2",
+            "--raw-command",
+        ])
         .assert()
         .success()
-        .stdout(contains(format!(
-            "Config dir:       {}",
-            testenv.config_dir.path().to_str().unwrap(),
-        )))
-        .stdout(contains(format!(
-            "Config path:      {}",
-            testenv
-                .config_dir
-                .path()
-                .join("config.toml")
-                .to_str()
-                .unwrap(),
-        )))
-        .stdout(contains(format!(
-            "Cache dir:        {}",
-            testenv.cache_dir.path().to_str().unwrap(),
-        )))
-        .stdout(contains(format!(
-            "Pages dir:        {}",
-            testenv
-                .cache_dir
-                .path()
-                .join(TLDR_PAGES_DIR)
-                .to_str()
-                .unwrap(),
-        )));
+        .stdout("tar xf source.tar\n");
+
+    testenv
+        .command()
+        .args(["tar", "--example", "3", "--raw-command"])
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Cannot show example 3, the page only has 2 examples",
+        ));
+
+    // `--example` is only supported together with `--raw-command`
+    testenv
+        .command()
+        .args(["tar", "--example", "1"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_full_width_background() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv.write_config(
+        "[style.background]\nbackground = 'black'\n[display]\nfull_width_background = true",
+    );
+
+    // Every line (including empty ones) is padded to the terminal width
+    testenv
+        .command()
+        .env("COLUMNS", "10")
+        .args(["--color", "always", "foo"])
+        .assert()
+        .success()
+        .stdout(
+            "\x1b[40m          \x1b[0m\n\
+             \x1b[40m  \x1b[0m\x1b[40mFoo.\x1b[0m\x1b[40m    \x1b[0m\n\
+             \x1b[40m          \x1b[0m\n",
+        );
+
+    // Nothing is filled without styles
+    testenv
+        .command()
+        .env("COLUMNS", "10")
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Foo.\n\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_broken_symlink_custom_page() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo from the cache.\n");
+    let custom_pages_dir = testenv.custom_pages_dir.path();
+    let page_path = custom_pages_dir.join("foo.page");
+    std::os::unix::fs::symlink(custom_pages_dir.join("missing.page"), &page_path).unwrap();
+    let config = format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        custom_pages_dir.to_str().unwrap()
+    );
+
+    testenv.write_config(&config);
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .failure()
+        .stderr(contains(format!(
+            "Page is a broken symlink: {}",
+            page_path.display()
+        )));
+
+    // With `skip_broken_symlinks`, the page is looked up as if there was no
+    // custom page
+    testenv.write_config(format!("{config}\nskip_broken_symlinks = true"));
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Foo from the cache."));
+}
+
+#[test]
+fn test_title_link() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv.write_config(format!(
+        "[display]\nshow_title = true\ntitle_link = true\n\
+         [directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+
+    testenv
+        .command()
+        .args(["--color", "always", "foo"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "\x1b]8;;https://github.com/tldr-pages/tldr/blob/main/pages/common/foo.md\x1b\\",
+        ));
+
+    // Custom pages link to the local file
+    testenv.add_page_entry("bar", "# bar\n\n> Bar.\n");
+    testenv
+        .command()
+        .args(["--color", "always", "bar"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b]8;;file:///").and(contains("/bar.page\x1b\\")));
+
+    // No links without styles
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b]8").not());
+}
+
+#[test]
+fn test_update_max_archive_size() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    let url = serve_once(test_archive());
+    testenv.write_config(format!(
+        "[updates]\narchive_url = '{url}'\nmax_archive_size = 100"
+    ));
+
+    testenv
+        .command()
+        .args(["--update"])
+        .assert()
+        .failure()
+        .stderr(contains("is larger than 100 bytes"));
+
+    // The existing cache is not touched
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .success()
+        .stdout(contains("Foo."));
+}
+
+#[test]
+fn test_archive_ref() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv
+        .command()
+        .args(["--cache-version"])
+        .assert()
+        .success()
+        .stdout("latest\n");
+
+    // The archive URL must contain the placeholder for the ref
+    testenv.write_config(
+        "[updates]\narchive_url = 'https://example.com/tldr.zip'\narchive_ref = 'v2.0'",
+    );
+    testenv
+        .command()
+        .args(["--cache-version"])
+        .assert()
+        .failure()
+        .stderr(contains("must contain a `{ref}` placeholder"));
+
+    let url = serve_once(test_archive()).replace("tldr.zip", "{ref}.zip");
+    testenv.write_config(format!(
+        "[updates]\narchive_url = '{url}'\narchive_ref = 'v2.0'"
+    ));
+    testenv
+        .command()
+        .args(["--cache-version"])
+        .assert()
+        .success()
+        .stdout("latest\n")
+        .stderr(contains("not downloaded for the pinned ref `v2.0`"));
+    testenv.command().args(["--update"]).assert().success();
+    testenv
+        .command()
+        .args(["--cache-version"])
+        .assert()
+        .success()
+        .stdout("v2.0\n")
+        .stderr("");
+}
+
+#[test]
+fn test_update_mirror_fallback() {
+    let testenv = TestEnv::new();
+    let mirror_url = serve_once(test_archive());
+
+    // Nothing listens on port 1, so the first mirror fails
+    testenv.write_config(format!(
+        "[updates]\narchive_urls = ['http://127.0.0.1:1/tldr.zip', '{mirror_url}']"
+    ));
+    testenv
+        .command()
+        .args(["--update"])
+        .assert()
+        .success()
+        .stderr(contains(format!(
+            "Successfully updated cache (using mirror {mirror_url})."
+        )));
+
+    testenv
+        .command()
+        .args(["tar"])
+        .assert()
+        .success()
+        .stdout(contains("Archiving utility."));
+}
+
+#[test]
+fn test_quiet_old_cache() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(["--update", "-q"])
+        .assert()
+        .success()
+        .stdout(is_empty());
+
+    filetime::set_file_mtime(
+        testenv.cache_dir.path().join(TLDR_PAGES_DIR),
+        filetime::FileTime::from_unix_time(1, 0),
+    )
+    .unwrap();
+
+    testenv
+        .command()
+        .args(["tldr"])
+        .assert()
+        .success()
+        .stderr(contains("The cache hasn't been updated for "));
+
+    testenv
+        .command()
+        .args(["tldr", "--quiet"])
+        .assert()
+        .success()
+        .stderr(contains("The cache hasn't been updated for ").not());
+}
+
+#[test]
+fn test_create_cache_directory_path() {
+    let testenv = TestEnv::new();
+    let cache_dir = testenv.cache_dir.path();
+    let internal_cache_dir = cache_dir.join("internal");
+
+    let mut command = testenv.command();
+    command.env(CACHE_DIR_ENV_VAR, internal_cache_dir.to_str().unwrap());
+
+    assert!(!internal_cache_dir.exists());
+
+    command
+        .arg("-u")
+        .assert()
+        .success()
+        .stderr(contains(format!(
+            "Successfully created cache directory path `{}`.",
+            internal_cache_dir.to_str().unwrap()
+        )))
+        .stderr(contains("Successfully updated cache."));
+
+    assert!(internal_cache_dir.is_dir());
+}
+
+#[test]
+fn test_cache_location_not_a_directory() {
+    let testenv = TestEnv::new();
+    let cache_dir = testenv.cache_dir.path();
+    let internal_file = cache_dir.join("internal");
+    File::create(&internal_file).unwrap();
+
+    let mut command = testenv.command();
+    command.env(CACHE_DIR_ENV_VAR, internal_file.to_str().unwrap());
+
+    command
+        .arg("-u")
+        .assert()
+        .failure()
+        .stderr(contains(format!(
+            "Path specified by ${} is not a directory",
+            CACHE_DIR_ENV_VAR
+        )));
+}
+
+#[test]
+fn test_setup_seed_config() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(["--seed-config"])
+        .assert()
+        .success()
+        .stderr(contains("Successfully created seed config file here"));
+}
+
+#[test]
+fn test_ignore_bad_config() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv.write_config("[display\ncompact = true");
+
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .failure()
+        .stderr(contains("Could not load config"));
+
+    testenv
+        .command()
+        .args(["--ignore-bad-config", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Foo."))
+        .stderr(contains(
+            "Could not load config, using the default config instead",
+        ));
+
+    testenv
+        .command()
+        .env("TEALDEER_IGNORE_BAD_CONFIG", "1")
+        .args(["foo"])
+        .assert()
+        .success()
+        .stdout(contains("Foo."));
+}
+
+#[test]
+fn test_show_paths() {
+    let testenv = TestEnv::new();
+
+    // Show general commands
+    testenv
+        .command()
+        .args(["--show-paths"])
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "Config dir:       {}",
+            testenv.config_dir.path().to_str().unwrap(),
+        )))
+        .stdout(contains(format!(
+            "Config path:      {}",
+            testenv
+                .config_dir
+                .path()
+                .join("config.toml")
+                .to_str()
+                .unwrap(),
+        )))
+        .stdout(contains(format!(
+            "Cache dir:        {}",
+            testenv.cache_dir.path().to_str().unwrap(),
+        )))
+        .stdout(contains(format!(
+            "Pages dir:        {}",
+            testenv
+                .cache_dir
+                .path()
+                .join(TLDR_PAGES_DIR)
+                .to_str()
+                .unwrap(),
+        )));
+
+    // Set custom pages directory
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+
+    // Now ensure that this path is contained in the output
+    testenv
+        .command()
+        .args(["--show-paths"])
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "Custom pages dir: {}",
+            testenv.custom_pages_dir.path().to_str().unwrap(),
+        )));
+}
+
+#[test]
+fn test_show_paths_machine_readable() {
+    let testenv = TestEnv::new();
+    let config_dir = testenv.config_dir.path().to_str().unwrap();
+    let cache_dir = testenv.cache_dir.path().to_str().unwrap();
+    let custom_pages_dir = testenv.custom_pages_dir.path().to_str().unwrap();
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{custom_pages_dir}'\n\
+         [[sources]]\nname = 'work'\npath = '{custom_pages_dir}'\nenabled = false"
+    ));
+
+    testenv
+        .command()
+        .args(["--show-paths", "--quiet"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "{config_dir}\n{config_dir}/config.toml\n{cache_dir}\n\
+             {cache_dir}/{TLDR_PAGES_DIR}\n{custom_pages_dir}\n"
+        ));
+
+    testenv
+        .command()
+        .args(["--show-paths", "--format", "json"])
+        .assert()
+        .success()
+        .stdout(format!(
+            "{{\"config_dir\":\"{config_dir}\",\"config_path\":\"{config_dir}/config.toml\",\
+             \"cache_dir\":\"{cache_dir}\",\"pages_dir\":\"{cache_dir}/{TLDR_PAGES_DIR}\",\
+             \"custom_pages_dir\":\"{custom_pages_dir}\",\"sources\":[{{\"name\":\"work\",\
+             \"path\":\"{custom_pages_dir}\",\"enabled\":false}}]}}\n"
+        ));
+}
+
+#[test]
+fn test_sources() {
+    let testenv = TestEnv::new();
+    let work_dir = testenv.input_dir.path().join("work");
+    let old_dir = testenv.input_dir.path().join("old");
+    create_dir_all(&work_dir).unwrap();
+    create_dir_all(&old_dir).unwrap();
+    std::fs::write(work_dir.join("deploy.page"), "# deploy\n\n> Work deploy.\n").unwrap();
+    std::fs::write(work_dir.join("tar.page"), "# tar\n\n> Work tar.\n").unwrap();
+    std::fs::write(old_dir.join("legacy.page"), "# legacy\n\n> Old legacy.\n").unwrap();
+    testenv.add_entry("tar", "# tar\n\n> Cached tar.\n");
+    testenv.write_config(format!(
+        "[[sources]]\nname = 'work'\npath = '{}'\n\n\
+         [[sources]]\nname = 'old'\npath = '{}'\nenabled = false\n",
+        work_dir.to_str().unwrap(),
+        old_dir.to_str().unwrap(),
+    ));
+
+    // Pages from sources take precedence over the cache
+    testenv
+        .command()
+        .args(["deploy"])
+        .assert()
+        .success()
+        .stdout(contains("Work deploy."));
+    testenv
+        .command()
+        .args(["tar"])
+        .assert()
+        .success()
+        .stdout(contains("Work tar."));
+    testenv
+        .command()
+        .args(["--explain", "tar"])
+        .assert()
+        .success()
+        .stdout(contains("source\twork"));
+
+    // Disabled sources are not searched
+    testenv.command().args(["legacy"]).assert().failure();
+    testenv
+        .command()
+        .args(["--source", "old", "legacy"])
+        .assert()
+        .failure()
+        .stderr(contains("The source `old` is disabled"));
+
+    // `--source` restricts the lookup to one source
+    testenv
+        .command()
+        .args(["--source", "work", "deploy"])
+        .assert()
+        .success()
+        .stdout(contains("Work deploy."));
+    testenv
+        .command()
+        .args(["--source", "work", "ls"])
+        .assert()
+        .failure()
+        .stderr(contains("Page `ls` not found in source `work`"));
+    testenv
+        .command()
+        .args(["--source", "home", "tar"])
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Unknown source `home` (configured sources: work, old)",
+        ));
+
+    testenv
+        .command()
+        .args(["--show-paths"])
+        .assert()
+        .success()
+        .stdout(contains(format!(
+            "Sources:          work ({})\n                  old ({}, disabled)\n",
+            work_dir.display(),
+            old_dir.display(),
+        )));
+}
+
+#[test]
+fn test_os_specific_page() {
+    let testenv = TestEnv::new();
+
+    testenv.add_os_entry("sunos", "truss", "contents");
+
+    testenv
+        .command()
+        .args(["--platform", "sunos", "truss"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_markdown_rendering() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("which", include_str!("which-markdown.expected"));
+
+    let expected = include_str!("which-markdown.expected");
+    testenv
+        .command()
+        .args(["--raw", "which"])
+        .assert()
+        .success()
+        .stdout(diff(expected));
+}
+
+#[test]
+fn test_dedup_examples() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Run foo:\n\n`foo --bar`\n\n- Run foo again:\n\n`foo --bar`\n",
+    );
+
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Run foo again:"));
+
+    testenv.write_config("[display]\ndedup_examples = true");
+    let output = testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.matches("foo --bar").count(), 1, "{stdout}");
+    assert!(stdout.contains("Run foo:"), "{stdout}");
+    assert!(!stdout.contains("Run foo again:"), "{stdout}");
+}
+
+#[test]
+fn test_debug_tokens() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Run foo:\n\n`foo {{bar}}`\n");
+
+    testenv
+        .command()
+        .args(["--debug-tokens", "foo"])
+        .assert()
+        .success()
+        .stdout(
+            "Title(\"foo\")\nLinebreak\nDescription(\"Foo.\")\nLinebreak\n\
+             Text(\"Run foo:\")\nLinebreak\nCodePrefix\nCommandName(\"foo\")\n\
+             NormalCode(\" \")\nVariable(\"bar\")\nLinebreak\nLinebreak\n",
+        );
+}
+
+#[test]
+fn test_demo() {
+    let testenv = TestEnv::new();
+
+    // No cache is needed
+    testenv
+        .command()
+        .args(["--demo", "--color", "never"])
+        .assert()
+        .success()
+        .stdout(contains("This is a demo page that is built into tealdeer"))
+        .stdout(contains("      tldr --render path/to/page.md"))
+        .stderr(is_empty());
+
+    testenv
+        .command()
+        .args(["--demo", "--raw"])
+        .assert()
+        .success()
+        .stdout(starts_with("# tldr\n"));
+
+    testenv.command().args(["--demo", "tar"]).assert().failure();
+}
+
+fn _test_correct_rendering(
+    input_file: &str,
+    filename: &str,
+    expected: &'static str,
+    color_option: &str,
+) {
+    let testenv = TestEnv::new();
+
+    // Create input file
+    let file_path = testenv.input_dir.path().join(filename);
+    println!("Testfile path: {:?}", file_path);
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(input_file.as_bytes()).unwrap();
+
+    testenv
+        .command()
+        .args(["--color", color_option, "-f", file_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(diff(expected));
+}
+
+#[test]
+fn test_render_glob() {
+    let testenv = TestEnv::new();
+    let dir = testenv.custom_pages_dir.path();
+    for (path, content) in [
+        ("common/foo.md", "# foo\n\n> Foo.\n"),
+        ("linux/bar.md", "# bar\n\n> Bar.\n"),
+        ("linux/notes.txt", "Not a page.\n"),
+    ] {
+        let path = dir.join(path);
+        create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    let pattern = dir.join("**").join("*.md");
+    testenv
+        .command()
+        .args(["--color", "never", "-f", pattern.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(format!(
+            "File: {}\n\n  Foo.\n\nFile: {}\n\n  Bar.\n\n",
+            dir.join("common").join("foo.md").display(),
+            dir.join("linux").join("bar.md").display(),
+        ));
+
+    let pattern = dir.join("*.page");
+    testenv
+        .command()
+        .args(["-f", pattern.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(contains("No files match the pattern"));
+}
+
+/// An end-to-end integration test for direct file rendering (v1 syntax).
+#[test]
+fn test_correct_rendering_v1() {
+    _test_correct_rendering(
+        include_str!("inkscape-v1.md"),
+        "inkscape-v1.md",
+        include_str!("inkscape-default.expected"),
+        "always",
+    );
+}
+
+/// An end-to-end integration test for direct file rendering (v2 syntax).
+#[test]
+fn test_correct_rendering_v2() {
+    _test_correct_rendering(
+        include_str!("inkscape-v2.md"),
+        "inkscape-v2.md",
+        include_str!("inkscape-default.expected"),
+        "always",
+    );
+}
+
+#[test]
+/// An end-to-end integration test for direct file rendering with the `--color auto` option. This
+/// will not use styling since output is not stdout.
+fn test_rendering_color_auto() {
+    _test_correct_rendering(
+        include_str!("inkscape-v2.md"),
+        "inkscape-v2.md",
+        include_str!("inkscape-default-no-color.expected"),
+        "auto",
+    );
+}
+
+#[test]
+/// An end-to-end integration test for direct file rendering with the `--color never` option.
+fn test_rendering_color_never() {
+    _test_correct_rendering(
+        include_str!("inkscape-v2.md"),
+        "inkscape-v2.md",
+        include_str!("inkscape-default-no-color.expected"),
+        "never",
+    );
+}
+
+#[test]
+fn test_rendering_i18n() {
+    _test_correct_rendering(
+        include_str!("chmod.ru.md"),
+        "chmod.ru.md",
+        include_str!("chmod.ru.expected"),
+        "always",
+    );
+}
+
+/// An end-to-end integration test for rendering with custom syntax config.
+#[test]
+fn test_correct_rendering_with_config() {
+    let testenv = TestEnv::new();
+
+    // Setup config file
+    // TODO should be config::CONFIG_FILE_NAME
+    let config_file_path = testenv.config_dir.path().join("config.toml");
+    println!("Config path: {:?}", config_file_path);
+
+    let mut config_file = File::create(&config_file_path).unwrap();
+    config_file
+        .write_all(include_bytes!("config.toml"))
+        .unwrap();
+
+    // Create input file
+    let file_path = testenv.input_dir.path().join("inkscape-v2.md");
+    println!("Testfile path: {:?}", file_path);
+
+    let mut file = File::create(&file_path).unwrap();
+    file.write_all(include_bytes!("inkscape-v2.md")).unwrap();
+
+    // Load expected output
+    let expected = include_str!("inkscape-with-config.expected");
+
+    testenv
+        .command()
+        .args(["--color", "always", "-f", file_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(diff(expected));
+}
+
+#[test]
+fn test_color_config_precedence() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Do foo:\n\n`foo`\n");
+
+    // By default, the output is not colored if stdout is not a terminal
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[").not());
+
+    // The config overrides the default
+    testenv.write_config("[display]\ncolor = 'always'");
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b["));
+
+    // The `--color` flag overrides the config
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[").not());
+
+    testenv.write_config("[display]\ncolor = 'never'");
+    testenv
+        .command()
+        .args(["--color", "always", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b["));
+}
+
+#[test]
+fn test_overflow_truncate() {
+    let testenv = TestEnv::new();
+    testenv.write_config("[display]\noverflow = 'truncate'");
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> A very long description of the foo command.\n\n- Run foo:\n\n`foo {{bar}} --some-option`\n",
+    );
+
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .env("COLUMNS", "20")
+        .assert()
+        .success()
+        .stdout("\n  A very long descr…\n\n  Run foo:\n\n      foo bar --som…\n\n");
+}
+
+#[test]
+fn test_overflow_wrap() {
+    let testenv = TestEnv::new();
+    testenv.write_config("[display]\noverflow = 'wrap'\nmin_wrap_width = 20");
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> A very long description of foo.\n\n- Run foo:\n\n`foo {{bar}} --option`\n",
+    );
+
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .env("COLUMNS", "20")
+        .assert()
+        .success()
+        .stdout("\n  A very long\n  description of\n  foo.\n\n  Run foo:\n\n      foo bar\n      --option\n\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn test_post_render_command() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Run foo:\n\n`foo`\n");
+
+    testenv.write_config("[display]\npost_render_command = \"tr 'a-z' 'A-Z'\"");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("RUN FOO:"))
+        .stderr(is_empty());
+
+    testenv.write_config("[display]\npost_render_command = \"sh -c 'exit 1'\"");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Run foo:"))
+        .stderr(contains(
+            "The post-render command \"sh -c 'exit 1'\" failed",
+        ));
+
+    testenv.write_config("[display]\npost_render_command = \"tealdeer-missing-filter\"");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Run foo:"))
+        .stderr(contains("Could not run the post-render command"));
+}
+
+#[test]
+fn test_cache_rendered() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Run foo:\n\n`foo`\n");
+    let render_cache_dir = testenv.cache_dir.path().join("rendered");
+    let entries = || {
+        let mut entries: Vec<_> = std::fs::read_dir(&render_cache_dir)
+            .map(|entries| entries.map(|entry| entry.unwrap().path()).collect())
+            .unwrap_or_default();
+        entries.sort();
+        entries
+    };
+
+    // Nothing is cached by default
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success();
+    assert!(entries().is_empty());
+
+    testenv.write_config("[display]\ncache_rendered = true");
+    let expected = "\n  Foo.\n\n  Run foo:\n\n      foo\n\n";
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(expected);
+    let cached = entries();
+    assert_eq!(cached.len(), 1);
+    assert_eq!(
+        read_to_string(&cached[0]).unwrap(),
+        format!("1 0 0\n{expected}")
+    );
+
+    // A hit uses the cached output
+    std::fs::write(&cached[0], "1 0 0\n  From the render cache.\n").unwrap();
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout("  From the render cache.\n");
+
+    // Changing the config or the render parameters results in a miss
+    testenv.write_config("[display]\ncache_rendered = true\ncompact = true");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout("  Foo.\n  Run foo:\n      foo\n\n");
+    testenv
+        .command()
+        .args(["--color", "never", "--line-numbers", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("From the render cache").not());
+    assert_eq!(entries().len(), 3);
+
+    // The rendered pages are removed with the rest of the cache
+    testenv.command().args(["--clear-cache"]).assert().success();
+    assert!(entries().is_empty());
+}
+
+#[test]
+fn test_warn_unknown_syntax() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n:::meta\n\n- Run foo:\n\n`foo`\n");
+
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Run foo:"))
+        .stderr(is_empty());
+
+    testenv.write_config("[display]\nwarn_unknown_syntax = true");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("Run foo:").and(contains(":::meta").not()))
+        .stderr(
+            "Note: 1 line of this page could not be understood and is not shown. \
+             Upgrading tealdeer may help.\n",
+        );
+
+    testenv
+        .command()
+        .args(["--quiet", "foo"])
+        .assert()
+        .success()
+        .stderr(is_empty());
+}
+
+#[test]
+fn test_section_rule() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Run foo:\n\n`foo`\n");
+    testenv.write_config("[display]\nsection_rule = '─'");
+
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .env("COLUMNS", "12")
+        .assert()
+        .success()
+        .stdout("\n  Foo.\n\n  ──────────\n\n  Run foo:\n\n      foo\n\n");
+
+    testenv.write_config("[display]\nsection_rule = '-='\ncompact = true");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .env("COLUMNS", "11")
+        .assert()
+        .success()
+        .stdout("  Foo.\n  -=-=-=-=\n  Run foo:\n      foo\n\n");
+}
+
+#[test]
+fn test_debug_width() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Run foo:\n\n`foo`\n");
+
+    testenv
+        .command()
+        .args(["--debug-width", "foo"])
+        .env("COLUMNS", "40")
+        .assert()
+        .success()
+        .stderr("Render width: terminal 40 columns, effective unlimited\n");
+
+    testenv.write_config("[display]\noverflow = 'wrap'\nmin_wrap_width = 20");
+    testenv
+        .command()
+        .args(["--debug-width", "--line-numbers", "foo"])
+        .env("COLUMNS", "40")
+        .assert()
+        .success()
+        .stderr("Render width: terminal 40 columns, effective 36 columns\n");
+
+    testenv
+        .command()
+        .args(["--debug-width", "--quiet", "foo"])
+        .env("COLUMNS", "40")
+        .assert()
+        .success()
+        .stderr(is_empty());
+}
+
+#[test]
+fn test_overflow_wrap_below_min_width() {
+    let testenv = TestEnv::new();
+    testenv.write_config("[display]\noverflow = 'wrap'");
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> A very long description of foo.\n\n- Run foo:\n\n`foo {{bar}} --option`\n",
+    );
+
+    // The terminal is narrower than the default `min_wrap_width`
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .env("COLUMNS", "20")
+        .assert()
+        .success()
+        .stdout("\n  A very long description of foo.\n\n  Run foo:\n\n      foo bar --option\n\n");
+}
+
+#[test]
+fn test_title_rendering() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("git-log", "# git log\n\n> Show the commit history.\n");
+
+    // By default, the title is not rendered
+    testenv
+        .command()
+        .args(["--color", "never", "git", "log"])
+        .assert()
+        .success()
+        .stdout("\n  Show the commit history.\n\n");
+
+    // The page heading is rendered as the title
+    testenv.write_config("[display]\nshow_title = true");
+    testenv
+        .command()
+        .args(["--color", "never", "git-log"])
+        .assert()
+        .success()
+        .stdout("  git log\n\n  Show the commit history.\n\n");
+
+    // The command as entered is rendered as the title
+    testenv.write_config("[display]\nshow_title = true\ntitle_original = true");
+    testenv
+        .command()
+        .args(["--color", "never", "Git-Log"])
+        .assert()
+        .success()
+        .stdout("  Git-Log\n\n  Show the commit history.\n\n");
+}
+
+#[test]
+fn test_callout_rendering() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Do foo things.\n> > Note: Requires bar.\n> More information: <https://example.com>.\n",
+    );
+
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Do foo things.\n  │ Note: Requires bar.\n  More information: <https://example.com>.\n\n");
+
+    testenv.write_config("[display]\ncallout_marker = ''");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("\n  Note: Requires bar.\n"));
+}
+
+#[test]
+#[cfg(unix)]
+fn test_edit_page() {
+    let testenv = TestEnv::new();
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+
+    // The editor command is split respecting quotes
+    testenv
+        .command()
+        .args(["--edit", "git", "Log"])
+        .env_remove("VISUAL")
+        .env("EDITOR", r##"sh -c 'echo "# git log" > "$1"' editor"##)
+        .assert()
+        .success();
+    assert_eq!(
+        read_to_string(testenv.custom_pages_dir.path().join("git-log.page")).unwrap(),
+        "# git log\n"
+    );
+
+    testenv
+        .command()
+        .args(["--edit", "git", "log"])
+        .env("VISUAL", "false")
+        .assert()
+        .failure()
+        .stderr(contains("Editor exited with"));
+}
+
+#[test]
+fn test_edit_page_without_editor() {
+    let testenv = TestEnv::new();
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+
+    testenv
+        .command()
+        .args(["--edit", "foo"])
+        .env_remove("VISUAL")
+        .env_remove("EDITOR")
+        .assert()
+        .failure()
+        .stderr(contains("Neither $VISUAL nor $EDITOR is set"));
+}
+
+#[test]
+fn test_merge_common() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Do a:\n\n`foo a`\n\n- Do b:\n\n`foo b`\n",
+    );
+    testenv.add_os_entry(
+        "linux",
+        "foo",
+        "# foo\n\n> Foo on Linux.\n\n- Do b:\n\n`foo b`\n\n- Do c:\n\n`foo c`\n",
+    );
+
+    testenv
+        .command()
+        .args(["--color", "never", "--platform", "linux", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Foo on Linux.\n\n  Do b:\n\n      foo b\n\n  Do c:\n\n      foo c\n\n");
+
+    testenv.write_config("[directories]\nmerge_common = true");
+    testenv
+        .command()
+        .args(["--color", "never", "--platform", "linux", "foo"])
+        .assert()
+        .success()
+        .stdout(
+            "\n  Foo.\n\n  Do a:\n\n      foo a\n\n  Do b:\n\n      foo b\n\n  Do c:\n\n      foo c\n\n",
+        );
+}
+
+#[test]
+fn test_fill_from_english() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Do a:\n\n`foo a`\n\n- Do b:\n\n`foo b`\n",
+    );
+    testenv.add_lang_entry(
+        "pages.de",
+        "common",
+        "foo",
+        "# foo\n\n> Deutsches Foo.\n\n- Mache a:\n\n`foo a`\n",
+    );
+
+    testenv
+        .command()
+        .args(["--color", "never", "--language", "de", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Deutsches Foo.\n\n  Mache a:\n\n      foo a\n\n");
+
+    testenv.write_config("[display]\nfill_from_english = true");
+    testenv
+        .command()
+        .args(["--color", "never", "--language", "de", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Deutsches Foo.\n\n  Mache a:\n\n      foo a\n\n  Do b:\n\n      foo b\n\n");
+
+    // English pages are not changed
+    testenv
+        .command()
+        .args(["--color", "never", "--language", "en", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Foo.\n\n  Do a:\n\n      foo a\n\n  Do b:\n\n      foo b\n\n");
+}
+
+#[test]
+fn test_aliases() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n> Aliases: bar, baz\n\n- Do foo:\n\n`foo`\n",
+    );
+
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Foo.\n  Aliases: bar, baz\n\n  Do foo:\n\n      foo\n\n");
+
+    // Aliases are only resolved if enabled
+    testenv
+        .command()
+        .args(["--color", "never", "Baz"])
+        .assert()
+        .failure()
+        .stderr(contains("Page `baz` not found in cache."));
+
+    testenv.write_config("[directories]\nresolve_aliases = true");
+    testenv
+        .command()
+        .args(["--color", "never", "Baz"])
+        .assert()
+        .success()
+        .stdout(contains("Aliases: bar, baz"));
+}
+
+#[test]
+fn test_theme() {
+    let testenv = TestEnv::new();
+    let show_command_name = |args: &[&str], colorfgbg: &str| {
+        testenv
+            .command()
+            .args(["--color", "always", "--show-style", "command_name"])
+            .args(args)
+            .env("COLORFGBG", colorfgbg)
+            .assert()
+            .success()
+    };
+
+    show_command_name(&[], "0;15").stdout(contains("foreground: Cyan\n"));
+    show_command_name(&["--theme", "light"], "15;0").stdout(contains("foreground: Blue\n"));
+    show_command_name(&["--theme", "auto"], "0;15").stdout(contains("foreground: Blue\n"));
+    show_command_name(&["--theme", "auto"], "15;0").stdout(contains("foreground: Cyan\n"));
+
+    testenv.write_config("[display]\ntheme = 'auto'");
+    show_command_name(&[], "0;15").stdout(contains("foreground: Blue\n"));
+    show_command_name(&["--theme", "dark"], "0;15").stdout(contains("foreground: Cyan\n"));
+
+    // Styles from the config file are kept
+    testenv.write_config("[display]\ntheme = 'light'\n\n[style.command_name]\nforeground = 'red'");
+    show_command_name(&[], "0;15").stdout(contains("foreground: Red\n"));
+}
+
+#[test]
+fn test_show_style() {
+    let testenv = TestEnv::new();
+    testenv.write_config("[style.description]\nforeground = 'red'\nbold = true");
+
+    testenv
+        .command()
+        .args(["--color", "always", "--show-style", "description"])
+        .assert()
+        .success()
+        .stdout(contains(
+            "foreground: Red\nbackground: none\nbold: true\nitalic: false\nunderline: false\n",
+        ));
+
+    testenv
+        .command()
+        .args(["--show-style", "title"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_color_depth() {
+    let testenv = TestEnv::new();
+    testenv.write_config("[style.command_name]\nforeground = '#ff8800'");
+    let show_style = |env: &[(&str, &str)], args: &[&str]| {
+        let mut command = testenv.command();
+        command
+            .env_remove("COLORTERM")
+            .env_remove("TERM")
+            .envs(env.iter().copied());
+        command
+            .args(["--color", "always"])
+            .args(args)
+            .args(["--show-style", "command_name"])
+            .assert()
+            .success()
+    };
+
+    show_style(&[("COLORTERM", "truecolor")], &[])
+        .stdout(contains("foreground: RGB(255, 136, 0)\n"));
+    show_style(&[("TERM", "xterm-256color")], &[]).stdout(contains("foreground: Fixed(208)\n"));
+    show_style(&[("TERM", "linux")], &[]).stdout(contains("foreground: Yellow\n"));
+
+    // The flag takes precedence over the detected depth
+    show_style(&[("COLORTERM", "truecolor")], &["--color-depth", "256"])
+        .stdout(contains("foreground: Fixed(208)\n"));
+}
+
+#[test]
+fn test_platform_fallback() {
+    let testenv = TestEnv::new();
+    testenv.add_os_entry("osx", "foo", "# foo\n\n> macOS foo.\n");
+    testenv.add_entry("bar", "# bar\n\n> Common bar.\n");
+    testenv.add_os_entry("osx", "bar", "# bar\n\n> macOS bar.\n");
+    testenv.add_os_entry("linux", "bar", "# bar\n\n> Linux bar.\n");
+    testenv.write_config("[directories]\nplatform_fallback = ['common', 'osx']");
+
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("macOS foo."));
+
+    testenv
+        .command()
+        .args(["--color", "never", "bar"])
+        .assert()
+        .success()
+        .stdout(contains("Common bar."));
+
+    // An explicit platform uses the built-in precedence
+    testenv
+        .command()
+        .args(["--color", "never", "--platform", "linux", "bar"])
+        .assert()
+        .success()
+        .stdout(contains("Linux bar."));
+    testenv
+        .command()
+        .args(["--platform", "linux", "foo"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn test_page_header_style() {
+    let testenv = TestEnv::new();
+    testenv.add_os_entry("linux", "foo", "# foo\n\n> Linux foo.\n");
+
+    // Bold and underlined by default
+    testenv
+        .command()
+        .args(["--color", "always", "--all-platforms", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[1;4mPlatform: linux\x1b[0m"));
+
+    testenv.write_config("[style.page_header]\nforeground = 'red'");
+    testenv
+        .command()
+        .args(["--color", "always", "--all-platforms", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[31mPlatform: linux\x1b[0m"));
+}
+
+#[test]
+fn test_all_platforms() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Common foo.\n");
+    testenv.add_os_entry("osx", "foo", "# foo\n\n> macOS foo.\n");
+    testenv.add_os_entry("linux", "foo", "# foo\n\n> Linux foo.\n");
+    testenv.add_os_entry("linux", "bar", "# bar\n\n> Linux bar.\n");
+
+    testenv
+        .command()
+        .args(["--color", "never", "--quiet", "--all-platforms", "foo"])
+        .assert()
+        .success()
+        .stdout(
+            "Platform: common\n\n  Common foo.\n\n\
+             Platform: linux\n\n  Linux foo.\n\n\
+             Platform: osx\n\n  macOS foo.\n\n",
+        );
+
+    testenv
+        .command()
+        .args(["--color", "never", "--quiet", "--all-platforms", "bar"])
+        .assert()
+        .success()
+        .stdout("Platform: linux\n\n  Linux bar.\n\n");
+
+    testenv
+        .command()
+        .args(["--all-platforms", "baz"])
+        .assert()
+        .failure()
+        .stderr(contains("Page `baz` not found in cache"));
+}
+
+#[test]
+fn test_all_platforms_lookup() {
+    let testenv = TestEnv::new();
+    testenv.add_os_entry("linux", "git-log", "# git log\n\n> Linux git log.\n");
+    testenv.add_os_entry("osx", "git-log", "# git log\n\n> macOS git log.\n");
+    let work_dir = testenv.input_dir.path().join("work");
+    create_dir_all(&work_dir).unwrap();
+    std::fs::write(work_dir.join("deploy.page"), "# deploy\n\n> Work deploy.\n").unwrap();
+    testenv.write_config(format!(
+        "[directories]\ncase_insensitive = true\nnormalize_separators = true\n\n\
+         [[sources]]\nname = 'work'\npath = '{}'\n",
+        work_dir.to_str().unwrap(),
+    ));
+
+    // The page is looked up like without `--all-platforms`
+    testenv
+        .command()
+        .args(["--color", "never", "--quiet", "--all-platforms", "Git_Log"])
+        .assert()
+        .success()
+        .stdout(
+            "Platform: linux\n\n  Linux git log.\n\n\
+             Platform: osx\n\n  macOS git log.\n\n",
+        );
+
+    // A page from a source is returned on its own
+    testenv
+        .command()
+        .args(["--color", "never", "--quiet", "--all-platforms", "deploy"])
+        .assert()
+        .success()
+        .stdout("\n  Work deploy.\n\n");
+}
+
+#[test]
+fn test_platform_tags_merged() {
+    let testenv = TestEnv::new();
+    testenv.write_config("[directories]\nmerge_common = true");
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Do a:\n\n`foo a`\n\n\
+         - Do b: <!-- platform: linux -->\n\n`foo b`\n\n\
+         - Do c: <!-- platform: windows -->\n\n`foo c`\n",
+    );
+    testenv.add_os_entry(
+        "linux",
+        "foo",
+        "# foo\n\n> Foo.\n\n- Do d:\n\n`foo d`\n\n\
+         - Do e: <!-- platform: windows -->\n\n`foo e`\n",
+    );
+
+    let examples = |args: &[&str]| {
+        let output = testenv
+            .command()
+            .args(["--color", "never", "--quiet"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        ["a", "b", "c", "d", "e"]
+            .into_iter()
+            .filter(|example| stdout.contains(&format!("Do {example}:")))
+            .collect::<Vec<_>>()
+    };
+    // The Linux page is merged with the common page
+    assert_eq!(examples(&["--platform", "linux", "foo"]), ["a", "b", "d"]);
+    // There is no Windows page, so only the common page is shown
+    assert_eq!(examples(&["--platform", "windows", "foo"]), ["a", "c"]);
+    assert_eq!(examples(&["--platform", "osx", "foo"]), ["a"]);
+    assert_eq!(
+        examples(&["--all-platforms", "foo"]),
+        ["a", "b", "c", "d", "e"]
+    );
+}
+
+#[test]
+fn test_normalize_separators() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("git-log", "# git log\n\n> Show commits.\n");
+    testenv.add_entry("pg_dump", "# pg_dump\n\n> Dump a database.\n");
+
+    for args in [
+        &["git-log"][..],
+        &["git", "log"],
+        &["git_log"],
+        &["git log"],
+        &["git", "-", "log"],
+    ] {
+        testenv
+            .command()
+            .args(args)
+            .assert()
+            .success()
+            .stdout(contains("Show commits."));
+    }
+    // Names with underscores are still found as they are
+    testenv
+        .command()
+        .args(["pg_dump"])
+        .assert()
+        .success()
+        .stdout(contains("Dump a database."));
+
+    testenv.write_config("[directories]\nnormalize_separators = false");
+    testenv.command().args(["git_log"]).assert().failure();
+    testenv
+        .command()
+        .args(["git", "log"])
+        .assert()
+        .success()
+        .stdout(contains("Show commits."));
+}
+
+#[test]
+fn test_case_insensitive_lookup() {
+    let testenv = TestEnv::new();
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+    testenv.add_entry("git", "# git\n\n> Version control.\n");
+    testenv.add_os_entry("linux", "Xrandr", "# Xrandr\n\n> Configure outputs.\n");
+    testenv.add_page_entry("MyTool", "# MyTool\n\n> Internal tool.\n");
+
+    testenv
+        .command()
+        .args(["GIT"])
+        .assert()
+        .success()
+        .stdout(contains("Version control."));
+    testenv
+        .command()
+        .args(["--platform", "linux", "xrandr"])
+        .assert()
+        .success()
+        .stdout(contains("Configure outputs."));
+    testenv
+        .command()
+        .args(["mytool"])
+        .assert()
+        .success()
+        .stdout(contains("Internal tool."));
+
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'\ncase_insensitive = false",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+    testenv
+        .command()
+        .args(["mytool"])
+        .assert()
+        .failure()
+        .stderr(contains("Page `mytool` not found in cache"));
+}
+
+#[test]
+fn test_not_found_message() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv.write_config(
+        "[display]\nnot_found_message = 'No page for `{{command}}`, see https://example.com'",
+    );
+
+    testenv
+        .command()
+        .args(["--color", "never", "bar"])
+        .assert()
+        .failure()
+        .stderr("No page for `bar`, see https://example.com\n");
+}
+
+#[test]
+fn test_not_found_message_tokens() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("docker", "# docker\n\n> Docker.\n");
+    testenv.add_entry("dock", "# dock\n\n> Dock.\n");
+    testenv.add_entry("tar", "# tar\n\n> Tar.\n");
+    testenv.write_config(
+        "[display]\nnot_found_message = '`{{command}}` not found. Did you mean {{suggestions}}? \
+         See {{docs_url}}. {{team}}'",
+    );
+
+    testenv
+        .command()
+        .args(["--color", "never", "dokcer"])
+        .assert()
+        .failure()
+        .stderr(
+            "Unknown placeholder `{{team}}` in `display.not_found_message`.\n\
+             `dokcer` not found. Did you mean docker? See https://github.com/tldr-pages/tldr. \
+             {{team}}\n",
+        );
+}
+
+#[test]
+fn test_default_command() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("git-log", "# git log\n\n> Show the commit history.\n");
+
+    // Without a default command, the usage is shown
+    testenv
+        .command()
+        .assert()
+        .failure()
+        .stderr(contains("USAGE:"));
+
+    testenv.write_config("[display]\ndefault_command = 'git log'");
+    testenv
+        .command()
+        .assert()
+        .success()
+        .stdout("\n  Show the commit history.\n\n");
+
+    // The command is split like `$PAGER`, respecting quotes
+    testenv.write_config("[display]\ndefault_command = 'git \"log\"'");
+    testenv
+        .command()
+        .assert()
+        .success()
+        .stdout("\n  Show the commit history.\n\n");
+
+    // A config that cannot be loaded is reported
+    testenv.write_config("[display]\ndefault_command = ' '");
+    testenv
+        .command()
+        .assert()
+        .failure()
+        .stderr(contains("ignoring `display.default_command`"))
+        .stderr(contains("`display.default_command` must not be empty"))
+        .stderr(contains("USAGE:"));
+}
+
+#[test]
+fn test_line_numbers() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Create an archive:\n\n`foo -c`\n\n- Extract it:\n\n`foo -x`\n\n- List:\n\n`foo -l`\n",
+    );
+
+    // The gutter is as wide as the largest number
+    testenv
+        .command()
+        .args(["--color", "never", "--line-numbers", "foo"])
+        .assert()
+        .success()
+        .stdout(
+            " 1 │\n 2 │   Foo.\n 3 │\n 4 │   Create an archive:\n 5 │\n 6 │       foo -c\n 7 │\n\
+             \x208 │   Extract it:\n 9 │\n10 │       foo -x\n11 │\n12 │   List:\n13 │\n\
+             14 │       foo -l\n15 │\n",
+        );
+
+    testenv
+        .command()
+        .args([
+            "--color",
+            "always",
+            "--color-depth",
+            "16",
+            "--line-numbers",
+            "foo",
+        ])
+        .assert()
+        .success()
+        .stdout(contains("\x1b[38;5;8m 2 │ \x1b[0m  Foo.\n"));
+}
+
+#[test]
+fn test_align_examples() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Create an archive:\n\n`foo -c`\n\n- List:\n\n`foo -l`\n\n\
+         - Extract an archive into a directory:\n\n`foo -x`\n",
+    );
+    testenv.write_config(
+        "[display]\nalign_examples = true\nmax_align_width = 20\nexample_bullet = '{n}.'",
+    );
+
+    // Texts that are wider than `max_align_width` are not padded
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(
+            "\n  Foo.\n\n  1. Create an archive: foo -c\n\n  2. List:             foo -l\n\n\
+             \x20 3. Extract an archive into a directory: foo -x\n\n",
+        );
+}
+
+#[test]
+fn test_inline_examples() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Create an archive:\n\n`foo -c`\n\n- Extract an archive:\n\n`foo -x`\n",
+    );
+    testenv.write_config("[display]\ninline_examples = true");
+
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Foo.\n\n  Create an archive: foo -c\n\n  Extract an archive: foo -x\n\n");
+
+    testenv.write_config(
+        "[display]\ninline_examples = true\ninline_delimiter = ' → '\ncompact = true",
+    );
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout("  Foo.\n  Create an archive: → foo -c\n  Extract an archive: → foo -x\n\n");
+}
+
+#[test]
+fn test_command_prefix() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Create an archive:\n\n`foo -c`\n\n- Extract an archive:\n\n`foo -x`\n",
+    );
+
+    testenv.write_config("[display]\ncommand_prefix = ''");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(
+            "\n  Foo.\n\n  Create an archive:\n\nfoo -c\n\n  Extract an archive:\n\nfoo -x\n\n",
+        );
+
+    testenv.write_config("[display]\ncommand_prefix = '  $ '");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(
+            "\n  Foo.\n\n  Create an archive:\n\n  $ foo -c\n\n  Extract an archive:\n\n  $ foo -x\n\n",
+        );
+}
+
+#[test]
+fn test_placeholder_case() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Copy a file:\n\n`foo {{source_File}} {{target}}`\n",
+    );
+
+    testenv.write_config("[display]\nplaceholder_case = 'upper'");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Foo.\n\n  Copy a file:\n\n      foo SOURCE_FILE TARGET\n\n");
+
+    // The raw page is not changed
+    testenv
+        .command()
+        .args(["--raw", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("`foo {{source_File}} {{target}}`"));
+}
+
+#[test]
+fn test_reverse_examples() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Do a:\n\n`foo a`\n\n- Do b:\n\n`foo b`\n\n- Do c:\n\n`foo c`\n",
+    );
+
+    testenv
+        .command()
+        .args(["--color", "never", "--reverse", "foo"])
+        .assert()
+        .success()
+        .stdout(
+            "\n  Foo.\n\n  Do c:\n\n      foo c\n\n  Do b:\n\n      foo b\n\n  \
+             Do a:\n\n      foo a\n\n",
+        );
+}
+
+#[test]
+fn test_max_lines() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Do a with a long description:\n\n`foo a`\n",
+    );
+
+    // The page has exactly 7 lines
+    testenv
+        .command()
+        .args(["--color", "never", "--max-lines", "7", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Foo.\n\n  Do a with a long description:\n\n      foo a\n\n");
+
+    testenv
+        .command()
+        .args(["--color", "never", "--max-lines", "4", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Foo.\n\n  ... (truncated)\n");
+
+    // Wrapped lines count as multiple lines
+    testenv.write_config("[display]\noverflow = 'wrap'\nmin_wrap_width = 10");
+    testenv
+        .command()
+        .env("COLUMNS", "20")
+        .args(["--color", "never", "--max-lines", "5", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Foo.\n\n  Do a with a long\n  ... (truncated)\n");
+
+    testenv
+        .command()
+        .args(["--max-lines", "0", "foo"])
+        .assert()
+        .failure()
+        .stderr(contains("The number of lines must be at least 1"));
+}
+
+#[test]
+fn test_max_description_lines() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n> More about foo.\n> More information: <https://example.com>.\n\n\
+         - Do a:\n\n`foo a`\n",
+    );
+
+    testenv.write_config("[display]\nmax_description_lines = 1");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Foo. …\n\n  Do a:\n\n      foo a\n\n");
+}
+
+#[test]
+fn test_show_usage() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Copy a file:\n\n`foo {{[-f|--force]}} {{path/to/source}} {{target_dir}}`\n\n\
+         - Do b:\n\n`foo b`\n",
+    );
+    testenv.add_entry("bar", "# bar\n\n> Bar.\n");
+
+    testenv.write_config("[display]\nshow_usage = true");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout(
+            "\n  Usage: foo [-f|--force] SOURCE TARGET_DIR\n\n  Foo.\n\n  Copy a file:\n\n\
+             \x20     foo [-f|--force] path/to/source target_dir\n\n  Do b:\n\n      foo b\n\n",
+        );
+
+    // Pages without examples have no usage
+    testenv
+        .command()
+        .args(["--color", "never", "bar"])
+        .assert()
+        .success()
+        .stdout("\n  Bar.\n\n");
+}
+
+#[test]
+fn test_example_bullet() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Create an archive:\n\n`foo -c`\n\n- Extract an archive:\n\n`foo -x`\n",
+    );
+
+    testenv.write_config("[display]\nexample_bullet = '•'\ncompact = true");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout("  Foo.\n  • Create an archive:\n      foo -c\n  • Extract an archive:\n      foo -x\n\n");
+
+    testenv.write_config("[display]\nexample_bullet = '{n}.'\ncompact = true");
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .assert()
+        .success()
+        .stdout("  Foo.\n  1. Create an archive:\n      foo -c\n  2. Extract an archive:\n      foo -x\n\n");
+
+    // Wide bullets are taken into account when wrapping
+    testenv.write_config(
+        "[display]\nexample_bullet = '🦀'\ncompact = true\noverflow = 'wrap'\nmin_wrap_width = 0",
+    );
+    testenv
+        .command()
+        .args(["--color", "never", "foo"])
+        .env("COLUMNS", "20")
+        .assert()
+        .success()
+        .stdout("  Foo.\n  🦀 Create an\n  archive:\n      foo -c\n  🦀 Extract an\n  archive:\n      foo -x\n\n");
+}
+
+#[test]
+fn test_grep_examples() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo.\n\n- Create an archive:\n\n`foo -c`\n\n- Extract an archive:\n\n`foo -x`\n\n- List an archive:\n\n`foo -t`\n",
+    );
+
+    testenv
+        .command()
+        .args(["--color", "never", "--grep", "EXTRACT", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Foo.\n\n  Extract an archive:\n\n      foo -x\n\n");
+
+    testenv
+        .command()
+        .args(["--color", "never", "--grep=-t", "foo"])
+        .assert()
+        .success()
+        .stdout(contains("List an archive").and(contains("Create").not()));
+
+    testenv
+        .command()
+        .args(["--color", "never", "--grep", "delete", "foo"])
+        .assert()
+        .success()
+        .stdout("\n  Foo.\n\n")
+        .stderr(contains("No examples match \"delete\"."));
+}
+
+#[test]
+fn test_empty_page_note() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n");
+    testenv.add_entry("bar", "# bar\n\n- Run bar:\n\n`bar`\n");
+
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .success()
+        .stderr(contains("Page exists but has no examples"));
+
+    testenv
+        .command()
+        .args(["bar"])
+        .assert()
+        .success()
+        .stderr(contains("Page exists but has no examples").not());
+
+    testenv
+        .command()
+        .args(["--quiet", "foo"])
+        .assert()
+        .success()
+        .stderr(is_empty());
+
+    testenv.write_config("[display]\nempty_page_note = false");
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .success()
+        .stderr(is_empty());
+}
+
+#[test]
+fn test_spaces_find_command() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(["--update"])
+        .assert()
+        .success()
+        .stderr(contains("Successfully updated cache."));
+
+    testenv
+        .command()
+        .args(["git", "checkout"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_subcommand_fallback() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("git", "# git\n\n> Version control.\n");
+    testenv.add_entry("git-log", "# git log\n\n> Show the history.\n");
+
+    // The hyphenated page is preferred
+    testenv
+        .command()
+        .args(["git", "log"])
+        .assert()
+        .success()
+        .stdout(contains("Show the history."))
+        .stderr(contains("subcommand").not());
+
+    // Otherwise, the page of the main command is shown with a hint
+    testenv
+        .command()
+        .args(["git", "frobnicate"])
+        .assert()
+        .success()
+        .stdout(contains("Version control."))
+        .stderr(contains("`frobnicate` might be a subcommand of `git`"));
+
+    testenv
+        .command()
+        .args(["--quiet", "git", "frobnicate"])
+        .assert()
+        .success()
+        .stderr(contains("subcommand").not());
+
+    testenv
+        .command()
+        .args(["frobnicate", "git"])
+        .assert()
+        .failure()
+        .stderr(contains("Page `frobnicate-git` not found in cache"));
+}
+
+#[test]
+fn test_pager_flag_enable() {
+    let testenv = TestEnv::new();
+
+    testenv
+        .command()
+        .args(["--update"])
+        .assert()
+        .success()
+        .stderr(contains("Successfully updated cache."));
+
+    testenv
+        .command()
+        .args(["--pager", "which"])
+        .assert()
+        .success();
+}
+
+#[test]
+fn test_list_flag_rendering() {
+    let testenv = TestEnv::new();
+
+    // set custom pages directory
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+
+    testenv
+        .command()
+        .args(["--list"])
+        .assert()
+        .failure()
+        .stderr(contains("Page cache not found. Please run `tldr --update`"));
+
+    testenv.add_entry("foo", "");
+
+    testenv
+        .command()
+        .args(["--list"])
+        .assert()
+        .success()
+        .stdout("foo\n");
+
+    testenv.add_entry("bar", "");
+    testenv.add_entry("baz", "");
+    testenv.add_entry("qux", "");
+    testenv.add_page_entry("faz", "");
+    testenv.add_page_entry("bar", "");
+    testenv.add_page_entry("fiz", "");
+    testenv.add_patch_entry("buz", "");
+
+    testenv
+        .command()
+        .args(["--list", "--sorted"])
+        .assert()
+        .success()
+        .stdout("bar\nbaz\nfaz\nfiz\nfoo\nqux\n");
+}
+
+#[test]
+fn test_list_with_description() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("ls", "# ls\n\n> List files.\n> More information.\n");
+    testenv.add_entry("tar", "# tar\n\n> Archiving utility.\n");
+    testenv.add_entry(
+        "a-pathologically-long-command-name",
+        "# a-pathologically-long-command-name\n\n> Long.\n",
+    );
+    testenv.add_entry("zip", "");
+
+    // Overlong names do not throw off the alignment of the other names
+    testenv.write_config("[display]\nmax_list_name_width = 10");
+    testenv
+        .command()
+        .args(["--list", "--with-description"])
+        .assert()
+        .success()
+        .stdout(
+            "a-pathologically-long-command-name\n     Long.\nls   List files.\n\
+             tar  Archiving utility.\nzip\n",
+        );
+}
+
+#[test]
+fn test_list_flag_streaming() {
+    let testenv = TestEnv::new();
 
-    // Set custom pages directory
     testenv.write_config(format!(
         "[directories]\ncustom_pages_dir = '{}'",
         testenv.custom_pages_dir.path().to_str().unwrap()
     ));
+    testenv.add_entry("foo", "");
+    testenv.add_entry("bar", "");
+    testenv.add_os_entry("linux", "foo", "");
+    testenv.add_os_entry("linux", "baz", "");
+    testenv.add_page_entry("qux", "");
+    testenv.add_page_entry("bar", "");
 
-    // Now ensure that this path is contained in the output
-    testenv
+    // Without `--sorted`, the order is unspecified, but every page is listed once
+    let output = testenv
         .command()
-        .args(["--show-paths"])
-        .assert()
-        .success()
-        .stdout(contains(format!(
-            "Custom pages dir: {}",
-            testenv.custom_pages_dir.path().to_str().unwrap(),
-        )));
+        .args(["--list", "--platform", "linux"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    let mut pages: Vec<&str> = std::str::from_utf8(&output.stdout)
+        .unwrap()
+        .lines()
+        .collect();
+    pages.sort_unstable();
+    assert_eq!(pages, ["bar", "baz", "foo", "qux"]);
 }
 
 #[test]
-fn test_os_specific_page() {
+fn test_list_empty_cache() {
     let testenv = TestEnv::new();
 
-    testenv.add_os_entry("sunos", "truss", "contents");
+    // Without a cache, the usual message about the missing cache is printed
+    testenv
+        .command()
+        .args(["--list"])
+        .assert()
+        .failure()
+        .stderr(contains("No pages found").not());
+
+    // An existing cache without pages is reported as well
+    create_dir_all(
+        testenv
+            .cache_dir
+            .path()
+            .join(TLDR_PAGES_DIR)
+            .join("pages")
+            .join("common"),
+    )
+    .unwrap();
+    for args in [
+        &["--list"][..],
+        &["--list", "--sorted"],
+        &["--list", "--with-description"],
+    ] {
+        testenv
+            .command()
+            .args(args)
+            .assert()
+            .failure()
+            .stdout(is_empty())
+            .stderr(contains("No pages found, try `tldr --update`"));
+    }
 
+    // A count of zero is not an error
     testenv
         .command()
-        .args(["--platform", "sunos", "truss"])
+        .args(["--count"])
         .assert()
-        .success();
+        .success()
+        .stdout("0\n");
 }
 
 #[test]
-fn test_markdown_rendering() {
+fn test_list_flag_print0() {
     let testenv = TestEnv::new();
 
-    testenv.add_entry("which", include_str!("which-markdown.expected"));
+    testenv.add_entry("foo", "");
+    testenv.add_entry("bar baz", "");
 
-    let expected = include_str!("which-markdown.expected");
     testenv
         .command()
-        .args(["--raw", "which"])
+        .args(["--list", "--sorted", "-0"])
         .assert()
         .success()
-        .stdout(diff(expected));
+        .stdout("bar baz\0foo\0");
 }
 
-fn _test_correct_rendering(
-    input_file: &str,
-    filename: &str,
-    expected: &'static str,
-    color_option: &str,
-) {
+#[test]
+fn test_search() {
     let testenv = TestEnv::new();
 
-    // Create input file
-    let file_path = testenv.input_dir.path().join(filename);
-    println!("Testfile path: {:?}", file_path);
-    let mut file = File::create(&file_path).unwrap();
-    file.write_all(input_file.as_bytes()).unwrap();
+    testenv.add_entry("tar", "# tar\n\n> Archiving utility.\n");
+    testenv.add_entry("zip", "# zip\n\n> Package and compress (ARCHIVE) files.\n");
+    testenv.add_entry("ls", "# ls\n\n> List directory contents.\n");
+    testenv.add_os_entry("linux", "ar", "# ar\n\n> Create archives.\n");
+    testenv.add_os_entry("osx", "ditto", "# ditto\n\n> Copy archives.\n");
+
+    for concurrency in ["1", "2", "16"] {
+        testenv
+            .command()
+            .args(["--platform", "linux", "--search", "archiv"])
+            .args(["--concurrency", concurrency])
+            .assert()
+            .success()
+            .stdout("ar\ntar\nzip\n");
+    }
 
     testenv
         .command()
-        .args(["--color", color_option, "-f", file_path.to_str().unwrap()])
+        .args(["--search", "nothing matches"])
         .assert()
         .success()
-        .stdout(diff(expected));
+        .stdout(is_empty());
 }
 
-/// An end-to-end integration test for direct file rendering (v1 syntax).
 #[test]
-fn test_correct_rendering_v1() {
-    _test_correct_rendering(
-        include_str!("inkscape-v1.md"),
-        "inkscape-v1.md",
-        include_str!("inkscape-default.expected"),
-        "always",
-    );
-}
+fn test_export() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n\n- Do foo:\n\n`foo`\n");
+    testenv.add_os_entry("linux", "bar", "# bar\n\n> Bar.\n");
+    testenv.add_os_entry("windows", "baz", "# baz\n\n> Baz.\n");
+    let export_path = testenv.cache_dir.path().join("all.md");
 
-/// An end-to-end integration test for direct file rendering (v2 syntax).
-#[test]
-fn test_correct_rendering_v2() {
-    _test_correct_rendering(
-        include_str!("inkscape-v2.md"),
-        "inkscape-v2.md",
-        include_str!("inkscape-default.expected"),
-        "always",
+    testenv
+        .command()
+        .args([
+            "--platform",
+            "linux",
+            "--export",
+            export_path.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stderr(contains("Exported 2 pages to"));
+    assert_eq!(
+        read_to_string(&export_path).unwrap(),
+        "# tldr pages\n\n## bar\n\n> Bar.\n\n## foo\n\n> Foo.\n\n- Do foo:\n\n`foo`\n\n",
     );
 }
 
 #[test]
-/// An end-to-end integration test for direct file rendering with the `--color auto` option. This
-/// will not use styling since output is not stdout.
-fn test_rendering_color_auto() {
-    _test_correct_rendering(
-        include_str!("inkscape-v2.md"),
-        "inkscape-v2.md",
-        include_str!("inkscape-default-no-color.expected"),
-        "auto",
+fn test_export_html() {
+    let testenv = TestEnv::new();
+    testenv.add_entry(
+        "foo",
+        "# foo\n\n> Foo & bar.\n\n- Do foo:\n\n`foo {{file}}`\n",
     );
-}
+    testenv.add_os_entry("windows", "baz", "# baz\n\n> Baz.\n");
+    let export_dir = testenv.cache_dir.path().join("html/pages");
 
-#[test]
-/// An end-to-end integration test for direct file rendering with the `--color never` option.
-fn test_rendering_color_never() {
-    _test_correct_rendering(
-        include_str!("inkscape-v2.md"),
-        "inkscape-v2.md",
-        include_str!("inkscape-default-no-color.expected"),
-        "never",
+    testenv
+        .command()
+        .args([
+            "--platform",
+            "linux",
+            "--export-html",
+            export_dir.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stderr(contains("Exported 1 pages to"));
+    let html = read_to_string(export_dir.join("foo.html")).unwrap();
+    assert!(
+        html.contains("<h1>foo</h1>\n<p>Foo &amp; bar.</p>\n"),
+        "{html}"
+    );
+    assert!(
+        html.contains("<pre><code>foo <var>file</var></code></pre>"),
+        "{html}"
     );
+    assert!(!export_dir.join("baz.html").exists());
+
+    // The export directory cannot be created
+    let file_path = testenv.cache_dir.path().join("file");
+    File::create(&file_path).unwrap();
+    testenv
+        .command()
+        .args(["--export-html", file_path.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stderr(contains("Could not create export directory"));
 }
 
 #[test]
-fn test_rendering_i18n() {
-    _test_correct_rendering(
-        include_str!("chmod.ru.md"),
-        "chmod.ru.md",
-        include_str!("chmod.ru.expected"),
-        "always",
+fn test_explain() {
+    let testenv = TestEnv::new();
+    testenv.write_config(format!(
+        "[directories]\ncustom_pages_dir = '{}'",
+        testenv.custom_pages_dir.path().to_str().unwrap()
+    ));
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv.add_os_entry("linux", "foo", "# foo\n\n> Foo.\n");
+    testenv.add_patch_entry("foo", "- Do foo:\n\n`foo`\n");
+
+    let custom_dir = testenv.custom_pages_dir.path();
+    let pages_dir = testenv.cache_dir.path().join(TLDR_PAGES_DIR);
+    let expected = format!(
+        "missing\t{}\nmissing\t{}\nchosen\t{}\nmissing\t{}\nexists\t{}\npatch\t{}\n",
+        custom_dir.join("foo.page").display(),
+        pages_dir.join("pages.de/linux/foo.md").display(),
+        pages_dir.join("pages/linux/foo.md").display(),
+        pages_dir.join("pages.de/common/foo.md").display(),
+        pages_dir.join("pages/common/foo.md").display(),
+        custom_dir.join("foo.patch").display(),
     );
+    testenv
+        .command()
+        .args(["--explain", "--platform", "linux", "foo"])
+        .env("LANG", "de")
+        .env_remove("LANGUAGE")
+        .assert()
+        .success()
+        .stdout(expected);
+
+    testenv
+        .command()
+        .args(["--explain", "--platform", "linux", "bar"])
+        .env("LANG", "de")
+        .env_remove("LANGUAGE")
+        .assert()
+        .failure()
+        .stdout(contains("chosen").not());
 }
 
-/// An end-to-end integration test for rendering with custom syntax config.
 #[test]
-fn test_correct_rendering_with_config() {
+fn test_show_languages() {
     let testenv = TestEnv::new();
+    testenv.add_entry("foo", "");
+    testenv.add_lang_entry("pages.de", "common", "foo", "");
 
-    // Setup config file
-    // TODO should be config::CONFIG_FILE_NAME
-    let config_file_path = testenv.config_dir.path().join("config.toml");
-    println!("Config path: {:?}", config_file_path);
-
-    let mut config_file = File::create(&config_file_path).unwrap();
-    config_file
-        .write_all(include_bytes!("config.toml"))
-        .unwrap();
-
-    // Create input file
-    let file_path = testenv.input_dir.path().join("inkscape-v2.md");
-    println!("Testfile path: {:?}", file_path);
-
-    let mut file = File::create(&file_path).unwrap();
-    file.write_all(include_bytes!("inkscape-v2.md")).unwrap();
-
-    // Load expected output
-    let expected = include_str!("inkscape-with-config.expected");
+    testenv
+        .command()
+        .args(["--show-languages"])
+        .env("LANG", "de_DE.UTF-8")
+        .env("LANGUAGE", "fr:de")
+        .assert()
+        .success()
+        .stdout("fr (not in cache)\nde\nde_DE (not in cache)\nen\n");
 
     testenv
         .command()
-        .args(["--color", "always", "-f", file_path.to_str().unwrap()])
+        .args(["--show-languages", "--language", "de"])
+        .env("LANG", "fr_FR.UTF-8")
         .assert()
         .success()
-        .stdout(diff(expected));
+        .stdout("de\n");
 }
 
 #[test]
-fn test_spaces_find_command() {
+fn test_lc_all_overrides_lang() {
     let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> English foo.\n");
+    testenv.add_lang_entry("pages.de", "common", "foo", "# foo\n\n> German foo.\n");
 
     testenv
         .command()
-        .args(["--update"])
+        .args(["foo"])
+        .env("LANG", "en_US.UTF-8")
+        .env("LC_ALL", "de_DE.UTF-8")
+        .env_remove("LANGUAGE")
         .assert()
         .success()
-        .stderr(contains("Successfully updated cache."));
+        .stdout(contains("German foo."));
 
     testenv
         .command()
-        .args(["git", "checkout"])
+        .args(["foo"])
+        .env("LANG", "en_US.UTF-8")
+        .env("LC_MESSAGES", "de_DE.UTF-8")
+        .env_remove("LANGUAGE")
         .assert()
-        .success();
+        .success()
+        .stdout(contains("German foo."));
 }
 
 #[test]
-fn test_pager_flag_enable() {
+fn test_count_flag() {
     let testenv = TestEnv::new();
+    testenv.add_entry("foo", "");
+    testenv.add_entry("bar", "");
+    testenv.add_os_entry("linux", "baz", "");
+    testenv.add_os_entry("osx", "qux", "");
+    testenv.add_lang_entry("pages.de", "common", "foo", "");
 
     testenv
         .command()
-        .args(["--update"])
+        .args(["--count", "--platform", "linux"])
         .assert()
         .success()
-        .stderr(contains("Successfully updated cache."));
+        .stdout("3\n");
 
     testenv
         .command()
-        .args(["--pager", "which"])
+        .args(["--count", "--platform", "osx"])
         .assert()
-        .success();
+        .success()
+        .stdout("3\n");
+
+    testenv
+        .command()
+        .args(["--count", "--language", "de"])
+        .assert()
+        .success()
+        .stdout("1\n");
+
+    testenv
+        .command()
+        .args(["--list", "--language", "de"])
+        .assert()
+        .success()
+        .stdout("foo\n");
 }
 
 #[test]
-fn test_list_flag_rendering() {
+fn test_count_flag_missing_cache() {
     let testenv = TestEnv::new();
 
-    // set custom pages directory
-    testenv.write_config(format!(
-        "[directories]\ncustom_pages_dir = '{}'",
-        testenv.custom_pages_dir.path().to_str().unwrap()
-    ));
-
     testenv
         .command()
-        .args(["--list"])
+        .args(["--count"])
         .assert()
         .failure()
-        .stderr(contains("Page cache not found. Please run `tldr --update`"));
+        .stderr(contains("Page cache not found"));
+}
+
+#[test]
+fn test_tree_flag_rendering() {
+    let testenv = TestEnv::new();
 
     testenv.add_entry("foo", "");
+    testenv.add_entry("bar", "");
+    testenv.add_os_entry("linux", "baz", "");
 
     testenv
         .command()
-        .args(["--list"])
+        .args(["--tree"])
         .assert()
         .success()
-        .stdout("foo\n");
+        .stdout("pages/\n├── common/ (2)\n└── linux/ (1)\n");
 
-    testenv.add_entry("bar", "");
-    testenv.add_entry("baz", "");
-    testenv.add_entry("qux", "");
-    testenv.add_page_entry("faz", "");
-    testenv.add_page_entry("bar", "");
-    testenv.add_page_entry("fiz", "");
-    testenv.add_patch_entry("buz", "");
+    testenv
+        .command()
+        .args(["--tree", "--quiet"])
+        .assert()
+        .success()
+        .stdout("pages/\n├── common/\n└── linux/\n");
+}
+
+#[test]
+fn test_autoupdate_offline() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv.write_config("[updates]\nauto_update = true\nauto_update_interval_hours = 0");
 
+    // The automatic update is skipped without trying to download the archive
     testenv
         .command()
         .args(["--list"])
+        .env("TEALDEER_OFFLINE", "1")
         .assert()
         .success()
-        .stdout("bar\nbaz\nfaz\nfiz\nfoo\nqux\n");
+        .stdout("foo\n")
+        .stderr(contains("update").not());
 }
 
 #[test]
@@ -607,6 +3231,38 @@ fn test_autoupdate_cache() {
     check_cache_updated(false);
 }
 
+#[test]
+fn test_auto_clear_cache() {
+    let testenv = TestEnv::new();
+    testenv.add_entry("foo", "# foo\n\n> Foo.\n");
+    testenv.write_config("[updates]\nauto_clear_after_hours = 24");
+    let cache_file_path = testenv.cache_dir.path().join(TLDR_PAGES_DIR);
+
+    // A recently updated cache is kept
+    let new_mtime = SystemTime::now() - Duration::from_secs(82_800);
+    filetime::set_file_mtime(&cache_file_path, new_mtime.into()).unwrap();
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .success()
+        .stderr(contains("clearing").not());
+
+    // An old cache is cleared
+    let new_mtime = SystemTime::now() - Duration::from_secs(90_000);
+    filetime::set_file_mtime(&cache_file_path, new_mtime.into()).unwrap();
+    testenv
+        .command()
+        .args(["foo"])
+        .assert()
+        .failure()
+        .stderr(contains(
+            "The cache has not been updated for more than 24 hours, clearing it.",
+        ))
+        .stderr(contains("Page cache not found"));
+    assert!(!cache_file_path.exists());
+}
+
 /// End-end test to ensure .page files overwrite pages in cache_dir
 #[test]
 fn test_custom_page_overwrites() {
@@ -738,6 +3394,29 @@ fn test_lowercased_page_lookup() {
     testenv.command().args(["eyeD3"]).assert().success();
 }
 
+#[test]
+fn test_inline_language_suffix() {
+    let testenv = TestEnv::new();
+
+    testenv.add_entry("tar", "# tar\n\n> English.\n");
+    testenv.add_lang_entry("pages.de", "common", "tar", "# tar\n\n> Deutsch.\n");
+
+    testenv
+        .command()
+        .args(["--color", "never", "tar@de"])
+        .assert()
+        .success()
+        .stdout(contains("Deutsch."));
+
+    // Unknown languages are treated as part of the command name
+    testenv
+        .command()
+        .args(["--color", "never", "tar@fr"])
+        .assert()
+        .failure()
+        .stderr(contains("Page `tar@fr` not found in cache."));
+}
+
 /// Regression test for #219: It should be possible to combine `--raw` and `-f`.
 #[test]
 fn test_raw_render_file() {